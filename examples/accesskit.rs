@@ -21,7 +21,9 @@ use accesskit::{
 
 use glazier::kurbo::Size;
 
-use glazier::{Application, KbKey, KeyEvent, Region, WinHandler, WindowBuilder, WindowHandle};
+use glazier::{
+    Application, FrameTiming, KbKey, KeyEvent, Region, WinHandler, WindowBuilder, WindowHandle,
+};
 
 const WINDOW_TITLE: &str = "Hello world";
 
@@ -126,7 +128,7 @@ impl WinHandler for HelloState {
         self.handle = handle.clone();
     }
 
-    fn prepare_paint(&mut self) {}
+    fn prepare_paint(&mut self, _: FrameTiming) {}
 
     fn paint(&mut self, _: &Region) {}
 
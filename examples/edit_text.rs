@@ -1,5 +1,4 @@
 use std::any::Any;
-use std::borrow::Cow;
 use std::cell::RefCell;
 use std::ops::Range;
 use std::rc::Rc;
@@ -9,7 +8,6 @@ use accesskit::TreeUpdate;
 use instant::Duration;
 use parley::FontContext;
 use tracing_subscriber::EnvFilter;
-use unicode_segmentation::GraphemeCursor;
 use vello::util::{RenderContext, RenderSurface};
 use vello::{
     kurbo::{Affine, Point, Rect},
@@ -20,13 +18,10 @@ use vello::{AaSupport, Renderer};
 
 use glazier::kurbo::Size;
 use glazier::{
-    text::{
-        Action, Affinity, Direction, Event, HitTestPoint, InputHandler, Movement, Selection,
-        VerticalMovement,
-    },
-    Application, KeyEvent, Region, Scalable, TextFieldToken, WinHandler, WindowHandle,
+    text::{Action, Affinity, Event, HitTestPoint, InputHandler, TextBuffer, TextLayout},
+    Application, FrameTiming, KeyEvent, Region, Scalable, TextFieldToken, WinHandler, WindowHandle,
 };
-use glazier::{HotKey, SysMods, TimerToken};
+use glazier::{HotKey, KillRing, SysMods, TimerToken};
 
 mod common;
 use common::text::{self, ParleyBrush};
@@ -83,8 +78,7 @@ struct WindowState {
     renderer: Option<Renderer>,
     surface: Option<RenderSurface>,
     scene: Scene,
-    size: Size,
-    document: Rc<RefCell<DocumentState>>,
+    document: Rc<RefCell<TextBuffer<ParleyTextLayout>>>,
     text_input_token: Option<TextFieldToken>,
     hotkeys: HotKeys,
 
@@ -92,49 +86,29 @@ struct WindowState {
     cursor_shown: bool,
 }
 
-struct DocumentState {
-    text: String,
-    selection: Selection,
-    composition: Option<Range<usize>>,
+/// Lays out the text of the document with parley, on a single line.
+struct ParleyTextLayout {
     layout: parley::Layout<ParleyBrush>,
     font_context: FontContext,
+    len: usize,
+    window_size: Size,
 }
 
-impl DocumentState {
-    fn update_selection(&mut self) {
-        let next_char_boundary = |mut idx| {
-            if idx > self.text.len() {
-                panic!("Tried to set selection outside of the string")
-            }
-            while !self.text.is_char_boundary(idx) {
-                idx += 1;
-            }
-            idx
-        };
-        self.selection.active = next_char_boundary(self.selection.active);
-        self.selection.anchor = next_char_boundary(self.selection.anchor);
-    }
-}
-
-impl Default for DocumentState {
+impl Default for ParleyTextLayout {
     fn default() -> Self {
-        let mut this = Self {
-            text: "hello world".to_string(),
-            selection: Default::default(),
-            composition: None,
+        Self {
             layout: Default::default(),
             font_context: FontContext::new(),
-        };
-        this.refresh_layout();
-        this
+            len: 0,
+            window_size: Size::new(800.0, 600.0),
+        }
     }
 }
 
-impl DocumentState {
-    fn refresh_layout(&mut self) {
+impl TextLayout for ParleyTextLayout {
+    fn set_text(&mut self, text: &str) {
         let mut lcx = parley::LayoutContext::new();
-        let contents = self.text.to_string();
-        let mut layout_builder = lcx.ranged_builder(&mut self.font_context, &contents, 1.0);
+        let mut layout_builder = lcx.ranged_builder(&mut self.font_context, text, 1.0);
         layout_builder.push_default(&parley::style::StyleProperty::FontSize(FONT_SIZE));
         layout_builder.push_default(&parley::style::StyleProperty::Brush(ParleyBrush(
             Brush::Solid(Color::rgb8(0, 0, 0)),
@@ -142,27 +116,56 @@ impl DocumentState {
         let mut layout = layout_builder.build();
         layout.break_all_lines(None, parley::layout::Alignment::Start);
         self.layout = layout;
+        self.len = text.len();
+    }
+    fn hit_test_point(&self, point: Point) -> HitTestPoint {
+        let cursor = parley::layout::Cursor::from_point(
+            &self.layout,
+            (point.x - TEXT_X) as f32,
+            (point.y - TEXT_Y) as f32,
+        );
+        let idx = match cursor.is_leading() {
+            true => cursor.text_range().start,
+            false => cursor.text_range().end,
+        };
+        HitTestPoint::new(idx, cursor.is_inside())
+    }
+    fn line_range(&self, _char_index: usize, _affinity: Affinity) -> Range<usize> {
+        // we don't have multiple lines, so no matter the input, output is the whole document
+        0..self.len
+    }
+    fn bounding_box(&self) -> Option<Rect> {
+        Some(Rect::new(
+            0.0,
+            0.0,
+            self.window_size.width,
+            self.window_size.height,
+        ))
+    }
+    fn slice_bounding_box(&self, range: Range<usize>) -> Option<Rect> {
+        let range_start = parley::layout::Cursor::from_position(&self.layout, range.start, true);
+        let range_end = parley::layout::Cursor::from_position(&self.layout, range.end, true);
+        Some(Rect::new(
+            range_start.offset() as f64 + TEXT_X,
+            range_start.baseline() as f64 + TEXT_Y - 30.,
+            range_end.offset() as f64 + TEXT_X + 5.0,
+            range_end.baseline() as f64 + TEXT_Y,
+        ))
     }
-}
-
-struct AppInputHandler {
-    state: Rc<RefCell<DocumentState>>,
-    window_size: Size,
-    window_handle: WindowHandle,
 }
 
 impl WindowState {
     pub fn new() -> Self {
         let render = (RenderContext::new()).unwrap();
-        let document: Rc<RefCell<DocumentState>> = Default::default();
+        let mut document = TextBuffer::new("hello world", ParleyTextLayout::default());
+        document.set_kill_ring(Some(KillRing::shared()));
         Self {
             handle: Default::default(),
-            document,
+            document: Rc::new(RefCell::new(document)),
             surface: None,
             render,
             renderer: None,
             scene: Default::default(),
-            size: Size::new(800.0, 600.0),
             text_input_token: None,
             hotkeys: Default::default(),
             cursor_blink_token: None,
@@ -240,19 +243,17 @@ impl WindowState {
             &rect,
         );
         let doc = self.document.borrow();
-        text::render_text(
-            &mut self.scene,
-            Affine::translate((TEXT_X, TEXT_Y)),
-            &doc.layout,
-        );
-        if doc.selection.len() > 0 {
+        let layout = layout().layout;
+        let selection = doc.selection();
+        text::render_text(&mut self.scene, Affine::translate((TEXT_X, TEXT_Y)), layout);
+        if selection.len() > 0 {
             let selection_start_x =
-                parley::layout::Cursor::from_position(&doc.layout, doc.selection.min(), true)
-                    .offset() as f64
+                parley::layout::Cursor::from_position(layout, selection.min(), true).offset()
+                    as f64
                     + TEXT_X;
             let selection_end_x =
-                parley::layout::Cursor::from_position(&doc.layout, doc.selection.max(), true)
-                    .offset() as f64
+                parley::layout::Cursor::from_position(layout, selection.max(), true).offset()
+                    as f64
                     + TEXT_X;
             let rect = Rect::from_points(
                 Point::new(selection_start_x, TEXT_Y),
@@ -268,8 +269,8 @@ impl WindowState {
         }
         if self.cursor_shown {
             let cursor_active_x =
-                parley::layout::Cursor::from_position(&doc.layout, doc.selection.active, true)
-                    .offset() as f64
+                parley::layout::Cursor::from_position(layout, selection.active, true).offset()
+                    as f64
                     + TEXT_X;
             let rect = Rect::from_points(
                 Point::new(cursor_active_x - 1.0, TEXT_Y),
@@ -283,16 +284,13 @@ impl WindowState {
                 &rect,
             );
         }
-        if let Some(composition) = &doc.composition {
-            let composition_start = parley::layout::Cursor::from_position(
-                &doc.layout,
-                composition.start,
-                true,
-            )
-            .offset() as f64
-                + TEXT_X;
+        if let Some(composition) = doc.composition_range() {
+            let composition_start =
+                parley::layout::Cursor::from_position(layout, composition.start, true).offset()
+                    as f64
+                    + TEXT_X;
             let composition_end =
-                parley::layout::Cursor::from_position(&doc.layout, composition.end, true).offset()
+                parley::layout::Cursor::from_position(layout, composition.end, true).offset()
                     as f64
                     + TEXT_X;
             let rect = Rect::from_points(
@@ -327,7 +325,7 @@ impl WinHandler for WindowState {
         self.schedule_render();
     }
 
-    fn prepare_paint(&mut self) {}
+    fn prepare_paint(&mut self, _: FrameTiming) {}
 
     fn paint(&mut self, _: &Region) {
         self.render();
@@ -350,7 +348,7 @@ impl WinHandler for WindowState {
     }
 
     fn size(&mut self, size: Size) {
-        self.size = size;
+        self.document.borrow_mut().layout_mut().window_size = size;
     }
 
     fn request_close(&mut self) {
@@ -366,11 +364,7 @@ impl WinHandler for WindowState {
         _token: TextFieldToken,
         _mutable: bool,
     ) -> Box<dyn InputHandler> {
-        Box::new(AppInputHandler {
-            state: self.document.clone(),
-            window_size: self.size,
-            window_handle: self.handle.clone(),
-        })
+        Box::new(self.document.clone())
     }
 
     fn release_input_lock(&mut self, _token: TextFieldToken) {
@@ -383,31 +377,19 @@ impl WinHandler for WindowState {
         self.cursor_shown = true;
         // Ignore the previous request
         self.cursor_blink_token = Some(self.handle.request_timer(CURSOR_BLINK_INTERVAL));
+        self.handle.request_anim_frame();
     }
 
     fn key_down(&mut self, event: &KeyEvent) -> bool {
         self.schedule_render();
         if self.hotkeys.copy.matches(event) {
-            let doc = self.document.borrow_mut();
-            let text = &doc.text[doc.selection.range()];
-            Application::global().clipboard().put_string(text); // return true prevents the keypress event from being handled as text input
-
+            self.document.borrow_mut().apply_action(Action::Copy);
+            // return true prevents the keypress event from being handled as text input
             return true;
         }
         if self.hotkeys.paste.matches(event) {
             println!("Pasting");
-            let clipboard_contents = Application::global().clipboard().get_string();
-            if let Some(mut contents) = clipboard_contents {
-                contents.retain(|c| c != '\n');
-                {
-                    let mut doc = self.document.borrow_mut();
-                    let selection = doc.selection;
-                    doc.text.replace_range(selection.range(), &contents);
-                    let new_caret_index = selection.min() + contents.len();
-                    doc.selection = Selection::caret(new_caret_index);
-                    doc.refresh_layout();
-                    doc.composition = None;
-                }
+            if self.document.borrow_mut().apply_action(Action::Paste) {
                 // notify the OS that we've updated the selection
                 self.handle
                     .update_text_field(self.text_input_token.unwrap(), Event::Reset);
@@ -419,10 +401,7 @@ impl WinHandler for WindowState {
             return true;
         }
         if self.hotkeys.select_all.matches(event) {
-            {
-                let mut doc = self.document.borrow_mut();
-                doc.selection = Selection::new(0, doc.text.len());
-            }
+            self.document.borrow_mut().apply_action(Action::SelectAll);
             // notify the OS that we've updated the selection
             self.handle
                 .update_text_field(self.text_input_token.unwrap(), Event::SelectionChanged);
@@ -458,181 +437,3 @@ impl WinHandler for WindowState {
         // Ignore the token otherwise, as it's been superceded
     }
 }
-
-impl InputHandler for AppInputHandler {
-    fn selection(&self) -> Selection {
-        self.state.borrow().selection
-    }
-    fn composition_range(&self) -> Option<Range<usize>> {
-        self.state.borrow().composition.clone()
-    }
-    fn set_selection(&mut self, range: Selection) {
-        let mut state = self.state.borrow_mut();
-        state.selection = range;
-        state.update_selection();
-        self.window_handle.request_anim_frame();
-    }
-    fn set_composition_range(&mut self, range: Option<Range<usize>>) {
-        self.state.borrow_mut().composition = range;
-        self.window_handle.request_anim_frame();
-    }
-    fn replace_range(&mut self, range: Range<usize>, text: &str) {
-        let mut doc = self.state.borrow_mut();
-        doc.text.replace_range(range.clone(), text);
-        if doc.selection.anchor < range.start && doc.selection.active < range.start {
-            // no need to update selection
-        } else if doc.selection.anchor > range.end && doc.selection.active > range.end {
-            doc.selection.anchor -= range.len();
-            doc.selection.active -= range.len();
-            doc.selection.anchor += text.len();
-            doc.selection.active += text.len();
-        } else {
-            doc.selection.anchor = range.start + text.len();
-            doc.selection.active = range.start + text.len();
-        }
-        doc.update_selection();
-        doc.refresh_layout();
-        doc.composition = None;
-        self.window_handle.request_anim_frame();
-    }
-    fn slice(&self, range: Range<usize>) -> Cow<str> {
-        self.state.borrow().text[range].to_string().into()
-    }
-    fn is_char_boundary(&self, i: usize) -> bool {
-        self.state.borrow().text.is_char_boundary(i)
-    }
-    fn len(&self) -> usize {
-        self.state.borrow().text.len()
-    }
-    fn hit_test_point(&self, point: Point) -> HitTestPoint {
-        let document = self.state.borrow();
-        let cursor = parley::layout::Cursor::from_point(
-            &document.layout,
-            (point.x - TEXT_X) as f32,
-            (point.y - TEXT_Y) as f32,
-        );
-        let idx = match cursor.is_leading() {
-            true => cursor.text_range().start,
-            false => cursor.text_range().end,
-        };
-        HitTestPoint::new(idx, cursor.is_inside())
-    }
-    fn bounding_box(&self) -> Option<Rect> {
-        Some(Rect::new(
-            0.0,
-            0.0,
-            self.window_size.width,
-            self.window_size.height,
-        ))
-    }
-    fn slice_bounding_box(&self, range: Range<usize>) -> Option<Rect> {
-        let doc = self.state.borrow();
-        let range_start = parley::layout::Cursor::from_position(&doc.layout, range.start, true);
-        let range_end = parley::layout::Cursor::from_position(&doc.layout, range.end, true);
-        Some(Rect::new(
-            range_start.offset() as f64 + TEXT_X,
-            range_start.baseline() as f64 + TEXT_Y - 30.,
-            range_end.offset() as f64 + TEXT_X + 5.0,
-            range_end.baseline() as f64 + TEXT_Y,
-        ))
-    }
-    fn line_range(&self, _char_index: usize, _affinity: Affinity) -> Range<usize> {
-        // we don't have multiple lines, so no matter the input, output is the whole document
-        0..self.state.borrow().text.len()
-    }
-
-    fn handle_action(&mut self, action: Action) {
-        let handled = apply_default_behavior(self, action);
-        println!("action: {:?} handled: {:?}", action, handled);
-    }
-}
-
-fn apply_default_behavior(handler: &mut AppInputHandler, action: Action) -> bool {
-    let is_caret = handler.selection().is_caret();
-    match action {
-        Action::Move(movement) => {
-            let selection = handler.selection();
-            let index = if movement_goes_downstream(movement) {
-                selection.max()
-            } else {
-                selection.min()
-            };
-            let updated_index = if let (false, Movement::Grapheme(_)) = (is_caret, movement) {
-                // handle special cases of pressing left/right when the selection is not a caret
-                index
-            } else {
-                match apply_movement(handler, movement, index) {
-                    Some(v) => v,
-                    None => return false,
-                }
-            };
-            handler.set_selection(Selection::caret(updated_index));
-        }
-        Action::MoveSelecting(movement) => {
-            let mut selection = handler.selection();
-            selection.active = match apply_movement(handler, movement, selection.active) {
-                Some(v) => v,
-                None => return false,
-            };
-            handler.set_selection(selection);
-        }
-        Action::SelectAll => {
-            let len = handler.len();
-            let selection = Selection::new(0, len);
-            handler.set_selection(selection);
-        }
-        Action::Delete(_) if !is_caret => {
-            // movement is ignored for non-caret selections
-            let selection = handler.selection();
-            handler.replace_range(selection.range(), "");
-        }
-        Action::Delete(movement) => {
-            let mut selection = handler.selection();
-            selection.active = match apply_movement(handler, movement, selection.active) {
-                Some(v) => v,
-                None => return false,
-            };
-            handler.replace_range(selection.range(), "");
-        }
-        _ => return false,
-    }
-    true
-}
-
-fn movement_goes_downstream(movement: Movement) -> bool {
-    match movement {
-        Movement::Grapheme(dir) => direction_goes_downstream(dir),
-        Movement::Word(dir) => direction_goes_downstream(dir),
-        Movement::Line(dir) => direction_goes_downstream(dir),
-        Movement::ParagraphEnd => true,
-        Movement::Vertical(VerticalMovement::LineDown) => true,
-        Movement::Vertical(VerticalMovement::PageDown) => true,
-        Movement::Vertical(VerticalMovement::DocumentEnd) => true,
-        _ => false,
-    }
-}
-
-fn direction_goes_downstream(direction: Direction) -> bool {
-    match direction {
-        Direction::Left => false,
-        Direction::Right => true,
-        Direction::Upstream => false,
-        Direction::Downstream => true,
-    }
-}
-
-fn apply_movement(edit_lock: &AppInputHandler, movement: Movement, index: usize) -> Option<usize> {
-    match movement {
-        Movement::Grapheme(dir) => {
-            let doc_len = edit_lock.len();
-            let mut cursor = GraphemeCursor::new(index, doc_len, true);
-            let doc = edit_lock.slice(0..doc_len);
-            if direction_goes_downstream(dir) {
-                cursor.next_boundary(&doc, 0).unwrap()
-            } else {
-                cursor.prev_boundary(&doc, 0).unwrap()
-            }
-        }
-        _ => None,
-    }
-}
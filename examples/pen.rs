@@ -15,8 +15,9 @@ use vello::{AaSupport, Renderer};
 
 use glazier::kurbo::Size;
 use glazier::{
-    Application, Cursor, FileDialogToken, FileInfo, IdleToken, KeyEvent, PenInclination,
-    PointerEvent, PointerId, PointerType, Region, Scalable, TimerToken, WinHandler, WindowHandle,
+    Application, Cursor, FileDialogToken, FileInfo, FrameTiming, IdleToken, KeyEvent,
+    PenInclination, PointerEvent, PointerId, PointerType, Region, Scalable, TimerToken, WinHandler,
+    WindowHandle,
 };
 
 const WIDTH: usize = 2048;
@@ -140,7 +141,7 @@ impl WinHandler for WindowState {
         self.schedule_render();
     }
 
-    fn prepare_paint(&mut self) {}
+    fn prepare_paint(&mut self, _: FrameTiming) {}
 
     fn paint(&mut self, _: &Region) {
         self.render();
@@ -14,8 +14,8 @@ use vello::{AaSupport, Renderer};
 
 use glazier::kurbo::Size;
 use glazier::{
-    Application, Cursor, FileDialogToken, FileInfo, IdleToken, KeyEvent, PointerEvent, Region,
-    Scalable, TimerToken, WinHandler, WindowHandle,
+    Application, Cursor, FileDialogToken, FileInfo, FrameTiming, IdleToken, KeyEvent, PointerEvent,
+    Region, Scalable, TimerToken, WinHandler, WindowHandle,
 };
 
 mod common;
@@ -128,7 +128,7 @@ impl WinHandler for WindowState {
         self.schedule_render();
     }
 
-    fn prepare_paint(&mut self) {}
+    fn prepare_paint(&mut self, _: FrameTiming) {}
 
     fn paint(&mut self, _: &Region) {
         self.render();
@@ -4,7 +4,7 @@ use std::any::Any;
 use accesskit::TreeUpdate;
 
 use glazier::kurbo::Size;
-use glazier::{Application, IdleToken, Region, Scalable, WinHandler, WindowHandle};
+use glazier::{Application, FrameTiming, IdleToken, Region, Scalable, WinHandler, WindowHandle};
 
 const WIDTH: usize = 2048;
 const HEIGHT: usize = 1536;
@@ -179,7 +179,7 @@ impl WinHandler for WindowState {
         self.inner = Some(inner);
     }
 
-    fn prepare_paint(&mut self) {}
+    fn prepare_paint(&mut self, _: FrameTiming) {}
 
     fn paint(&mut self, _: &Region) {
         let inner = self.inner.as_mut().unwrap();
@@ -21,6 +21,10 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use crate::backend::application as backend;
 use crate::clipboard::Clipboard;
 use crate::error::Error;
+use crate::hotkey::HotKey;
+use crate::key_bindings::KeyBindings;
+use crate::keyboard::{Code, KbKey, KeyEvent, Modifiers};
+use crate::text::Action;
 use crate::util;
 
 /// A top-level handler that is not associated with any window.
@@ -40,6 +44,20 @@ pub trait AppHandler {
     /// Called when a menu item is selected.
     #[allow(unused_variables)]
     fn command(&mut self, id: u32) {}
+
+    /// Called when the global hotkey `id` is pressed.
+    ///
+    /// See [`Application::register_global_hotkey`].
+    #[allow(unused_variables)]
+    fn global_hotkey(&mut self, id: u32) {}
+
+    /// Called when the global hotkey `id` turned out not to be registered after all.
+    ///
+    /// This happens on Wayland when the desktop doesn't bind a hotkey which
+    /// [`Application::register_global_hotkey`] already returned for, and on X11 when the
+    /// hotkey can't be grabbed again after the keyboard layout changed, which unregisters it.
+    #[allow(unused_variables)]
+    fn global_hotkey_failed(&mut self, id: u32, error: Error) {}
 }
 
 /// The top level application object.
@@ -55,6 +73,7 @@ pub struct Application {
 /// Platform-independent `Application` state.
 struct State {
     running: bool,
+    key_bindings: KeyBindings,
 }
 
 /// Used to ensure only one Application instance is ever created.
@@ -81,7 +100,10 @@ impl Application {
             .map_err(|_| Error::ApplicationAlreadyExists)?;
         util::claim_main_thread();
         let backend_app = backend::Application::new()?;
-        let state = Rc::new(RefCell::new(State { running: false }));
+        let state = Rc::new(RefCell::new(State {
+            running: false,
+            key_bindings: KeyBindings::default(),
+        }));
         let app = Application { backend_app, state };
         GLOBAL_APP.with(|global_app| {
             *global_app.borrow_mut() = Some(app.clone());
@@ -168,6 +190,70 @@ impl Application {
         self.backend_app.clipboard().into()
     }
 
+    /// The keyboard shortcuts of text fields.
+    ///
+    /// These start out as the [`KeyBindings::default`] of the platform.
+    pub fn key_bindings(&self) -> KeyBindings {
+        self.state.borrow().key_bindings.clone()
+    }
+
+    /// Change the keyboard shortcuts of text fields.
+    ///
+    /// See [`KeyBindings`] for where these apply.
+    pub fn set_key_bindings(&self, key_bindings: KeyBindings) {
+        self.state.borrow_mut().key_bindings = key_bindings;
+    }
+
+    /// The action which the key press `event` performs in text fields.
+    pub(crate) fn key_binding(&self, event: &KeyEvent) -> Option<Action> {
+        self.state.borrow().key_bindings.action(event)
+    }
+
+    /// The key which the physical key `code` produces with `mods` held, in the active
+    /// keyboard layout.
+    ///
+    /// This gives the label of a key which is identified by its position, such as the
+    /// `KeyZ` of a game's "undo" shortcut producing "y" on a German keyboard. Returns `None`
+    /// if the key produces nothing, or if the layout isn't known. This is currently only
+    /// implemented on Linux, where on Wayland the layout is only known once a window has had
+    /// keyboard focus.
+    pub fn key_for_code(&self, code: Code, mods: Modifiers) -> Option<KbKey> {
+        self.backend_app.key_for_code(code, mods)
+    }
+
+    /// The physical key which produces `key` in the active keyboard layout, and the
+    /// modifiers which need to be held for it.
+    ///
+    /// Keys which can be typed without modifiers are preferred over those which need shift.
+    /// Like [`key_for_code`](Self::key_for_code), this is only implemented on Linux.
+    pub fn code_for_key(&self, key: &KbKey) -> Option<(Code, Modifiers)> {
+        self.backend_app.code_for_key(key)
+    }
+
+    /// Register a global hotkey, which calls [`AppHandler::global_hotkey`] with `id` when
+    /// `hotkey` is pressed, even while another application has focus.
+    ///
+    /// Registering another hotkey with the same `id` replaces the previous one. This fails if
+    /// the hotkey is taken, such as by another application, or if the platform has no global
+    /// hotkeys.
+    ///
+    /// This is currently implemented on X11, and on Wayland through the GlobalShortcuts
+    /// interface of the desktop portal. On X11 the hotkey is grabbed on whichever key produces
+    /// it in the active layout, and grabbed again when the layout changes. On Wayland the
+    /// desktop may ask the user to confirm the shortcut, or to choose another trigger for it,
+    /// so this returns without waiting for the desktop, and failures to bind the hotkey are
+    /// reported to [`AppHandler::global_hotkey_failed`] instead.
+    pub fn register_global_hotkey(&self, id: u32, hotkey: &HotKey) -> Result<(), Error> {
+        Ok(self.backend_app.register_global_hotkey(id, hotkey)?)
+    }
+
+    /// Stop listening for the global hotkey `id`.
+    ///
+    /// Nothing happens if no hotkey is registered with `id`.
+    pub fn unregister_global_hotkey(&self, id: u32) -> Result<(), Error> {
+        Ok(self.backend_app.unregister_global_hotkey(id)?)
+    }
+
     /// Returns the current locale string.
     ///
     /// This should be a [Unicode language identifier].
@@ -2,7 +2,7 @@
 use crate::backend::wayland;
 #[cfg(feature = "x11")]
 use crate::backend::x11;
-use crate::AppHandler;
+use crate::{AppHandler, Code, HotKey, KbKey, Modifiers};
 
 use super::clipboard::Clipboard;
 
@@ -51,6 +51,42 @@ impl Application {
         }
     }
 
+    pub fn key_for_code(&self, code: Code, mods: Modifiers) -> Option<KbKey> {
+        match self {
+            #[cfg(feature = "x11")]
+            Application::X11(app) => app.key_for_code(code, mods),
+            #[cfg(feature = "wayland")]
+            Application::Wayland(app) => app.key_for_code(code, mods),
+        }
+    }
+
+    pub fn code_for_key(&self, key: &KbKey) -> Option<(Code, Modifiers)> {
+        match self {
+            #[cfg(feature = "x11")]
+            Application::X11(app) => app.code_for_key(key),
+            #[cfg(feature = "wayland")]
+            Application::Wayland(app) => app.code_for_key(key),
+        }
+    }
+
+    pub fn register_global_hotkey(&self, id: u32, hotkey: &HotKey) -> Result<(), anyhow::Error> {
+        match self {
+            #[cfg(feature = "x11")]
+            Application::X11(app) => app.register_global_hotkey(id, hotkey),
+            #[cfg(feature = "wayland")]
+            Application::Wayland(app) => app.register_global_hotkey(id, hotkey),
+        }
+    }
+
+    pub fn unregister_global_hotkey(&self, id: u32) -> Result<(), anyhow::Error> {
+        match self {
+            #[cfg(feature = "x11")]
+            Application::X11(app) => app.unregister_global_hotkey(id),
+            #[cfg(feature = "wayland")]
+            Application::Wayland(app) => app.unregister_global_hotkey(id),
+        }
+    }
+
     pub fn get_locale() -> String {
         let app = crate::Application::try_global().unwrap();
         match &app.backend_app {
@@ -9,8 +9,11 @@ use crate::backend::wayland;
 #[cfg(feature = "x11")]
 use crate::backend::x11;
 use crate::{
-    text::Event, Cursor, CursorDesc, Error, FileDialogOptions, FileDialogToken, IdleToken, Scale,
-    TextFieldToken, TimerToken, WinHandler, WindowLevel, WindowState,
+    text::{Event, InputProperties},
+    ClipboardFormat, Code, Cursor, CursorDesc, DragActions, DragImage, DragToken, Error,
+    FileDialogOptions, FileDialogToken, IdleToken, KeyboardLayouts, Modifiers, PointerButtons,
+    PointerConstraint, PointerId, Scale, TextFieldToken, TimerToken, WinHandler, WindowLevel,
+    WindowState,
 };
 
 use super::{application::Application, menu::Menu};
@@ -564,6 +567,20 @@ impl WindowHandle {
         }
     }
 
+    pub fn set_text_field_properties(&self, token: TextFieldToken, properties: InputProperties) {
+        match self {
+            #[cfg(feature = "x11")]
+            WindowHandle::X11(handle) => {
+                handle.set_text_field_properties(token, properties);
+            }
+            #[cfg(feature = "wayland")]
+            WindowHandle::Wayland(handle) => {
+                handle.set_text_field_properties(token, properties);
+            }
+            WindowHandle::None => panic!("Used an uninitialised WindowHandle"),
+        }
+    }
+
     pub fn update_text_field(&self, token: TextFieldToken, update: Event) {
         match self {
             #[cfg(feature = "x11")]
@@ -658,6 +675,111 @@ impl WindowHandle {
         }
     }
 
+    pub fn start_drag(
+        &self,
+        formats: &[ClipboardFormat],
+        actions: DragActions,
+        image: Option<DragImage>,
+    ) -> Option<DragToken> {
+        match self {
+            #[cfg(feature = "x11")]
+            WindowHandle::X11(handle) => handle.start_drag(formats, actions, image),
+            #[cfg(feature = "wayland")]
+            WindowHandle::Wayland(handle) => handle.start_drag(formats, actions, image),
+            WindowHandle::None => panic!("Used an uninitialised WindowHandle"),
+        }
+    }
+
+    pub fn capture_pointer(&self, pointer_id: PointerId) {
+        match self {
+            #[cfg(feature = "x11")]
+            WindowHandle::X11(handle) => handle.capture_pointer(pointer_id),
+            #[cfg(feature = "wayland")]
+            WindowHandle::Wayland(handle) => handle.capture_pointer(pointer_id),
+            WindowHandle::None => panic!("Used an uninitialised WindowHandle"),
+        }
+    }
+
+    pub fn release_pointer_capture(&self, pointer_id: PointerId) {
+        match self {
+            #[cfg(feature = "x11")]
+            WindowHandle::X11(handle) => handle.release_pointer_capture(pointer_id),
+            #[cfg(feature = "wayland")]
+            WindowHandle::Wayland(handle) => handle.release_pointer_capture(pointer_id),
+            WindowHandle::None => panic!("Used an uninitialised WindowHandle"),
+        }
+    }
+
+    pub fn set_pointer_constraint(&self, constraint: PointerConstraint) {
+        match self {
+            #[cfg(feature = "x11")]
+            WindowHandle::X11(handle) => handle.set_pointer_constraint(constraint),
+            #[cfg(feature = "wayland")]
+            WindowHandle::Wayland(handle) => handle.set_pointer_constraint(constraint),
+            WindowHandle::None => panic!("Used an uninitialised WindowHandle"),
+        }
+    }
+
+    pub fn set_cursor_visible(&self, visible: bool) {
+        match self {
+            #[cfg(feature = "x11")]
+            WindowHandle::X11(handle) => handle.set_cursor_visible(visible),
+            #[cfg(feature = "wayland")]
+            WindowHandle::Wayland(handle) => handle.set_cursor_visible(visible),
+            WindowHandle::None => panic!("Used an uninitialised WindowHandle"),
+        }
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        match self {
+            #[cfg(feature = "x11")]
+            WindowHandle::X11(handle) => handle.modifiers(),
+            #[cfg(feature = "wayland")]
+            WindowHandle::Wayland(handle) => handle.modifiers(),
+            WindowHandle::None => panic!("Used an uninitialised WindowHandle"),
+        }
+    }
+
+    pub fn pressed_keys(&self) -> Vec<Code> {
+        match self {
+            #[cfg(feature = "x11")]
+            WindowHandle::X11(handle) => handle.pressed_keys(),
+            #[cfg(feature = "wayland")]
+            WindowHandle::Wayland(handle) => handle.pressed_keys(),
+            WindowHandle::None => panic!("Used an uninitialised WindowHandle"),
+        }
+    }
+
+    pub fn keyboard_layouts(&self) -> KeyboardLayouts {
+        match self {
+            #[cfg(feature = "x11")]
+            WindowHandle::X11(handle) => handle.keyboard_layouts(),
+            #[cfg(feature = "wayland")]
+            WindowHandle::Wayland(handle) => handle.keyboard_layouts(),
+            WindowHandle::None => panic!("Used an uninitialised WindowHandle"),
+        }
+    }
+
+    pub fn pointer_buttons(&self) -> PointerButtons {
+        match self {
+            #[cfg(feature = "x11")]
+            WindowHandle::X11(handle) => handle.pointer_buttons(),
+            #[cfg(feature = "wayland")]
+            WindowHandle::Wayland(handle) => handle.pointer_buttons(),
+            WindowHandle::None => panic!("Used an uninitialised WindowHandle"),
+        }
+    }
+
+    pub fn pointer_position(&self) -> Option<Point> {
+        match self {
+            #[cfg(feature = "x11")]
+            WindowHandle::X11(handle) => handle.pointer_position(),
+            #[cfg(feature = "wayland")]
+            WindowHandle::Wayland(handle) => handle.pointer_position(),
+            WindowHandle::None => panic!("Used an uninitialised WindowHandle"),
+        }
+    }
+
     pub fn get_idle_handle(&self) -> Option<IdleHandle> {
         match self {
             #[cfg(feature = "x11")]
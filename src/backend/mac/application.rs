@@ -30,6 +30,7 @@ use objc::{class, msg_send, sel, sel_impl};
 
 use crate::application::AppHandler;
 use crate::common_util::{shared_queue, SharedDequeuer, SharedEnqueuer};
+use crate::{Code, HotKey, KbKey, Modifiers};
 
 use super::clipboard::Clipboard;
 use super::error::Error;
@@ -118,6 +119,26 @@ impl Application {
         Clipboard
     }
 
+    pub fn key_for_code(&self, _code: Code, _mods: Modifiers) -> Option<KbKey> {
+        tracing::warn!("Application::key_for_code is currently unimplemented for Mac.");
+        None
+    }
+
+    pub fn code_for_key(&self, _key: &KbKey) -> Option<(Code, Modifiers)> {
+        tracing::warn!("Application::code_for_key is currently unimplemented for Mac.");
+        None
+    }
+
+    pub fn register_global_hotkey(&self, _id: u32, _hotkey: &HotKey) -> Result<(), anyhow::Error> {
+        Err(anyhow::anyhow!(
+            "Application::register_global_hotkey is currently unimplemented for Mac."
+        ))
+    }
+
+    pub fn unregister_global_hotkey(&self, _id: u32) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+
     pub fn get_locale() -> String {
         unsafe {
             let nslocale_class = class!(NSLocale);
@@ -332,6 +332,7 @@ impl KeyboardState {
                 mods,
                 repeat,
                 is_composing,
+                time: None,
             };
             Some(event)
         }
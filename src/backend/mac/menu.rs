@@ -35,14 +35,14 @@ fn make_menu_item(
     selected: Option<bool>,
     enabled: bool,
 ) -> id {
-    let key_equivalent = key.map(HotKey::key_equivalent).unwrap_or("");
+    let key_equivalent = key.map(HotKey::key_equivalent).unwrap_or_default();
     let stripped_text = strip_access_key(text);
     unsafe {
         let item = NSMenuItem::alloc(nil)
             .initWithTitle_action_keyEquivalent_(
                 make_nsstring(&stripped_text),
                 sel!(handleMenuItem:),
-                make_nsstring(key_equivalent),
+                make_nsstring(&key_equivalent),
             )
             .autorelease();
 
@@ -118,8 +118,9 @@ impl HotKey {
     /// objects.
     ///
     /// Returns the empty string if no key equivalent is known.
-    fn key_equivalent(&self) -> &str {
-        match &self.key {
+    fn key_equivalent(&self) -> String {
+        let key = self.display_key();
+        let equivalent: &str = match &key {
             KbKey::Character(t) => t,
 
             // from NSText.h
@@ -162,7 +163,8 @@ impl HotKey {
                 eprintln!("no key equivalent for {self:?}");
                 ""
             }
-        }
+        };
+        equivalent.to_string()
     }
 
     fn key_modifier_mask(&self) -> NSEventModifierFlags {
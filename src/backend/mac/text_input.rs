@@ -26,6 +26,7 @@ use std::ops::Range;
 use std::os::raw::c_uchar;
 
 use super::window::with_edit_lock_from_window;
+use crate::kill_ring::KillRing;
 use crate::kurbo::Point;
 use crate::text::{
     Action, Affinity, Direction, InputHandler, Movement, Selection, VerticalMovement,
@@ -284,14 +285,16 @@ fn do_command_by_selector_impl(mut edit_lock: Box<dyn InputHandler>, cmd: Sel) {
             edit_lock.handle_action(Action::Delete(Movement::Line(Direction::Upstream)))
         }
         "deleteToBeginningOfParagraph:" => {
-            // TODO(lord): this seems to also kill the text into a buffer that can get pasted with yank
+            // this also kills the text, for yank: to insert again
+            record_kill(&mut edit_lock, Movement::ParagraphStart);
             edit_lock.handle_action(Action::Delete(Movement::ParagraphStart))
         }
         "deleteToEndOfLine:" => {
             edit_lock.handle_action(Action::Delete(Movement::Line(Direction::Downstream)))
         }
         "deleteToEndOfParagraph:" => {
-            // TODO(lord): this seems to also kill the text into a buffer that can get pasted with yank
+            // this also kills the text, for yank: to insert again
+            record_kill(&mut edit_lock, Movement::ParagraphEnd);
             edit_lock.handle_action(Action::Delete(Movement::ParagraphEnd))
         }
         "deleteWordBackward:" => {
@@ -577,11 +580,17 @@ fn do_command_by_selector_impl(mut edit_lock: Box<dyn InputHandler>, cmd: Sel) {
             edit_lock.handle_action(Action::InsertSingleQuoteIgnoringSmartQuotes)
         }
         "cancelOperation:" => edit_lock.handle_action(Action::Cancel),
-        // "deleteToMark:" => {}          // TODO(lord): selectToMark, then delete selection. also puts selection in yank buffer
-        // "selectToMark:" => {}          // TODO(lord): extends the selection to include the mark
-        // "setMark:" => {}               // TODO(lord): remembers index in document (but what about grapheme clusters??)
-        // "swapWithMark:" => {}          // TODO(lord): swaps current and stored mark indices
-        // "yank:" => {}                  // TODO(lord): triggered with control-y, inserts in the text previously killed deleteTo*OfParagraph
+        "undo:" => edit_lock.handle_action(Action::Undo),
+        "redo:" => edit_lock.handle_action(Action::Redo),
+        "cut:" => edit_lock.handle_action(Action::Cut),
+        "copy:" => edit_lock.handle_action(Action::Copy),
+        "paste:" => edit_lock.handle_action(Action::Paste),
+        "pasteAsPlainText:" => edit_lock.handle_action(Action::PasteAsPlainText),
+        "deleteToMark:" => edit_lock.handle_action(Action::DeleteToMark),
+        "selectToMark:" => edit_lock.handle_action(Action::SelectToMark),
+        "setMark:" => edit_lock.handle_action(Action::SetMark),
+        "swapWithMark:" => edit_lock.handle_action(Action::SwapWithMark),
+        "yank:" => edit_lock.handle_action(Action::Yank),
         "transposeWords:" => {} // textedit doesn't support, so neither will we
         "changeCaseOfLetter:" => {} // textedit doesn't support, so neither will we
         "indent:" => {}         // textedit doesn't support, so neither will we
@@ -620,6 +629,17 @@ fn decode_nsrange(
     Some(start_utf8..end_utf8)
 }
 
+// Adds the text which deleting with `movement` is about to remove to the shared kill ring.
+//
+// Cocoa's paragraph deletions are kills, but handlers still get them as `Action::Delete`, so
+// that handlers which don't keep a kill ring keep deleting the text.
+// Repeated deletions from the same place join, like repeated `Action::Kill`s.
+fn record_kill(edit_lock: &mut Box<dyn InputHandler>, movement: Movement) {
+    KillRing::shared()
+        .borrow_mut()
+        .record_kill(&mut **edit_lock, movement);
+}
+
 // Encodes the UTF-8 `Range<usize>` into a UTF-16 `NSRange`.
 fn encode_nsrange(edit_lock: &mut Box<dyn InputHandler>, mut range: Range<usize>) -> NSRange {
     while !edit_lock.is_char_boundary(range.start) {
@@ -57,18 +57,23 @@ use super::keyboard::{make_modifiers, KeyboardState};
 use super::menu::Menu;
 use super::text_input::NSRange;
 use super::util::{assert_main_thread, make_nsstring};
+use crate::clipboard::ClipboardFormat;
 use crate::common_util::IdleCallback;
 use crate::dialog::{FileDialogOptions, FileDialogType};
+use crate::drag::{DragActions, DragImage, DragToken};
+use crate::keyboard::{Code, KeyboardLayouts, Modifiers};
 use crate::keyboard_types::KeyState;
 use crate::mouse::{Cursor, CursorDesc};
 use crate::pointer::{
-    MouseInfo, PointerButton, PointerButtons, PointerEvent, PointerId, PointerType,
+    MouseInfo, PointerButton, PointerButtons, PointerConstraint, PointerEvent, PointerId,
+    PointerType, ScrollDeltaUnit, ScrollPhase, ScrollSource,
 };
 use crate::region::Region;
 use crate::scale::Scale;
-use crate::text::{Event, InputHandler};
+use crate::text::{Event, InputHandler, InputProperties};
 use crate::window::{
-    FileDialogToken, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowLevel, WindowState,
+    FileDialogToken, FrameTiming, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowLevel,
+    WindowState,
 };
 use crate::Error;
 
@@ -698,7 +703,7 @@ fn mouse_pointer_event(
     count: u8,
     focus: bool,
     button: PointerButton,
-    wheel_delta: Vec2,
+    mouse_info: MouseInfo,
 ) -> PointerEvent {
     unsafe {
         let point = nsevent.locationInWindow();
@@ -706,13 +711,16 @@ fn mouse_pointer_event(
         PointerEvent {
             pointer_id: PointerId(0),
             is_primary: true,
-            pointer_type: PointerType::Mouse(MouseInfo { wheel_delta }),
+            pointer_type: PointerType::Mouse(mouse_info),
+            time: None,
             pos: Point::new(view_point.x, view_point.y),
             buttons: get_mouse_buttons(NSEvent::pressedMouseButtons(nsevent)),
             modifiers: make_modifiers(nsevent.modifierFlags()),
             button,
             focus,
             count,
+            coalesced: Vec::new(),
+            predicted: Vec::new(),
         }
     }
 }
@@ -800,7 +808,14 @@ fn mouse_down(this: &mut Object, nsevent: id, button: PointerButton) {
         let view_state = &mut *(view_state as *mut ViewState);
         let count = nsevent.clickCount() as u8;
         let focus = view_state.focus_click && button == PointerButton::Primary;
-        let event = mouse_pointer_event(nsevent, this as id, count, focus, button, Vec2::ZERO);
+        let event = mouse_pointer_event(
+            nsevent,
+            this as id,
+            count,
+            focus,
+            button,
+            MouseInfo::default(),
+        );
         view_state.handler.pointer_down(&event);
     }
 }
@@ -831,7 +846,8 @@ fn mouse_up(this: &mut Object, nsevent: id, button: PointerButton) {
         } else {
             false
         };
-        let event = mouse_pointer_event(nsevent, this as id, 0, focus, button, Vec2::ZERO);
+        let event =
+            mouse_pointer_event(nsevent, this as id, 0, focus, button, MouseInfo::default());
         view_state.handler.pointer_up(&event);
         // If we have already received a mouseExited event then that means
         // we're still receiving mouse events because some buttons are being held down.
@@ -854,7 +870,7 @@ extern "C" fn mouse_move(this: &mut Object, _: Sel, nsevent: id) {
             0,
             false,
             PointerButton::None,
-            Vec2::ZERO,
+            MouseInfo::default(),
         );
         view_state.handler.pointer_move(&event);
     }
@@ -865,7 +881,14 @@ extern "C" fn mouse_enter(this: &mut Object, _sel: Sel, nsevent: id) {
         let view_state: *mut c_void = *this.get_ivar("viewState");
         let view_state = &mut *(view_state as *mut ViewState);
         view_state.mouse_left = false;
-        let event = mouse_pointer_event(nsevent, this, 0, false, PointerButton::None, Vec2::ZERO);
+        let event = mouse_pointer_event(
+            nsevent,
+            this,
+            0,
+            false,
+            PointerButton::None,
+            MouseInfo::default(),
+        );
         view_state.handler.pointer_move(&event);
     }
 }
@@ -883,14 +906,31 @@ extern "C" fn scroll_wheel(this: &mut Object, _: Sel, nsevent: id) {
     unsafe {
         let view_state: *mut c_void = *this.get_ivar("viewState");
         let view_state = &mut *(view_state as *mut ViewState);
-        let (dx, dy) = {
-            let dx = -nsevent.scrollingDeltaX();
-            let dy = -nsevent.scrollingDeltaY();
-            if nsevent.hasPreciseScrollingDeltas() == cocoa::base::YES {
-                (dx, dy)
+        let wheel_delta = Vec2::new(-nsevent.scrollingDeltaX(), -nsevent.scrollingDeltaY());
+        let phase: NSUInteger = msg_send![nsevent, phase];
+        let momentum: NSUInteger = msg_send![nsevent, momentumPhase];
+        let (scroll_phase, momentum_phase) = (scroll_phase(phase), scroll_phase(momentum));
+        let precise = nsevent.hasPreciseScrollingDeltas() == cocoa::base::YES;
+        let mouse_info = MouseInfo {
+            wheel_delta,
+            // Imprecise deltas are in lines, possibly accelerated
+            wheel_delta_unit: if precise {
+                ScrollDeltaUnit::Pixels
             } else {
-                (dx * 32.0, dy * 32.0)
-            }
+                ScrollDeltaUnit::Lines
+            },
+            scroll_phase,
+            momentum_phase,
+            // Only touchpads (and the Magic Mouse) report phases
+            scroll_source: if scroll_phase != ScrollPhase::None
+                || momentum_phase != ScrollPhase::None
+            {
+                ScrollSource::Finger
+            } else if precise {
+                ScrollSource::Continuous
+            } else {
+                ScrollSource::Wheel
+            },
         };
 
         let event = mouse_pointer_event(
@@ -899,12 +939,31 @@ extern "C" fn scroll_wheel(this: &mut Object, _: Sel, nsevent: id) {
             0,
             false,
             PointerButton::None,
-            Vec2::new(dx, dy),
+            mouse_info,
         );
         view_state.handler.wheel(&event);
     }
 }
 
+/// Convert an `NSEventPhase`.
+fn scroll_phase(phase: NSUInteger) -> ScrollPhase {
+    // From NSEvent.h
+    const NS_EVENT_PHASE_BEGAN: NSUInteger = 1 << 0;
+    const NS_EVENT_PHASE_STATIONARY: NSUInteger = 1 << 1;
+    const NS_EVENT_PHASE_CHANGED: NSUInteger = 1 << 2;
+    const NS_EVENT_PHASE_ENDED: NSUInteger = 1 << 3;
+    const NS_EVENT_PHASE_CANCELLED: NSUInteger = 1 << 4;
+    if phase & NS_EVENT_PHASE_BEGAN != 0 {
+        ScrollPhase::Began
+    } else if phase & (NS_EVENT_PHASE_CHANGED | NS_EVENT_PHASE_STATIONARY) != 0 {
+        ScrollPhase::Changed
+    } else if phase & (NS_EVENT_PHASE_ENDED | NS_EVENT_PHASE_CANCELLED) != 0 {
+        ScrollPhase::Ended
+    } else {
+        ScrollPhase::None
+    }
+}
+
 extern "C" fn pinch_event(this: &mut Object, _: Sel, nsevent: id) {
     unsafe {
         let view_state: *mut c_void = *this.get_ivar("viewState");
@@ -959,7 +1018,8 @@ extern "C" fn view_will_draw(this: &mut Object, _: Sel) {
     unsafe {
         let view_state: *mut c_void = *this.get_ivar("viewState");
         let view_state = &mut *(view_state as *mut ViewState);
-        view_state.handler.prepare_paint();
+        // TODO: report frame timing, using CVDisplayLink
+        view_state.handler.prepare_paint(FrameTiming::default());
     }
 }
 
@@ -1286,6 +1346,10 @@ impl WindowHandle {
         }
     }
 
+    pub fn set_text_field_properties(&self, _token: TextFieldToken, _properties: InputProperties) {
+        // The text input client doesn't report a content type yet, so there's nothing to update
+    }
+
     pub fn update_text_field(&self, token: TextFieldToken, update: Event) {
         unsafe {
             if let Some(view) = self.nsview.load().as_ref() {
@@ -1519,12 +1583,63 @@ impl WindowHandle {
         }
     }
 
+    pub fn start_drag(
+        &self,
+        _formats: &[ClipboardFormat],
+        _actions: DragActions,
+        _image: Option<DragImage>,
+    ) -> Option<DragToken> {
+        tracing::warn!("WindowHandle::start_drag is currently unimplemented for Mac.");
+        None
+    }
+
+    pub fn capture_pointer(&self, _pointer_id: PointerId) {
+        tracing::warn!("WindowHandle::capture_pointer is currently unimplemented for Mac.");
+    }
+
+    pub fn release_pointer_capture(&self, _pointer_id: PointerId) {
+        tracing::warn!("WindowHandle::release_pointer_capture is currently unimplemented for Mac.");
+    }
+
+    pub fn set_pointer_constraint(&self, _constraint: PointerConstraint) {
+        tracing::warn!("WindowHandle::set_pointer_constraint is currently unimplemented for Mac.");
+    }
+
+    pub fn set_cursor_visible(&self, _visible: bool) {
+        tracing::warn!("WindowHandle::set_cursor_visible is currently unimplemented for Mac.");
+    }
+
     fn defer(&self, op: DeferredOp) {
         if let Some(i) = self.get_idle_handle() {
             i.add_idle(IdleKind::DeferredOp(op))
         }
     }
 
+    pub fn modifiers(&self) -> Modifiers {
+        tracing::warn!("WindowHandle::modifiers is currently unimplemented for Mac.");
+        Modifiers::empty()
+    }
+
+    pub fn pressed_keys(&self) -> Vec<Code> {
+        tracing::warn!("WindowHandle::pressed_keys is currently unimplemented for Mac.");
+        Vec::new()
+    }
+
+    pub fn keyboard_layouts(&self) -> KeyboardLayouts {
+        tracing::warn!("WindowHandle::keyboard_layouts is currently unimplemented for Mac.");
+        KeyboardLayouts::default()
+    }
+
+    pub fn pointer_buttons(&self) -> PointerButtons {
+        tracing::warn!("WindowHandle::pointer_buttons is currently unimplemented for Mac.");
+        PointerButtons::new()
+    }
+
+    pub fn pointer_position(&self) -> Option<Point> {
+        tracing::warn!("WindowHandle::pointer_position is currently unimplemented for Mac.");
+        None
+    }
+
     /// Get a handle that can be used to schedule an idle task.
     pub fn get_idle_handle(&self) -> Option<IdleHandle> {
         if self.nsview.load().is_null() {
@@ -0,0 +1,19 @@
+//! AccessKit support shared by the X11 and Wayland backends.
+
+use accesskit::{NodeBuilder, NodeClassSet, NodeId, Role, Tree, TreeUpdate};
+
+/// A tree of just the window, to create an `accesskit_unix` adapter with.
+///
+/// The adapter takes its initial tree from a `'static` callback, which can't reach the
+/// `WinHandler`. It only calls back when the AT-SPI bus is running, and the backends then
+/// replace this tree with the handler's, so that handlers only build their trees when
+/// assistive technologies can read them.
+pub(crate) fn placeholder_tree() -> TreeUpdate {
+    let root = NodeId(0);
+    let window = NodeBuilder::new(Role::Window).build(&mut NodeClassSet::new());
+    TreeUpdate {
+        nodes: vec![(root, window)],
+        tree: Some(Tree::new(root)),
+        focus: root,
+    }
+}
@@ -0,0 +1,103 @@
+//! Turning the millisecond timestamps of X11 and Wayland input events into `Instant`s.
+
+use std::time::Duration;
+
+use instant::Instant;
+use nix::time::{clock_gettime, ClockId};
+
+/// How old an event can plausibly be when we receive it. Timestamps which are further from
+/// the current time than this are assumed to be on some other clock.
+const MAX_AGE: Duration = Duration::from_secs(60);
+
+/// Converts the 32-bit millisecond timestamps of input events to `Instant`s.
+///
+/// Both X11 and Wayland leave the base of their timestamps unspecified, although in practice
+/// they are almost always `CLOCK_MONOTONIC`, which is also what `Instant` uses. We use that
+/// clock for as long as the timestamps agree with it. Otherwise we fall back to matching up the
+/// first timestamp we see with the time we received it, and measuring later timestamps from
+/// there.
+pub(crate) struct EventClock {
+    /// An event time, and the `Instant` it happened at, once we know the timestamps are not on
+    /// `CLOCK_MONOTONIC`.
+    anchor: Option<(u32, Instant)>,
+}
+
+impl EventClock {
+    pub fn new() -> Self {
+        EventClock { anchor: None }
+    }
+
+    /// The `Instant` at which an event with timestamp `time` happened.
+    pub fn instant(&mut self, time: u32) -> Instant {
+        let now = Instant::now();
+        let monotonic_ms = clock_gettime(ClockId::CLOCK_MONOTONIC)
+            .ok()
+            .map(|ts| Duration::from(ts).as_millis() as u32);
+        self.convert(time, monotonic_ms, now)
+    }
+
+    /// Convert `time`, given the current time as an `Instant` and (if available) in
+    /// milliseconds of `CLOCK_MONOTONIC`, truncated to 32 bits like the timestamps.
+    fn convert(&mut self, time: u32, monotonic_ms: Option<u32>, now: Instant) -> Instant {
+        if self.anchor.is_none() {
+            if let Some(monotonic_ms) = monotonic_ms {
+                // Timestamps wrap around every 49 days, so compare them modulo 2^32
+                let age = monotonic_ms.wrapping_sub(time) as i32;
+                if u128::from(age.unsigned_abs()) <= MAX_AGE.as_millis() {
+                    // Clocks can be slightly out of step, but events never come from the future
+                    let age = Duration::from_millis(age.max(0) as u64);
+                    return now.checked_sub(age).unwrap_or(now);
+                }
+            }
+            self.anchor = Some((time, now));
+            return now;
+        }
+        let (anchor_time, anchor) = self.anchor.unwrap();
+        let offset = Duration::from_millis(u64::from(time.wrapping_sub(anchor_time)));
+        match anchor.checked_add(offset) {
+            Some(instant) if instant <= now && now - instant <= MAX_AGE => instant,
+            // The clock jumped, or we've gone so long without events that the offset wrapped
+            _ => {
+                self.anchor = Some((time, now));
+                now
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn uses_monotonic_clock() {
+        let mut clock = EventClock::new();
+        let now = Instant::now();
+        assert_eq!(
+            clock.convert(1000, Some(1016), now),
+            now - Duration::from_millis(16)
+        );
+        // Across the wrap-around of the timestamps
+        assert_eq!(
+            clock.convert(u32::MAX - 5, Some(10), now),
+            now - Duration::from_millis(16)
+        );
+        // Events which seem to be slightly in the future happened now
+        assert_eq!(clock.convert(1020, Some(1016), now), now);
+        assert!(clock.anchor.is_none());
+    }
+
+    #[test]
+    fn falls_back_to_first_event() {
+        let mut clock = EventClock::new();
+        let start = Instant::now();
+        assert_eq!(clock.convert(5000, Some(1_000_000), start), start);
+        let later = start + Duration::from_millis(100);
+        assert_eq!(
+            clock.convert(5090, Some(1_000_100), later),
+            start + Duration::from_millis(90)
+        );
+        // A timestamp from before the anchor re-anchors to the current time
+        assert_eq!(clock.convert(4000, Some(1_000_100), later), later);
+    }
+}
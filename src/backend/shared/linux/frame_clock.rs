@@ -0,0 +1,209 @@
+//! Turning the display refreshes reported by X11 Present and Wayland into [`FrameTiming`]s.
+
+use std::time::Duration;
+
+use instant::Instant;
+use nix::time::{clock_gettime, ClockId};
+
+use crate::window::FrameTiming;
+
+/// Tracks the refreshes of the display showing a window.
+///
+/// Refreshes are identified by a counter (the X11 MSC, or the Wayland presentation sequence)
+/// and the time they happened at, on the clock given by the compositor.
+pub(crate) struct FrameClock {
+    clock: ClockId,
+    /// The counter and time of the latest refresh we know about.
+    last_refresh: Option<(u64, Duration)>,
+    refresh_interval: Option<Duration>,
+    /// The refresh we are waiting for to paint the next frame of an animation, if any.
+    target: Option<u64>,
+    /// The refresh at which the latest frame of the running animation was painted, if one is
+    /// running.
+    animation_frame: Option<u64>,
+}
+
+impl FrameClock {
+    /// Create a clock whose refresh times are on `clock`, with an initial guess at the refresh
+    /// interval.
+    pub fn new(clock: ClockId, refresh_interval: Option<Duration>) -> Self {
+        FrameClock {
+            clock,
+            last_refresh: None,
+            refresh_interval,
+            target: None,
+            animation_frame: None,
+        }
+    }
+
+    /// Change the clock on which refresh times are reported.
+    #[allow(dead_code)] // Only used on Wayland so far.
+    pub fn set_clock(&mut self, clock: ClockId) {
+        if clock != self.clock {
+            self.clock = clock;
+            self.last_refresh = None;
+        }
+    }
+
+    /// Use the refresh interval reported by the compositor, rather than measuring it.
+    #[allow(dead_code)] // Only used on Wayland so far.
+    pub fn set_refresh_interval(&mut self, refresh_interval: Duration) {
+        if !refresh_interval.is_zero() {
+            self.refresh_interval = Some(refresh_interval);
+        }
+    }
+
+    /// Record that refresh `counter` happened at `time`.
+    pub fn refreshed(&mut self, counter: u64, time: Duration) {
+        if let Some((last_counter, last_time)) = self.last_refresh {
+            if counter <= last_counter {
+                return;
+            }
+            if time > last_time {
+                if let Ok(refreshes) = u32::try_from(counter - last_counter) {
+                    self.refresh_interval = Some((time - last_time) / refreshes);
+                }
+            }
+        }
+        self.last_refresh = Some((counter, time));
+    }
+
+    /// Choose the refresh at which the next frame should be painted, which is the first one
+    /// after now.
+    ///
+    /// Returns `None` if we haven't seen a refresh yet, in which case painting should happen as
+    /// soon as possible.
+    pub fn next_target(&mut self) -> Option<u64> {
+        self.target = self.next_refresh(self.now()).map(|(counter, _)| counter);
+        self.target
+    }
+
+    /// Forget the target refresh, because the animation has stopped.
+    pub fn cancel_target(&mut self) {
+        self.target = None;
+        self.animation_frame = None;
+    }
+
+    /// The timing of a frame painted now, in response to the latest refresh.
+    ///
+    /// The frame will be shown at the first refresh after now. Frames are only missed while an
+    /// animation runs, by painting later than the refresh after its previous frame.
+    pub fn frame_timing(&mut self) -> FrameTiming {
+        let target = self.target.take();
+        let counter = self.last_refresh.map(|(counter, _)| counter);
+        let missed_frames = match (target, self.animation_frame, counter) {
+            (Some(_), Some(previous), Some(counter)) => {
+                u32::try_from(counter.saturating_sub(previous + 1)).unwrap_or(u32::MAX)
+            }
+            _ => 0,
+        };
+        self.animation_frame = target.and(counter);
+        let target_presentation_time = self
+            .next_refresh(self.now())
+            .and_then(|(_, time)| self.to_instant(time?));
+        FrameTiming {
+            target_presentation_time,
+            refresh_interval: self.refresh_interval,
+            missed_frames,
+        }
+    }
+
+    /// The counter of the first refresh after `now`, extrapolated from the latest refresh we
+    /// know about, and its time if we know the refresh interval.
+    ///
+    /// Without the refresh interval or the time, that is taken to be the next refresh.
+    fn next_refresh(&self, now: Option<Duration>) -> Option<(u64, Option<Duration>)> {
+        let (counter, time) = self.last_refresh?;
+        let (Some(interval), Some(now)) = (self.refresh_interval, now) else {
+            return Some((counter + 1, None));
+        };
+        let elapsed = now.saturating_sub(time).as_nanos() / interval.as_nanos().max(1);
+        let Ok(refreshes) = u32::try_from(elapsed + 1) else {
+            return Some((counter + 1, None));
+        };
+        let next_time = interval
+            .checked_mul(refreshes)
+            .and_then(|offset| time.checked_add(offset));
+        Some((counter + u64::from(refreshes), next_time))
+    }
+
+    /// The current time on our clock.
+    fn now(&self) -> Option<Duration> {
+        clock_gettime(self.clock).ok().map(Duration::from)
+    }
+
+    /// Convert a time on our clock to an `Instant`.
+    fn to_instant(&self, time: Duration) -> Option<Instant> {
+        let now = self.now()?;
+        let instant_now = Instant::now();
+        if time >= now {
+            instant_now.checked_add(time - now)
+        } else {
+            instant_now.checked_sub(now - time)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn measures_refresh_interval() {
+        let mut clock = FrameClock::new(ClockId::CLOCK_MONOTONIC, None);
+        clock.refreshed(10, Duration::from_millis(1000));
+        assert_eq!(clock.refresh_interval, None);
+        clock.refreshed(12, Duration::from_millis(1032));
+        assert_eq!(clock.refresh_interval, Some(Duration::from_millis(16)));
+        // Stale refreshes are ignored
+        clock.refreshed(11, Duration::from_millis(1016));
+        assert_eq!(clock.last_refresh, Some((12, Duration::from_millis(1032))));
+    }
+
+    fn monotonic_now() -> Duration {
+        clock_gettime(ClockId::CLOCK_MONOTONIC).unwrap().into()
+    }
+
+    #[test]
+    fn counts_missed_frames() {
+        let mut clock = FrameClock::new(ClockId::CLOCK_MONOTONIC, None);
+        assert_eq!(clock.next_target(), None);
+        assert_eq!(clock.frame_timing().missed_frames, 0);
+
+        let now = monotonic_now();
+        clock.refreshed(100, now - Duration::from_millis(1000));
+        // Without the refresh interval, the next refresh is the target
+        assert_eq!(clock.next_target(), Some(101));
+        clock.refreshed(101, now - Duration::from_millis(984));
+        // The first frame of an animation
+        assert_eq!(clock.frame_timing().missed_frames, 0);
+        clock.next_target();
+        clock.refreshed(104, now - Duration::from_millis(936));
+        let before = Instant::now();
+        let timing = clock.frame_timing();
+        assert_eq!(timing.missed_frames, 2);
+        assert_eq!(timing.refresh_interval, Some(Duration::from_millis(16)));
+        assert!(timing.target_presentation_time.unwrap() >= before);
+
+        // Without a target, e.g. after an invalidation, nothing was missed
+        assert_eq!(clock.frame_timing().missed_frames, 0);
+    }
+
+    #[test]
+    fn starts_animations_at_the_next_refresh() {
+        let interval = Duration::from_secs(1);
+        let mut clock = FrameClock::new(ClockId::CLOCK_MONOTONIC, Some(interval));
+        clock.refreshed(100, monotonic_now() - Duration::from_millis(3500));
+        clock.next_target();
+        clock.frame_timing();
+
+        // The animation stopped, and starts again three refreshes later
+        clock.cancel_target();
+        assert_eq!(clock.next_target(), Some(104));
+        clock.refreshed(104, monotonic_now());
+        let before = Instant::now();
+        let timing = clock.frame_timing();
+        assert_eq!(timing.missed_frames, 0);
+        assert!(timing.target_presentation_time.unwrap() >= before);
+    }
+}
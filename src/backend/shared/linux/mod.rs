@@ -1,2 +1,6 @@
+#[cfg(feature = "accesskit")]
+pub mod accessibility;
 // environment based utilities
 pub mod env;
+pub mod event_clock;
+pub mod frame_clock;
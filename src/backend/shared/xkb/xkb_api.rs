@@ -18,10 +18,13 @@ use super::{keycodes, xkbcommon_sys::*};
 use crate::{
     backend::shared::{code_to_location, hardware_keycode_to_code, linux},
     text::CompositionResult,
-    KeyEvent, KeyState, Modifiers,
+    KeyEvent, KeyState, KeyboardLayouts, Modifiers,
 };
 use keyboard_types::{Code, Key};
-use std::{convert::TryFrom, ffi::CString};
+use std::{
+    convert::TryFrom,
+    ffi::{CStr, CString},
+};
 use std::{os::raw::c_char, ptr::NonNull};
 
 #[cfg(feature = "x11")]
@@ -162,6 +165,7 @@ impl Context {
                 num_lock: mod_idx(XKB_MOD_NAME_NUM),
             },
             active_mods: Modifiers::empty(),
+            pressed_keys: Vec::new(),
             compose_state: self.compose_state(),
             is_composing: false,
             compose_sequence: vec![],
@@ -223,6 +227,8 @@ pub struct KeyEventsState {
     mod_indices: ModsIndices,
     compose_state: Option<NonNull<xkb_compose_state>>,
     active_mods: Modifiers,
+    /// The physical keys which are held down, in the order they were pressed.
+    pressed_keys: Vec<Code>,
     is_composing: bool,
     compose_sequence: Vec<KeySym>,
     compose_string: String,
@@ -253,6 +259,14 @@ pub struct ActiveModifiers {
 /// An opaque representation of a KeySym, to make APIs less error prone
 pub struct KeySym(xkb_keysym_t);
 
+impl KeySym {
+    /// The raw keysym value, for passing to other X11 or Wayland APIs
+    #[allow(unused)]
+    pub fn raw(self) -> u32 {
+        self.0
+    }
+}
+
 impl KeyEventsState {
     /// Stop the active composition.
     /// This should happen if the text field changes, or the selection within the text field changes
@@ -266,6 +280,76 @@ impl KeyEventsState {
         was_composing
     }
 
+    /// The modifiers which are currently active, for events which don't come with their own.
+    #[allow(unused)]
+    pub fn active_modifiers(&self) -> Modifiers {
+        self.active_mods
+    }
+
+    /// The physical keys which are currently held down, as far as this state has seen.
+    #[allow(unused)]
+    pub fn pressed_keys(&self) -> &[Code] {
+        &self.pressed_keys
+    }
+
+    /// Replace the held down keys, for when we find them out other than through key events,
+    /// such as when a Wayland keyboard enters a window.
+    #[allow(unused)]
+    pub fn set_pressed_keys(&mut self, scancodes: impl IntoIterator<Item = u32>) {
+        self.pressed_keys.clear();
+        for code in scancodes.into_iter().map(scancode_to_code) {
+            if !self.pressed_keys.contains(&code) {
+                self.pressed_keys.push(code);
+            }
+        }
+    }
+
+    /// The layouts of the keymap, and which of them is active in this state.
+    pub fn layouts(&self) -> KeyboardLayouts {
+        unsafe {
+            let keymap = xkb_state_get_keymap(self.mods_state);
+            let names = (0..xkb_keymap_num_layouts(keymap))
+                .map(|idx| {
+                    let name = xkb_keymap_layout_get_name(keymap, idx);
+                    if name.is_null() {
+                        String::new()
+                    } else {
+                        CStr::from_ptr(name).to_string_lossy().into_owned()
+                    }
+                })
+                .collect();
+            let active = xkb_state_serialize_layout(
+                self.mods_state,
+                xkb_state_component::XKB_STATE_LAYOUT_EFFECTIVE,
+            );
+            KeyboardLayouts {
+                names,
+                active: active as usize,
+            }
+        }
+    }
+
+    /// A lookup of the keys in this state's keymap, under its active layout.
+    ///
+    /// Returns `None` if xkbcommon couldn't create the state for the lookup.
+    pub fn key_lookup(&self) -> Option<KeyLookup> {
+        unsafe {
+            let keymap = xkb_state_get_keymap(self.mods_state);
+            let state = xkb_state_new(keymap);
+            if state.is_null() {
+                return None;
+            }
+            Some(KeyLookup {
+                state,
+                mod_indices: self.mod_indices,
+                layout: xkb_state_serialize_layout(
+                    self.mods_state,
+                    xkb_state_component::XKB_STATE_LAYOUT_EFFECTIVE,
+                ),
+            })
+        }
+    }
+
     pub fn update_xkb_state(&mut self, mods: ActiveModifiers) {
         unsafe {
             xkb_state_update_mask(
@@ -311,10 +395,12 @@ impl KeyEventsState {
         state: KeyState,
         repeat: bool,
     ) -> KeyEvent {
-        // TODO: This shouldn't be repeated
-        let code = u16::try_from(scancode)
-            .map(hardware_keycode_to_code)
-            .unwrap_or(Code::Unidentified);
+        let code = scancode_to_code(scancode);
+        match state {
+            KeyState::Down if !self.pressed_keys.contains(&code) => self.pressed_keys.push(code),
+            KeyState::Down => {}
+            KeyState::Up => self.pressed_keys.retain(|it| *it != code),
+        }
         // TODO this is lazy - really should use xkb i.e. augment the get_logical_key method.
         // TODO: How?
         let location = code_to_location(code);
@@ -328,6 +414,7 @@ impl KeyEventsState {
             mods: self.active_mods,
             repeat,
             is_composing: self.is_composing,
+            time: None,
         }
     }
 
@@ -605,6 +692,144 @@ impl KeyEventsState {
     }
 }
 
+/// Translates between physical keys and the keys they produce, for one keymap and layout.
+///
+/// This keeps its own xkb state, so that looking keys up doesn't disturb the modifiers of the
+/// keyboard, and so it can outlive a borrow of the [`KeyEventsState`] it came from.
+pub struct KeyLookup {
+    state: *mut xkb_state,
+    mod_indices: ModsIndices,
+    layout: xkb_layout_index_t,
+}
+
+impl KeyLookup {
+    /// The modifier combinations which [`code_for_key`](Self::code_for_key) tries, in order.
+    const LEVEL_MODS: [Modifiers; 2] = [Modifiers::empty(), Modifiers::SHIFT];
+
+    /// The key which `code` produces with `mods` held.
+    pub fn key_for_code(&self, code: Code, mods: Modifiers) -> Option<Key> {
+        let keycode = self
+            .keycodes()
+            .find(|&keycode| scancode_to_code(keycode) == code)?;
+        self.set_mods(mods);
+        match self.key(keycode) {
+            Key::Unidentified => None,
+            key => Some(key),
+        }
+    }
+
+    /// The physical key which produces `key`, and the modifiers which need to be held for it.
+    ///
+    /// Keys which need no modifiers are preferred over those which need shift.
+    pub fn code_for_key(&self, key: &Key) -> Option<(Code, Modifiers)> {
+        Self::LEVEL_MODS.iter().find_map(|&mods| {
+            self.set_mods(mods);
+            self.keycodes()
+                .filter(|&keycode| self.key(keycode) == *key)
+                .map(scancode_to_code)
+                .find(|code| *code != Code::Unidentified)
+                .map(|code| (code, mods))
+        })
+    }
+
+    fn keycodes(&self) -> impl Iterator<Item = xkb_keycode_t> {
+        unsafe {
+            let keymap = xkb_state_get_keymap(self.state);
+            xkb_keymap_min_keycode(keymap)..=xkb_keymap_max_keycode(keymap)
+        }
+    }
+
+    fn key(&self, keycode: xkb_keycode_t) -> Key {
+        let keysym = unsafe { xkb_state_key_get_one_sym(self.state, keycode) };
+        if keysym == XKB_KEY_NoSymbol {
+            return Key::Unidentified;
+        }
+        KeyEventsState::get_logical_key(KeySym(keysym))
+    }
+
+    fn set_mods(&self, mods: Modifiers) {
+        let indices = self.mod_indices;
+        let mut mask: xkb_mod_mask_t = 0;
+        for (modifier, idx) in [
+            (Modifiers::CONTROL, indices.control),
+            (Modifiers::SHIFT, indices.shift),
+            (Modifiers::ALT, indices.alt),
+            (Modifiers::META, indices.super_),
+            (Modifiers::CAPS_LOCK, indices.caps_lock),
+            (Modifiers::NUM_LOCK, indices.num_lock),
+        ] {
+            if mods.contains(modifier) && idx != XKB_MOD_INVALID {
+                mask |= 1 << idx;
+            }
+        }
+        unsafe {
+            xkb_state_update_mask(self.state, mask, 0, 0, 0, 0, self.layout);
+        }
+    }
+}
+
+impl Drop for KeyLookup {
+    fn drop(&mut self) {
+        unsafe {
+            xkb_state_unref(self.state);
+        }
+    }
+}
+
+/// The name of the keysym of `key`, such as `comma` for `,`, for describing keys to other
+/// programs.
+#[allow(unused)]
+pub fn keysym_name(key: &Key) -> Option<String> {
+    let keysym = match key {
+        Key::Character(s) => {
+            let mut chars = s.chars();
+            let c = chars.next()?;
+            if chars.next().is_some() {
+                return None;
+            }
+            unsafe { xkb_utf32_to_keysym(c as u32) }
+        }
+        Key::Enter => XKB_KEY_Return,
+        Key::Tab => XKB_KEY_Tab,
+        Key::Backspace => XKB_KEY_BackSpace,
+        Key::Escape => XKB_KEY_Escape,
+        Key::Delete => XKB_KEY_Delete,
+        Key::Insert => XKB_KEY_Insert,
+        Key::Home => XKB_KEY_Home,
+        Key::End => XKB_KEY_End,
+        Key::PageUp => XKB_KEY_Page_Up,
+        Key::PageDown => XKB_KEY_Page_Down,
+        Key::ArrowLeft => XKB_KEY_Left,
+        Key::ArrowRight => XKB_KEY_Right,
+        Key::ArrowUp => XKB_KEY_Up,
+        Key::ArrowDown => XKB_KEY_Down,
+        Key::PrintScreen => XKB_KEY_Print,
+        Key::Pause => XKB_KEY_Pause,
+        key => {
+            // The keysyms of F1 to F35 are consecutive
+            let number: u32 = key.to_string().strip_prefix('F')?.parse().ok()?;
+            if !(1..=35).contains(&number) {
+                return None;
+            }
+            XKB_KEY_F1 + number - 1
+        }
+    };
+    if keysym == XKB_KEY_NoSymbol {
+        return None;
+    }
+    let mut name = [0u8; 64];
+    let len = unsafe { xkb_keysym_get_name(keysym, name.as_mut_ptr().cast(), name.len()) };
+    let len = usize::try_from(len).ok()?;
+    Some(String::from_utf8_lossy(name.get(..len)?).into_owned())
+}
+
+/// The physical key of an xkb keycode, which is an evdev scancode plus 8.
+pub fn scancode_to_code(scancode: u32) -> Code {
+    u16::try_from(scancode)
+        .map(hardware_keycode_to_code)
+        .unwrap_or(Code::Unidentified)
+}
+
 impl Drop for KeyEventsState {
     fn drop(&mut self) {
         unsafe {
@@ -22,6 +22,7 @@ use std::{
     sync::mpsc::{Sender, TryRecvError},
 };
 
+use nix::time::ClockId;
 use smithay_client_toolkit::{
     compositor::CompositorState,
     output::OutputState,
@@ -32,19 +33,26 @@ use smithay_client_toolkit::{
             protocol::wl_compositor,
             Connection, QueueHandle, WaylandSource,
         },
+        protocols::wp::presentation_time::client::wp_presentation::WpPresentation,
     },
     registry::RegistryState,
     seat::SeatState,
     shell::xdg::XdgShell,
 };
 
-use super::{clipboard, error::Error, ActiveAction, IdleAction, WaylandState};
+use super::{
+    clipboard, error::Error, global_shortcuts::GlobalShortcuts, ActiveAction, IdleAction,
+    WaylandState,
+};
 use crate::{
     backend::{
-        shared::{linux, xkb::Context},
+        shared::{
+            linux,
+            xkb::{Context, KeyLookup},
+        },
         wayland::input::TextInputManagerData,
     },
-    AppHandler,
+    AppHandler, Code, HotKey, KbKey, Modifiers,
 };
 
 #[derive(Clone)]
@@ -54,6 +62,8 @@ pub struct Application {
     // The inner is taken in `run`
     state: Rc<RefCell<Option<WaylandState>>>,
     pub(super) compositor: wl_compositor::WlCompositor,
+    // Behind an `Rc` to keep this and the window builder small
+    pub(super) presentation: Option<Rc<WpPresentation>>,
     pub(super) wayland_queue: QueueHandle<WaylandState>,
     pub(super) xdg_shell: Weak<XdgShell>,
     loop_signal: LoopSignal,
@@ -61,6 +71,8 @@ pub struct Application {
     pub(super) idle_sender: Sender<IdleAction>,
     pub(super) loop_sender: channel::Sender<ActiveAction>,
     pub(super) raw_display_handle: *mut c_void,
+    key_lookup: Rc<RefCell<Option<KeyLookup>>>,
+    global_shortcuts: Rc<GlobalShortcuts>,
 }
 
 impl Application {
@@ -109,11 +121,20 @@ impl Application {
             },
             |it| Ok(Some(it)),
         )?;
+        // Version 3 is needed to find out whether a drag was successful
+        let data_device_manager = globals.bind(&qh, 3..=3, ()).ok();
+        let shm = globals.bind(&qh, 1..=1, ()).ok();
+        let presentation: Option<WpPresentation> = globals.bind(&qh, 1..=1, ()).ok();
+        let presentation = presentation.map(Rc::new);
+        let pointer_gestures = globals.bind(&qh, 1..=3, ()).ok();
+        let pointer_constraints = globals.bind(&qh, 1..=1, ()).ok();
+        let relative_pointer_manager = globals.bind(&qh, 1..=1, ()).ok();
+        let key_lookup = Rc::new(RefCell::new(None));
 
         let mut state = WaylandState {
             registry_state: RegistryState::new(&globals),
             output_state: OutputState::new(&globals, &qh),
-            _compositor_state: compositor_state,
+            compositor_state,
             _xdg_shell_state: shell,
             event_loop: Some(event_loop),
             handler: None,
@@ -126,13 +147,28 @@ impl Application {
             input_states: vec![],
             seats: SeatState::new(&globals, &qh),
             xkb_context: Context::new(),
+            key_lookup: key_lookup.clone(),
             text_input: text_input_global,
+            data_device_manager,
+            shm,
+            presentation_clock: ClockId::CLOCK_MONOTONIC,
+            event_clock: linux::event_clock::EventClock::new(),
+            pointer_gestures,
+            pointer_constraints,
+            relative_pointer_manager,
+            drag: None,
+            connection: conn.clone(),
+            default_cursor: None,
             loop_handle: loop_handle.clone(),
         };
         state.initial_seats();
+        let global_shortcuts = Rc::new(GlobalShortcuts::new(AppHandle {
+            loop_sender: loop_sender.clone(),
+        }));
         Ok(Application {
             state: Rc::new(RefCell::new(Some(state))),
             compositor,
+            presentation,
             wayland_queue: qh,
             loop_signal,
             idle_sender,
@@ -140,6 +176,8 @@ impl Application {
             loop_handle,
             xdg_shell: shell_ref,
             raw_display_handle: conn.backend().display_ptr().cast(),
+            key_lookup,
+            global_shortcuts,
         })
     }
 
@@ -182,6 +220,24 @@ impl Application {
         clipboard::Clipboard {}
     }
 
+    pub fn key_for_code(&self, code: Code, mods: Modifiers) -> Option<KbKey> {
+        let key_lookup = self.key_lookup.borrow();
+        key_lookup.as_ref()?.key_for_code(code, mods)
+    }
+
+    pub fn code_for_key(&self, key: &KbKey) -> Option<(Code, Modifiers)> {
+        let key_lookup = self.key_lookup.borrow();
+        key_lookup.as_ref()?.code_for_key(key)
+    }
+
+    pub fn register_global_hotkey(&self, id: u32, hotkey: &HotKey) -> Result<(), anyhow::Error> {
+        self.global_shortcuts.register(id, hotkey)
+    }
+
+    pub fn unregister_global_hotkey(&self, id: u32) -> Result<(), anyhow::Error> {
+        self.global_shortcuts.unregister(id)
+    }
+
     pub fn get_locale() -> String {
         linux::env::locale()
     }
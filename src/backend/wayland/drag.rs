@@ -0,0 +1,405 @@
+//! Starting drag and drop operations, using `wl_data_device`.
+
+use std::{
+    ffi::CStr,
+    fs::File,
+    io::Write,
+    os::fd::{AsRawFd, FromRawFd},
+};
+
+use nix::sys::memfd::{memfd_create, MemFdCreateFlag};
+use smithay_client_toolkit::reexports::client::{
+    event_created_child,
+    protocol::{
+        wl_buffer::{self, WlBuffer},
+        wl_data_device::{self, WlDataDevice},
+        wl_data_device_manager::{self, DndAction, WlDataDeviceManager},
+        wl_data_offer::{self, WlDataOffer},
+        wl_data_source::{self, WlDataSource},
+        wl_shm::{self, WlShm},
+        wl_shm_pool::{self, WlShmPool},
+        wl_surface::WlSurface,
+    },
+    Connection, Dispatch, Proxy, QueueHandle, WEnum,
+};
+
+use super::{window::WindowId, WaylandState};
+use crate::{ClipboardFormat, DragAction, DragActions, DragImage, DragOutcome, DragToken};
+
+/// The mime type which text is offered as, in addition to [`ClipboardFormat::TEXT`].
+const TEXT_MIME: &str = "text/plain;charset=utf-8";
+
+/// A drag requested by [`WindowHandle::start_drag`](super::window::WindowHandle::start_drag),
+/// which will be started once control returns to the event loop.
+pub(super) struct DragRequest {
+    pub token: DragToken,
+    pub formats: Vec<ClipboardFormat>,
+    pub actions: DragActions,
+    pub image: Option<DragImage>,
+}
+
+/// The drag which this application is currently the source of.
+///
+/// Wayland only allows a single drag per seat, and we only track one drag overall,
+/// which in practice is all that users can physically do anyway.
+pub(super) struct ActiveDrag {
+    source: WlDataSource,
+    window: WindowId,
+    token: DragToken,
+    /// The data offered to the target, which is only sent once the target asks for it
+    formats: Vec<ClipboardFormat>,
+    /// The action most recently selected by the compositor
+    action: Option<DragAction>,
+    _icon: Option<DragIcon>,
+}
+
+impl ActiveDrag {
+    fn data_for(&self, mime_type: &str) -> Option<&[u8]> {
+        self.formats
+            .iter()
+            .find(|format| {
+                format.identifier == mime_type
+                    || (mime_type == TEXT_MIME && format.identifier == ClipboardFormat::TEXT)
+            })
+            .map(|format| &format.data[..])
+    }
+}
+
+impl Drop for ActiveDrag {
+    fn drop(&mut self) {
+        self.source.destroy();
+    }
+}
+
+/// The surface shown under the pointer whilst dragging, and the buffer backing it.
+struct DragIcon {
+    surface: WlSurface,
+    pool: WlShmPool,
+    buffer: WlBuffer,
+}
+
+impl Drop for DragIcon {
+    fn drop(&mut self) {
+        self.buffer.destroy();
+        self.pool.destroy();
+        self.surface.destroy();
+    }
+}
+
+fn dnd_actions(actions: DragActions) -> DndAction {
+    let mut result = DndAction::empty();
+    if actions.contains(DragAction::Copy) {
+        result |= DndAction::Copy;
+    }
+    if actions.contains(DragAction::Move) {
+        result |= DndAction::Move;
+    }
+    // Wayland has no equivalent to the link action
+    result
+}
+
+fn drag_action(action: DndAction) -> Option<DragAction> {
+    if action.contains(DndAction::Move) {
+        Some(DragAction::Move)
+    } else if action.contains(DndAction::Copy) {
+        Some(DragAction::Copy)
+    } else {
+        None
+    }
+}
+
+impl WaylandState {
+    pub(super) fn start_drag(&mut self, window_id: &WindowId, request: DragRequest) {
+        let token = request.token;
+        if let Err(reason) = self.try_start_drag(window_id, request) {
+            tracing::warn!("couldn't start drag: {reason}");
+            if let Some(window) = self.windows.get_mut(window_id) {
+                window.handler.drag_finished(token, DragOutcome::Cancelled);
+            }
+        }
+    }
+
+    fn try_start_drag(
+        &mut self,
+        window_id: &WindowId,
+        request: DragRequest,
+    ) -> Result<(), &'static str> {
+        if self.drag.is_some() {
+            return Err("a drag is already in progress");
+        }
+        let manager = self
+            .data_device_manager
+            .clone()
+            .ok_or("the compositor doesn't support wl_data_device_manager version 3")?;
+        let origin = self
+            .windows
+            .get(window_id)
+            .ok_or("the window no longer exists")?
+            .wl_surface();
+        let (device, serial) = self
+            .input_states
+            .iter()
+            .find_map(|seat| seat.drag_grab(window_id))
+            .ok_or("no pointer button is held over the window")?;
+
+        let source = manager.create_data_source(&self.wayland_queue, ());
+        for format in &request.formats {
+            source.offer(format.identifier.to_string());
+            if format.identifier == ClipboardFormat::TEXT {
+                source.offer(TEXT_MIME.to_string());
+            }
+        }
+        source.set_actions(dnd_actions(request.actions));
+        let icon = request
+            .image
+            .and_then(|image| self.create_drag_icon(&image));
+        device.start_drag(
+            Some(&source),
+            &origin,
+            icon.as_ref().map(|icon| &icon.surface),
+            serial,
+        );
+        if let Some(icon) = icon.as_ref() {
+            icon.surface.commit();
+        }
+        self.drag = Some(ActiveDrag {
+            source,
+            window: window_id.clone(),
+            token: request.token,
+            formats: request.formats,
+            action: None,
+            _icon: icon,
+        });
+        Ok(())
+    }
+
+    fn create_drag_icon(&self, image: &DragImage) -> Option<DragIcon> {
+        let Some(shm) = self.shm.as_ref() else {
+            tracing::warn!("the compositor doesn't support wl_shm, so drag icons are unavailable");
+            return None;
+        };
+        let stride = image.width * 4;
+        let size = stride * image.height;
+        if size == 0 {
+            return None;
+        }
+        // The buffer is in premultiplied ARGB8888, which is stored little-endian (i.e. as BGRA)
+        let mut pixels = Vec::with_capacity(size);
+        for pixel in image.pixels.chunks_exact(4) {
+            let alpha = pixel[3] as u16;
+            let premultiply = |channel: u8| ((channel as u16 * alpha + 127) / 255) as u8;
+            pixels.extend_from_slice(&[
+                premultiply(pixel[2]),
+                premultiply(pixel[1]),
+                premultiply(pixel[0]),
+                pixel[3],
+            ]);
+        }
+        let name = CStr::from_bytes_with_nul(b"glazier-drag-icon\0").unwrap();
+        let fd = match memfd_create(name, MemFdCreateFlag::MFD_CLOEXEC) {
+            Ok(fd) => fd,
+            Err(err) => {
+                tracing::warn!("failed to create drag icon buffer: {err}");
+                return None;
+            }
+        };
+        // Safety: `memfd_create` just gave us ownership of this file descriptor
+        let mut file = unsafe { File::from_raw_fd(fd) };
+        if let Err(err) = file.write_all(&pixels) {
+            tracing::warn!("failed to write drag icon buffer: {err}");
+            return None;
+        }
+
+        let qh = &self.wayland_queue;
+        let pool = shm.create_pool(file.as_raw_fd(), size as i32, qh, ());
+        let buffer = pool.create_buffer(
+            0,
+            image.width as i32,
+            image.height as i32,
+            stride as i32,
+            wl_shm::Format::Argb8888,
+            qh,
+            (),
+        );
+        let surface = self
+            .compositor_state
+            .wl_compositor()
+            .create_surface(qh, Default::default());
+        // The icon is positioned relative to the pointer, so offset it by the hot spot
+        let (x, y) = (-image.hot.x as i32, -image.hot.y as i32);
+        if surface.version() >= 5 {
+            surface.attach(Some(&buffer), 0, 0);
+            surface.offset(x, y);
+        } else {
+            surface.attach(Some(&buffer), x, y);
+        }
+        surface.damage(0, 0, image.width as i32, image.height as i32);
+        Some(DragIcon {
+            surface,
+            pool,
+            buffer,
+        })
+    }
+
+    fn finish_drag(&mut self, source: &WlDataSource, outcome: DragOutcome) {
+        if self
+            .drag
+            .as_ref()
+            .map_or(true, |drag| &drag.source != source)
+        {
+            tracing::warn!("got an event for a data source which isn't being dragged");
+            source.destroy();
+            return;
+        }
+        // Dropping the drag destroys the source and icon
+        let drag = self.drag.take().unwrap();
+        if let Some(window) = self.windows.get_mut(&drag.window) {
+            window.handler.drag_finished(drag.token, outcome);
+        }
+    }
+}
+
+impl Dispatch<WlDataSource, ()> for WaylandState {
+    fn event(
+        state: &mut Self,
+        source: &WlDataSource,
+        event: <WlDataSource as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_data_source::Event::Target { mime_type } => {
+                tracing::trace!(?mime_type, "drag target changed");
+            }
+            wl_data_source::Event::Send { mime_type, fd } => {
+                let Some(drag) = state.drag.as_ref().filter(|drag| &drag.source == source) else {
+                    return;
+                };
+                let Some(data) = drag.data_for(&mime_type) else {
+                    tracing::warn!("drag target asked for {mime_type}, which wasn't offered");
+                    return;
+                };
+                // The target might be slow to read the data, or even be this application,
+                // so we must not block the event loop whilst writing it
+                let data = data.to_vec();
+                std::thread::spawn(move || {
+                    if let Err(err) = File::from(fd).write_all(&data) {
+                        tracing::warn!("failed to send dragged data: {err}");
+                    }
+                });
+            }
+            wl_data_source::Event::Action { dnd_action } => {
+                let Some(drag) = state.drag.as_mut().filter(|drag| &drag.source == source) else {
+                    return;
+                };
+                if let WEnum::Value(action) = dnd_action {
+                    drag.action = drag_action(action);
+                }
+            }
+            wl_data_source::Event::DndDropPerformed => {
+                // We wait for `dnd_finished` (or `cancelled`) before reporting the outcome
+            }
+            wl_data_source::Event::DndFinished => {
+                let action = state
+                    .drag
+                    .as_ref()
+                    .and_then(|drag| drag.action)
+                    .unwrap_or(DragAction::Copy);
+                state.finish_drag(source, DragOutcome::Dropped(action));
+            }
+            wl_data_source::Event::Cancelled => {
+                state.finish_drag(source, DragOutcome::Cancelled);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<WlDataDevice, ()> for WaylandState {
+    fn event(
+        _: &mut Self,
+        _: &WlDataDevice,
+        event: <WlDataDevice as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            // We don't yet accept drops or read the selection, so we have no use for offers
+            wl_data_device::Event::Enter {
+                id: Some(offer), ..
+            }
+            | wl_data_device::Event::Selection { id: Some(offer) } => offer.destroy(),
+            _ => {}
+        }
+    }
+
+    event_created_child!(WaylandState, WlDataDevice, [
+        wl_data_device::EVT_DATA_OFFER_OPCODE => (WlDataOffer, ()),
+    ]);
+}
+
+impl Dispatch<WlDataOffer, ()> for WaylandState {
+    fn event(
+        _: &mut Self,
+        _: &WlDataOffer,
+        _: wl_data_offer::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<WlDataDeviceManager, ()> for WaylandState {
+    fn event(
+        _: &mut Self,
+        _: &WlDataDeviceManager,
+        event: wl_data_device_manager::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        tracing::error!(?event, "unexpected wl_data_device_manager event");
+    }
+}
+
+impl Dispatch<WlShm, ()> for WaylandState {
+    fn event(
+        _: &mut Self,
+        _: &WlShm,
+        _: wl_shm::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        // Argb8888 is always supported, which is the only format we use
+    }
+}
+
+impl Dispatch<WlShmPool, ()> for WaylandState {
+    fn event(
+        _: &mut Self,
+        _: &WlShmPool,
+        event: wl_shm_pool::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        tracing::error!(?event, "unexpected wl_shm_pool event");
+    }
+}
+
+impl Dispatch<WlBuffer, ()> for WaylandState {
+    fn event(
+        _: &mut Self,
+        _: &WlBuffer,
+        _: wl_buffer::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        // The icon buffer is never reused, so we don't care when it is released
+    }
+}
@@ -0,0 +1,407 @@
+//! Global hotkeys, through the GlobalShortcuts interface of the desktop portal.
+
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::thread;
+
+use anyhow::{anyhow, Context, Error};
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures::executor::block_on;
+use futures::{FutureExt, StreamExt};
+use zbus::export::serde::Serialize;
+use zbus::zvariant::{DynamicType, ObjectPath, OwnedObjectPath, OwnedValue, Value};
+use zbus::{Connection, Proxy};
+
+use super::application::AppHandle;
+use crate::backend::shared::xkb;
+use crate::{HotKey, KbKey, Modifiers};
+
+const PORTAL_SERVICE: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const GLOBAL_SHORTCUTS_INTERFACE: &str = "org.freedesktop.portal.GlobalShortcuts";
+const REQUEST_INTERFACE: &str = "org.freedesktop.portal.Request";
+const SESSION_INTERFACE: &str = "org.freedesktop.portal.Session";
+
+/// A change to the shortcuts, for the portal thread to make.
+enum Request {
+    /// Bind the hotkey `id`, with its description and trigger, in place of its previous ones.
+    Register(u32, String, String),
+    Unregister(u32),
+}
+
+/// The global hotkeys of the application.
+///
+/// Binding shortcuts can take as long as the user takes to confirm them, so a thread talks to
+/// the portal, and tells the `AppHandler` when a hotkey couldn't be registered.
+pub(super) struct GlobalShortcuts {
+    /// Sends requests to the thread which talks to the portal, once it has started.
+    portal: RefCell<Option<UnboundedSender<Request>>>,
+    app: AppHandle,
+}
+
+impl GlobalShortcuts {
+    pub fn new(app: AppHandle) -> Self {
+        GlobalShortcuts {
+            portal: RefCell::new(None),
+            app,
+        }
+    }
+
+    pub fn register(&self, id: u32, hotkey: &HotKey) -> Result<(), Error> {
+        let trigger = trigger(hotkey)
+            .ok_or_else(|| anyhow!("the hotkey {hotkey} can't be described to the portal"))?;
+        self.send(Request::Register(id, hotkey.to_string(), trigger))
+    }
+
+    pub fn unregister(&self, id: u32) -> Result<(), Error> {
+        self.send(Request::Unregister(id))
+    }
+
+    fn send(&self, request: Request) -> Result<(), Error> {
+        let mut portal = self.portal.borrow_mut();
+        // Start the thread again if it stopped, such as when there was no portal last time
+        if portal.as_ref().map_or(true, UnboundedSender::is_closed) {
+            *portal = Some(spawn_portal_thread(self.app.clone()));
+        }
+        portal
+            .as_ref()
+            .unwrap()
+            .unbounded_send(request)
+            .map_err(|_| anyhow!("the global shortcuts portal thread stopped"))
+    }
+}
+
+/// Start the thread which binds the shortcuts it is asked to, and tells the `AppHandler` when
+/// they are pressed.
+fn spawn_portal_thread(app: AppHandle) -> UnboundedSender<Request> {
+    let (sender, requests) = mpsc::unbounded();
+    thread::spawn(move || block_on(run_portal(app, requests)));
+    sender
+}
+
+/// Tell the `AppHandler` that the hotkey `id` couldn't be registered.
+fn report_failure(app: &AppHandle, id: u32, err: Error) {
+    tracing::warn!("failed to register the global hotkey {id}: {err:#}");
+    app.run_on_main(move |handler| {
+        if let Some(handler) = handler {
+            handler.global_hotkey_failed(id, err.into());
+        }
+    });
+}
+
+async fn run_portal(app: AppHandle, mut requests: UnboundedReceiver<Request>) {
+    let connected = async {
+        let portal = Portal::connect().await?;
+        // Listen for activations before binding anything, so that none can be missed
+        let activations = portal.proxy.receive_signal("Activated").await?;
+        Ok::<_, Error>((portal, activations))
+    };
+    let (portal, mut activations) = match connected.await {
+        Ok(connected) => connected,
+        Err(err) => {
+            // Fail the requests which were sent before the thread stops, and leave the later
+            // ones to start the thread again
+            let mut request = requests.next().await;
+            while let Some(current) = request {
+                if let Request::Register(id, ..) = current {
+                    let err = anyhow!("the desktop has no global shortcuts portal: {err:#}");
+                    report_failure(&app, id, err);
+                }
+                request = requests.next().now_or_never().flatten();
+            }
+            return;
+        }
+    };
+    // The shortcuts which the desktop has bound, by id, as their descriptions and triggers
+    let mut bound = BTreeMap::new();
+    let mut session = None;
+    loop {
+        futures::select! {
+            request = requests.next() => {
+                let mut shortcuts = bound.clone();
+                let registered = match request {
+                    Some(Request::Register(id, description, trigger)) => {
+                        shortcuts.insert(id, (description, trigger));
+                        Some(id)
+                    }
+                    Some(Request::Unregister(id)) => {
+                        if shortcuts.remove(&id).is_none() {
+                            continue;
+                        }
+                        None
+                    }
+                    None => break,
+                };
+                match portal.rebind(&mut session, &shortcuts).await {
+                    Ok(()) => bound = shortcuts,
+                    Err(err) => match registered {
+                        Some(id) => report_failure(&app, id, err),
+                        None => tracing::warn!("failed to unregister a global hotkey: {err:#}"),
+                    },
+                }
+            }
+            activated = activations.next() => {
+                let Some(activated) = activated else {
+                    tracing::warn!("the global shortcuts portal stopped sending activations");
+                    break;
+                };
+                let Ok((session_handle, shortcut_id, ..)) = activated.body::<Activated>() else {
+                    continue;
+                };
+                // Activations of a session which has since been replaced are stale
+                if session.as_ref() != Some(&session_handle) {
+                    continue;
+                }
+                if let Ok(id) = shortcut_id.parse() {
+                    app.run_on_main(move |handler| {
+                        if let Some(handler) = handler {
+                            handler.global_hotkey(id);
+                        }
+                    });
+                }
+            }
+        }
+    }
+    if let Some(session) = session {
+        let _ = portal.close_session(&session).await;
+    }
+}
+
+/// The body of the `Activated` signal: the session, the shortcut's id, a timestamp and
+/// options.
+type Activated = (OwnedObjectPath, String, u64, HashMap<String, OwnedValue>);
+
+/// Our own connection to the GlobalShortcuts interface of the portal.
+///
+/// The portal only sends the `Activated` signals of a session to the connection which created
+/// it, so the sessions and the stream of activations have to share a connection.
+struct Portal {
+    proxy: Proxy<'static>,
+    /// Makes the handle tokens of requests and sessions unique.
+    next_token: Cell<u32>,
+}
+
+impl Portal {
+    async fn connect() -> Result<Portal, Error> {
+        let conn = Connection::session()
+            .await
+            .context("connect to the session bus")?;
+        let proxy = Proxy::new(
+            &conn,
+            PORTAL_SERVICE,
+            PORTAL_PATH,
+            GLOBAL_SHORTCUTS_INTERFACE,
+        )
+        .await?;
+        proxy
+            .get_property::<u32>("version")
+            .await
+            .context("find the GlobalShortcuts interface")?;
+        Ok(Portal {
+            proxy,
+            next_token: Cell::new(0),
+        })
+    }
+
+    fn token(&self) -> String {
+        let token = self.next_token.get();
+        self.next_token.set(token + 1);
+        format!("glazier{token}")
+    }
+
+    /// Bind `shortcuts` in a new session, and close `session` once they are, leaving it bound
+    /// if they can't be.
+    ///
+    /// The portal can't unbind single shortcuts, so every change binds all of them again.
+    async fn rebind(
+        &self,
+        session: &mut Option<OwnedObjectPath>,
+        shortcuts: &BTreeMap<u32, (String, String)>,
+    ) -> Result<(), Error> {
+        let new_session = if shortcuts.is_empty() {
+            None
+        } else {
+            let new_session = self.create_session().await?;
+            match self.bind(&new_session, shortcuts).await {
+                Ok(()) => Some(new_session),
+                Err(err) => {
+                    let _ = self.close_session(&new_session).await;
+                    return Err(err);
+                }
+            }
+        };
+        if let Some(old_session) = std::mem::replace(session, new_session) {
+            if let Err(err) = self.close_session(&old_session).await {
+                tracing::warn!("failed to close the previous global shortcuts session: {err:#}");
+            }
+        }
+        Ok(())
+    }
+
+    async fn create_session(&self) -> Result<OwnedObjectPath, Error> {
+        let token = self.token();
+        let session_token = self.token();
+        let options = HashMap::from([
+            ("handle_token", Value::from(token.as_str())),
+            ("session_handle_token", Value::from(session_token.as_str())),
+        ]);
+        let results = self
+            .request("CreateSession", &token, &(options,))
+            .await
+            .context("create a global shortcuts session")?;
+        // The specification makes this a string, but some portals send an object path
+        match results.get("session_handle").map(|handle| &**handle) {
+            Some(Value::Str(handle)) => Ok(OwnedObjectPath::try_from(handle.as_str())?),
+            Some(Value::ObjectPath(handle)) => Ok(handle.to_owned().into()),
+            _ => Err(anyhow!(
+                "the portal didn't create a global shortcuts session"
+            )),
+        }
+    }
+
+    /// Bind `shortcuts` in `session`, failing unless the desktop binds all of them.
+    async fn bind(
+        &self,
+        session: &ObjectPath<'_>,
+        shortcuts: &BTreeMap<u32, (String, String)>,
+    ) -> Result<(), Error> {
+        let new_shortcuts = shortcuts
+            .iter()
+            .map(|(id, (description, trigger))| {
+                let options = HashMap::from([
+                    ("description", Value::from(description.as_str())),
+                    ("preferred_trigger", Value::from(trigger.as_str())),
+                ]);
+                (id.to_string(), options)
+            })
+            .collect::<Vec<_>>();
+        let token = self.token();
+        let options = HashMap::from([("handle_token", Value::from(token.as_str()))]);
+        let results = self
+            .request(
+                "BindShortcuts",
+                &token,
+                &(session, new_shortcuts, "", options),
+            )
+            .await
+            .context("the desktop didn't bind the global shortcuts")?;
+        let bound = results
+            .get("shortcuts")
+            .map(|shortcuts| shortcut_ids(shortcuts))
+            .unwrap_or_default();
+        for id in shortcuts.keys() {
+            let id = id.to_string();
+            if !bound.contains(&id) {
+                return Err(anyhow!("the desktop didn't bind the global shortcut {id}"));
+            }
+        }
+        Ok(())
+    }
+
+    async fn close_session(&self, session: &ObjectPath<'_>) -> Result<(), Error> {
+        let session = Proxy::new(
+            self.proxy.connection(),
+            PORTAL_SERVICE,
+            session,
+            SESSION_INTERFACE,
+        )
+        .await?;
+        session.call::<_, _, ()>("Close", &()).await?;
+        Ok(())
+    }
+
+    /// Call `method`, which answers through a `Request` object made with `token`, and return
+    /// the results of its response.
+    async fn request<B>(
+        &self,
+        method: &str,
+        token: &str,
+        body: &B,
+    ) -> Result<HashMap<String, OwnedValue>, Error>
+    where
+        B: Serialize + DynamicType,
+    {
+        // The request's path follows from our unique name and the token, so we can listen for
+        // its response before making it, and can't miss a quick one
+        let conn = self.proxy.connection();
+        let sender = conn
+            .unique_name()
+            .ok_or_else(|| anyhow!("the session bus gave us no name"))?;
+        let sender = sender.trim_start_matches(':').replace('.', "_");
+        let path = format!("{PORTAL_PATH}/request/{sender}/{token}");
+        let request = Proxy::new(conn, PORTAL_SERVICE, path, REQUEST_INTERFACE).await?;
+        let mut responses = request.receive_signal("Response").await?;
+        self.proxy
+            .call::<_, _, OwnedObjectPath>(method, body)
+            .await?;
+        let response = responses
+            .next()
+            .await
+            .ok_or_else(|| anyhow!("the portal didn't answer {method}"))?;
+        let (code, results) = response.body::<(u32, HashMap<String, OwnedValue>)>()?;
+        match code {
+            0 => Ok(results),
+            1 => Err(anyhow!("the user cancelled {method}")),
+            _ => Err(anyhow!("the portal failed {method}")),
+        }
+    }
+}
+
+/// The ids in the `shortcuts` result of `BindShortcuts`, which has the signature `a(sa{sv})`.
+fn shortcut_ids(shortcuts: &Value) -> Vec<String> {
+    let Value::Array(shortcuts) = shortcuts else {
+        return Vec::new();
+    };
+    shortcuts
+        .get()
+        .iter()
+        .filter_map(|shortcut| match shortcut {
+            Value::Structure(shortcut) => match shortcut.fields().first()? {
+                Value::Str(id) => Some(id.as_str().to_owned()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// The trigger of `hotkey` in the form of the XDG shortcuts specification, such as
+/// `CTRL+SHIFT+k`.
+fn trigger(hotkey: &HotKey) -> Option<String> {
+    let mods: Modifiers = hotkey.mods.into();
+    let mut trigger = String::new();
+    for (modifier, name) in [
+        (Modifiers::CONTROL, "CTRL"),
+        (Modifiers::ALT, "ALT"),
+        (Modifiers::SHIFT, "SHIFT"),
+        (Modifiers::META, "LOGO"),
+    ] {
+        if mods.contains(modifier) {
+            trigger.push_str(name);
+            trigger.push('+');
+        }
+    }
+    // Letters are named by their lowercase keysyms, with SHIFT as a modifier of its own
+    let key = match hotkey.display_key() {
+        KbKey::Character(c) => KbKey::Character(c.to_lowercase()),
+        key => key,
+    };
+    trigger.push_str(&xkb::keysym_name(&key)?);
+    Some(trigger)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::RawMods;
+
+    #[test]
+    fn triggers() {
+        let trigger_of = |mods, key: &str| trigger(&HotKey::new(mods, key));
+        assert_eq!(trigger_of(RawMods::CtrlShift, "K").unwrap(), "CTRL+SHIFT+k");
+        assert_eq!(trigger_of(RawMods::AltMeta, ",").unwrap(), "ALT+LOGO+comma");
+        assert_eq!(trigger_of(RawMods::None, "ab"), None);
+        let f4 = HotKey::new(RawMods::Ctrl, KbKey::F4);
+        assert_eq!(trigger(&f4).unwrap(), "CTRL+F4");
+    }
+}
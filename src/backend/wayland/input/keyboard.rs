@@ -73,6 +73,15 @@ impl WaylandState {
     fn keyboard(&mut self, data: &KeyboardUserData) -> &mut KeyboardState {
         keyboard(&mut self.input_states, data)
     }
+    /// Translate keys for `Application::key_for_code` with the keymap and layout of the given
+    /// keyboard, as the one which was most recently used.
+    fn share_key_lookup(&mut self, data: &KeyboardUserData) {
+        let Some((xkb_state, _)) = self.keyboard(data).xkb_state.as_ref() else {
+            return;
+        };
+        let key_lookup = xkb_state.key_lookup();
+        self.key_lookup.replace(key_lookup);
+    }
     /// Stop receiving events for the given keyboard
     fn delete_keyboard(&mut self, data: &KeyboardUserData) {
         let it = self.input_state(data.0);
@@ -123,6 +132,9 @@ impl Dispatch<wl_keyboard::WlKeyboard, KeyboardUserData> for WaylandState {
 
                     let keyboard = state.keyboard(data);
                     keyboard.xkb_state = Some((keymapstate, keymap));
+                    input_state(&mut state.input_states, data.0)
+                        .keyboard_layouts_changed(&mut state.windows);
+                    state.share_key_lookup(data);
                 }
                 WEnum::Value(KeymapFormat::NoKeymap) => {
                     // TODO: What's the expected behaviour here? Is this just for embedded devices?
@@ -158,16 +170,30 @@ impl Dispatch<wl_keyboard::WlKeyboard, KeyboardUserData> for WaylandState {
             wl_keyboard::Event::Enter {
                 serial: _,
                 surface,
-                // TODO: How should we handle `keys`?
-                keys: _,
+                keys,
             } => {
                 let seat = input_state(&mut state.input_states, data.0);
+                if let Some((xkb_state, _)) = seat
+                    .keyboard_state
+                    .as_mut()
+                    .and_then(|keyboard| keyboard.xkb_state.as_mut())
+                {
+                    // The keys which were already held down, as native-endian evdev scancodes
+                    let scancodes = keys
+                        .chunks_exact(4)
+                        .map(|key| u32::from_ne_bytes(key.try_into().unwrap()) + 8);
+                    xkb_state.set_pressed_keys(scancodes);
+                }
                 seat.window_focus_enter(&mut state.windows, WindowId::of_surface(&surface));
+                state.share_key_lookup(data);
             }
             wl_keyboard::Event::Leave { .. } => {
                 let seat = input_state(&mut state.input_states, data.0);
                 seat.window_focus_leave(&mut state.windows);
                 if let Some(keyboard_state) = seat.keyboard_state.as_mut() {
+                    if let Some((xkb_state, _)) = keyboard_state.xkb_state.as_mut() {
+                        xkb_state.set_pressed_keys([]);
+                    }
                     if let Some((token, _)) = keyboard_state.repeat_details.take() {
                         state.loop_handle.remove(token);
                     }
@@ -193,11 +219,16 @@ impl Dispatch<wl_keyboard::WlKeyboard, KeyboardUserData> for WaylandState {
                     base_layout: group,
                     latched_layout: 0,
                     locked_layout: 0,
-                })
+                });
+                let seat = input_state(&mut state.input_states, data.0);
+                seat.keyboard_state_changed(&state.windows);
+                // Switching layouts changes the group
+                seat.keyboard_layouts_changed(&mut state.windows);
+                state.share_key_lookup(data);
             }
             wl_keyboard::Event::Key {
                 serial: _,
-                time: _, // TODO: Report the time of the event to the keyboard
+                time,
                 key,
                 state: key_state,
             } => {
@@ -214,7 +245,8 @@ impl Dispatch<wl_keyboard::WlKeyboard, KeyboardUserData> for WaylandState {
                     WEnum::Unknown(_) => unreachable!(),
                 };
 
-                seat.handle_key_event(scancode, key_state, false, &mut state.windows);
+                let time = state.event_clock.instant(time);
+                seat.handle_key_event(scancode, key_state, false, time, &mut state.windows);
                 let keyboard_info = seat.keyboard_state.as_mut().unwrap();
                 match keyboard_info.repeat_settings {
                     RepeatInfo::Repeat { delay, .. } => {
@@ -286,6 +318,7 @@ fn handle_repeat(
                         scancode,
                         KeyState::Down,
                         true,
+                        deadline,
                         &mut state.windows,
                     );
                     let keyboard_info = seat.keyboard_state.as_mut().unwrap();
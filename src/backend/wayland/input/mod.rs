@@ -6,30 +6,39 @@ use std::{
 
 use crate::{
     backend::shared::xkb::{xkb_simulate_input, KeyboardHandled},
-    text::InputHandler,
-    Counter, TextFieldToken, WinHandler,
+    text::{InputHandler, InputProperties},
+    Counter, Modifiers, PointerConstraint, TextFieldToken, WinHandler,
 };
 
-use self::{keyboard::KeyboardState, text_input::InputState};
+use self::{
+    keyboard::KeyboardState, pointer::PointerState, text_input::InputState, touch::TouchState,
+};
 
 use super::{
     window::{WaylandWindowState, WindowId},
     WaylandState,
 };
 
+use instant::Instant;
 use keyboard_types::KeyState;
 use smithay_client_toolkit::{
     delegate_seat,
     reexports::{
-        client::{protocol::wl_seat, Connection, QueueHandle},
+        client::{
+            protocol::{wl_data_device::WlDataDevice, wl_seat},
+            Connection, QueueHandle,
+        },
         protocols::wp::text_input::zv3::client::zwp_text_input_v3,
     },
     seat::SeatHandler,
 };
 
 mod keyboard;
+mod pointer;
 mod text_input;
+mod touch;
 
+pub(super) use pointer::DefaultCursor;
 pub(super) use text_input::TextInputManagerData;
 
 #[derive(Debug)]
@@ -78,8 +87,12 @@ pub(super) struct SeatInfo {
     id: SeatName,
     seat: wl_seat::WlSeat,
     keyboard_state: Option<KeyboardState>,
+    pointer_state: Option<PointerState>,
+    touch_state: Option<TouchState>,
     input_state: Option<InputState>,
     keyboard_focused: Option<WindowId>,
+    /// Used to start drags from this seat
+    data_device: Option<WlDataDevice>,
 
     text_field_owner: TextFieldOwner,
 }
@@ -117,6 +130,9 @@ pub(in crate::backend::wayland) struct TextInputProperties {
     /// to what they previously were. This is only set to true by the application
     pub active_text_field_updated: bool,
     pub active_text_layout_changed: bool,
+    /// The properties of `active_text_field`, which are sent to the compositor as its content type
+    pub active_input_properties: InputProperties,
+    pub next_input_properties: InputProperties,
 }
 
 pub(in crate::backend::wayland) type TextInputCell = Rc<Cell<TextInputProperties>>;
@@ -151,6 +167,10 @@ impl SeatInfo {
                 input_state.remove_field();
             }
             window.set_input_seat(self.id);
+            self.share_keyboard_state(window);
+            self.share_keyboard_layouts(window);
+            #[cfg(feature = "accesskit")]
+            window.update_accesskit_focus(true);
             let mut handler = window_handler(window);
             handler.0.got_focus();
             self.keyboard_focused = Some(new_window);
@@ -168,6 +188,9 @@ impl SeatInfo {
             let window = windows.get_mut(&old_focus);
             if let Some(window) = window {
                 window.remove_input_seat(self.id);
+                window.set_keyboard_state(Modifiers::empty(), &[]);
+                #[cfg(feature = "accesskit")]
+                window.update_accesskit_focus(false);
                 let TextFieldDetails(handler, props) = window_handler(window);
                 handler.lost_focus();
                 let props = props.get();
@@ -208,6 +231,7 @@ impl SeatInfo {
                     // text field updating window method. Because of that, we synchronise which field will be active now
                     props = props_cell.get();
                     props.active_text_field = props.next_text_field;
+                    props.active_input_properties = props.next_input_properties;
                     props_cell.set(props);
                 }
             }
@@ -232,6 +256,7 @@ impl SeatInfo {
                             // might have cleared the field and disabled the text input, if it had any state
                             // See the comment there for explanation
                             input_state.set_field_if_needed(field);
+                            input_state.set_properties(props.active_input_properties);
 
                             let mut ime = handler.acquire_input_lock(field, false);
                             input_state
@@ -349,6 +374,73 @@ impl SeatInfo {
         self.text_field_owner = TextFieldOwner::Neither;
     }
 
+    /// The data device and serial to use to start a drag from `window`, if this seat's
+    /// pointer is currently holding a button over it
+    pub(super) fn drag_grab(&self, window: &WindowId) -> Option<(WlDataDevice, u32)> {
+        let pointer = self.pointer_state.as_ref()?;
+        if pointer.focus.as_ref() != Some(window) {
+            return None;
+        }
+        Some((self.data_device.clone()?, pointer.grab_serial?))
+    }
+
+    /// The modifiers which are active on the keyboard of this seat
+    fn modifiers(&self) -> Modifiers {
+        self.keyboard_state
+            .as_ref()
+            .and_then(|keyboard| keyboard.xkb_state.as_ref())
+            .map_or(Modifiers::empty(), |(xkb_state, _)| {
+                xkb_state.active_modifiers()
+            })
+    }
+
+    /// Tell `window` which modifiers and keys are held on the keyboard of this seat, for
+    /// `WindowHandle::modifiers` and `WindowHandle::pressed_keys`.
+    fn share_keyboard_state(&self, window: &WaylandWindowState) {
+        match self
+            .keyboard_state
+            .as_ref()
+            .and_then(|keyboard| keyboard.xkb_state.as_ref())
+        {
+            Some((xkb_state, _)) => {
+                window.set_keyboard_state(xkb_state.active_modifiers(), xkb_state.pressed_keys())
+            }
+            None => window.set_keyboard_state(Modifiers::empty(), &[]),
+        }
+    }
+
+    /// Update the keyboard state of the focused window, after it changed.
+    fn keyboard_state_changed(&self, windows: &Windows) {
+        let focused = self.keyboard_focused.as_ref();
+        if let Some(window) = focused.and_then(|window| windows.get(window)) {
+            self.share_keyboard_state(window);
+        }
+    }
+
+    /// Tell `window` the layouts of the keyboard of this seat, for
+    /// `WindowHandle::keyboard_layouts`, and notify its handler if they changed.
+    fn share_keyboard_layouts(&self, window: &mut WaylandWindowState) {
+        let Some((xkb_state, _)) = self
+            .keyboard_state
+            .as_ref()
+            .and_then(|keyboard| keyboard.xkb_state.as_ref())
+        else {
+            return;
+        };
+        if window.set_keyboard_layouts(xkb_state.layouts()) {
+            window.handler.keyboard_layout_changed();
+        }
+    }
+
+    /// Update the keyboard layouts of the focused window, after the keymap or the active
+    /// layout may have changed.
+    fn keyboard_layouts_changed(&self, windows: &mut Windows) {
+        let focused = self.keyboard_focused.as_ref();
+        if let Some(window) = focused.and_then(|window| windows.get_mut(window)) {
+            self.share_keyboard_layouts(window);
+        }
+    }
+
     /// Stop receiving events for the keyboard of this seat
     fn destroy_keyboard(&mut self) {
         self.keyboard_state = None;
@@ -365,6 +457,7 @@ impl SeatInfo {
         scancode: u32,
         key_state: KeyState,
         is_repeat: bool,
+        time: Instant,
         windows: &mut Windows,
     ) {
         let Some(window) = self.keyboard_focused.as_ref() else {
@@ -382,7 +475,9 @@ impl SeatInfo {
             .expect("Has xkb state by the time keyboard events are arriving")
             .0;
         let keysym = xkb_state.get_one_sym(scancode);
-        let event = xkb_state.key_event(scancode, keysym, key_state, is_repeat);
+        let mut event = xkb_state.key_event(scancode, keysym, key_state, is_repeat);
+        event.time = Some(time);
+        self.keyboard_state_changed(windows);
 
         let Some(mut handler) = handler(windows, window) else {
             return;
@@ -525,12 +620,19 @@ pub(super) fn input_state(seats: &mut [SeatInfo], name: SeatName) -> &mut SeatIn
 impl WaylandState {
     fn handle_new_seat(&mut self, seat: wl_seat::WlSeat) {
         let id = SeatName(SEAT_COUNTER.next());
+        let data_device = self
+            .data_device_manager
+            .as_ref()
+            .map(|manager| manager.get_data_device(&seat, &self.wayland_queue, ()));
         let new_info = SeatInfo {
             id,
             seat,
             keyboard_state: None,
+            pointer_state: None,
+            touch_state: None,
             input_state: None,
             keyboard_focused: None,
+            data_device,
             text_field_owner: TextFieldOwner::Neither,
         };
         let idx = self.input_states.len();
@@ -542,6 +644,64 @@ impl WaylandState {
             .map(|text_input| InputState::new(text_input, &input.seat, &self.wayland_queue, id));
     }
 
+    /// Lock or confine the pointer of every seat to `window`.
+    pub(super) fn set_pointer_constraint(
+        &mut self,
+        window: &WindowId,
+        constraint: PointerConstraint,
+    ) {
+        let pointers = self
+            .input_states
+            .iter_mut()
+            .filter_map(|seat| seat.pointer_state.as_mut());
+        if constraint == PointerConstraint::None {
+            pointers.for_each(|pointer| pointer.remove_constraint(window));
+            return;
+        }
+        let Some(constraints) = self.pointer_constraints.as_ref() else {
+            tracing::warn!("the compositor doesn't support zwp_pointer_constraints_v1");
+            return;
+        };
+        let Some(surface) = self.windows.get(window).map(|window| window.wl_surface()) else {
+            return;
+        };
+        for pointer in pointers {
+            pointer.constrain(
+                &self.wayland_queue,
+                window,
+                &surface,
+                constraint,
+                constraints,
+                self.relative_pointer_manager.as_ref(),
+            );
+        }
+    }
+
+    /// Show or hide the cursor whilst it is over `window`.
+    pub(super) fn set_cursor_visible(&mut self, window: &WindowId, visible: bool) {
+        let Some(window_state) = self.windows.get_mut(window) else {
+            return;
+        };
+        if std::mem::replace(&mut window_state.cursor_visible, visible) == visible {
+            return;
+        }
+        if self.default_cursor.is_none() {
+            self.default_cursor = self.shm.as_ref().and_then(|shm| {
+                DefaultCursor::load(
+                    &self.connection,
+                    shm,
+                    self.compositor_state.wl_compositor(),
+                    &self.wayland_queue,
+                )
+            });
+        }
+        for seat in &self.input_states {
+            if let Some(pointer) = seat.pointer_state.as_ref() {
+                pointer.set_cursor_visible(window, visible, self.default_cursor.as_mut());
+            }
+        }
+    }
+
     pub(super) fn initial_seats(&mut self) {
         for seat in self.seats.seats() {
             self.handle_new_seat(seat)
@@ -565,6 +725,7 @@ impl SeatHandler for WaylandState {
         seat: wl_seat::WlSeat,
         capability: smithay_client_toolkit::seat::Capability,
     ) {
+        let pointer_gestures = self.pointer_gestures.clone();
         let seat_info = self.info_of_seat(&seat);
 
         match capability {
@@ -572,8 +733,14 @@ impl SeatHandler for WaylandState {
                 let state = KeyboardState::new(qh, seat_info.id, seat);
                 seat_info.keyboard_state = Some(state);
             }
-            smithay_client_toolkit::seat::Capability::Pointer => {}
-            smithay_client_toolkit::seat::Capability::Touch => {}
+            smithay_client_toolkit::seat::Capability::Pointer => {
+                let state = PointerState::new(qh, seat_info.id, seat, pointer_gestures.as_ref());
+                seat_info.pointer_state = Some(state);
+            }
+            smithay_client_toolkit::seat::Capability::Touch => {
+                let state = TouchState::new(qh, seat_info.id, seat);
+                seat_info.touch_state = Some(state);
+            }
             it => tracing::warn!(?seat, "Unknown seat capability {it}"),
         }
     }
@@ -588,8 +755,8 @@ impl SeatHandler for WaylandState {
         let state = self.info_of_seat(&seat);
         match capability {
             smithay_client_toolkit::seat::Capability::Keyboard => state.destroy_keyboard(),
-            smithay_client_toolkit::seat::Capability::Pointer => {}
-            smithay_client_toolkit::seat::Capability::Touch => {}
+            smithay_client_toolkit::seat::Capability::Pointer => state.pointer_state = None,
+            smithay_client_toolkit::seat::Capability::Touch => state.touch_state = None,
             it => tracing::info!(?seat, "Removed unknown seat capability {it}"),
         }
     }
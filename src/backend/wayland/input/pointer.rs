@@ -0,0 +1,763 @@
+use instant::Instant;
+use smithay_client_toolkit::reexports::{
+    client::{
+        protocol::{
+            wl_compositor::WlCompositor, wl_pointer, wl_seat, wl_shm::WlShm, wl_surface::WlSurface,
+        },
+        Connection, Dispatch, Proxy, QueueHandle, WEnum,
+    },
+    protocols::wp::{
+        pointer_constraints::zv1::client::{
+            zwp_confined_pointer_v1::ZwpConfinedPointerV1,
+            zwp_locked_pointer_v1::ZwpLockedPointerV1,
+            zwp_pointer_constraints_v1::{Lifetime, ZwpPointerConstraintsV1},
+        },
+        pointer_gestures::zv1::client::{
+            zwp_pointer_gesture_pinch_v1::{self, ZwpPointerGesturePinchV1},
+            zwp_pointer_gesture_swipe_v1::{self, ZwpPointerGestureSwipeV1},
+            zwp_pointer_gestures_v1::ZwpPointerGesturesV1,
+        },
+        relative_pointer::zv1::client::{
+            zwp_relative_pointer_manager_v1::ZwpRelativePointerManagerV1,
+            zwp_relative_pointer_v1::{self, ZwpRelativePointerV1},
+        },
+    },
+};
+use wayland_cursor::CursorTheme;
+
+use crate::backend::shared::linux::event_clock::EventClock;
+use crate::backend::wayland::window::{WaylandWindowState, WindowId};
+use crate::common_util::ClickCounter;
+use crate::gesture::{Gesture, GestureEvent, GesturePhase};
+use crate::kurbo::{Point, Vec2};
+use crate::pointer::{
+    Angle, MouseInfo, PointerButton, PointerButtons, PointerConstraint, PointerEvent, PointerId,
+    PointerType, RelativeMotionEvent, ScrollDeltaUnit, ScrollPhase, ScrollSource,
+};
+use crate::Modifiers;
+
+use super::{input_state, SeatName, WaylandState, Windows};
+
+// From linux/input-event-codes.h
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+const BTN_MIDDLE: u32 = 0x112;
+const BTN_SIDE: u32 = 0x113;
+const BTN_EXTRA: u32 = 0x114;
+const BTN_FORWARD: u32 = 0x115;
+const BTN_BACK: u32 = 0x116;
+
+/// The size of cursors, unless `XCURSOR_SIZE` says otherwise
+const CURSOR_SIZE: u32 = 24;
+
+/// The seat identifier of this pointer
+struct PointerUserData(SeatName);
+
+/// The seat identifier of the pointer which this gesture object belongs to
+struct GestureUserData(SeatName);
+
+/// The seat identifier of the pointer whose relative motion this reports
+struct RelativePointerUserData(SeatName);
+
+pub(super) struct PointerState {
+    pointer: wl_pointer::WlPointer,
+    seat: SeatName,
+    /// The window which the pointer is currently over
+    pub(super) focus: Option<WindowId>,
+    /// The number of buttons which are currently held down
+    buttons_held: u32,
+    /// The serial of the button press which started the current implicit grab, if any.
+    ///
+    /// Requests such as `wl_data_device.start_drag` must be made with the serial of the
+    /// event which caused them, and compositors only accept serials of an ongoing grab.
+    pub(super) grab_serial: Option<u32>,
+    /// The position of the pointer in the focused window
+    pos: Point,
+    /// When the latest event of the pointer happened
+    time: Option<Instant>,
+    /// The buttons we know about which are currently held down
+    buttons: PointerButtons,
+    click_counter: ClickCounter,
+    /// The scrolling reported since the last `frame` event
+    scroll: ScrollFrame,
+    /// Whether a scroll gesture (which `axis_stop` ends) is in progress
+    scrolling: bool,
+    /// Used to receive touchpad gestures, if the compositor supports them
+    gestures: Option<(ZwpPointerGesturePinchV1, ZwpPointerGestureSwipeV1)>,
+    /// The number of fingers in the ongoing gesture
+    gesture_fingers: u32,
+    /// The scale of the ongoing pinch gesture, as of its previous event
+    pinch_scale: f64,
+    /// The serial of the latest `enter` event, which changing the cursor needs
+    enter_serial: u32,
+    /// The windows which this pointer is locked or confined to, whilst they have focus
+    constraints: Vec<(WindowId, ActiveConstraint)>,
+    /// Reports the movement of the pointer whilst it is constrained
+    relative_pointer: Option<ZwpRelativePointerV1>,
+}
+
+enum ActiveConstraint {
+    Locked(ZwpLockedPointerV1),
+    Confined(ZwpConfinedPointerV1),
+}
+
+impl Drop for ActiveConstraint {
+    fn drop(&mut self) {
+        match self {
+            ActiveConstraint::Locked(locked) => locked.destroy(),
+            ActiveConstraint::Confined(confined) => confined.destroy(),
+        }
+    }
+}
+
+/// The axis events which make up a single scroll.
+#[derive(Default)]
+struct ScrollFrame {
+    /// The distance scrolled, in surface coordinates
+    delta: Vec2,
+    /// The distance scrolled in wheel detents, if the compositor told us
+    detents: Option<Vec2>,
+    source: Option<ScrollSource>,
+    stop: bool,
+}
+
+impl ScrollFrame {
+    fn is_empty(&self) -> bool {
+        self.delta == Vec2::ZERO && self.detents.is_none() && !self.stop
+    }
+
+    fn add_detents(&mut self, axis: WEnum<wl_pointer::Axis>, detents: f64) {
+        let total = self.detents.get_or_insert(Vec2::ZERO);
+        match axis {
+            WEnum::Value(wl_pointer::Axis::VerticalScroll) => total.y += detents,
+            WEnum::Value(wl_pointer::Axis::HorizontalScroll) => total.x += detents,
+            _ => tracing::warn!("unknown pointer axis {axis:?}"),
+        }
+    }
+}
+
+impl PointerState {
+    pub(super) fn new(
+        qh: &QueueHandle<WaylandState>,
+        name: SeatName,
+        seat: wl_seat::WlSeat,
+        pointer_gestures: Option<&ZwpPointerGesturesV1>,
+    ) -> Self {
+        let pointer = seat.get_pointer(qh, PointerUserData(name));
+        let gestures = pointer_gestures.map(|manager| {
+            (
+                manager.get_pinch_gesture(&pointer, qh, GestureUserData(name)),
+                manager.get_swipe_gesture(&pointer, qh, GestureUserData(name)),
+            )
+        });
+        PointerState {
+            pointer,
+            seat: name,
+            focus: None,
+            buttons_held: 0,
+            grab_serial: None,
+            pos: Point::ZERO,
+            time: None,
+            buttons: PointerButtons::new(),
+            click_counter: ClickCounter::default(),
+            scroll: ScrollFrame::default(),
+            scrolling: false,
+            gestures,
+            gesture_fingers: 0,
+            pinch_scale: 1.0,
+            enter_serial: 0,
+            constraints: Vec::new(),
+            relative_pointer: None,
+        }
+    }
+
+    /// Lock or confine this pointer to `window`, replacing its previous constraint there.
+    ///
+    /// Constraints persist, so the compositor reapplies them whenever the window regains focus.
+    pub(super) fn constrain(
+        &mut self,
+        qh: &QueueHandle<WaylandState>,
+        window: &WindowId,
+        surface: &WlSurface,
+        constraint: PointerConstraint,
+        constraints: &ZwpPointerConstraintsV1,
+        relative_pointer_manager: Option<&ZwpRelativePointerManagerV1>,
+    ) {
+        self.remove_constraint(window);
+        let active = match constraint {
+            PointerConstraint::None => None,
+            PointerConstraint::Confined => {
+                Some(ActiveConstraint::Confined(constraints.confine_pointer(
+                    surface,
+                    &self.pointer,
+                    None,
+                    Lifetime::Persistent,
+                    qh,
+                    (),
+                )))
+            }
+            PointerConstraint::Locked => Some(ActiveConstraint::Locked(constraints.lock_pointer(
+                surface,
+                &self.pointer,
+                None,
+                Lifetime::Persistent,
+                qh,
+                (),
+            ))),
+        };
+        if let Some(active) = active {
+            self.constraints.push((window.clone(), active));
+        }
+        if self.relative_pointer.is_none() && !self.constraints.is_empty() {
+            self.relative_pointer = relative_pointer_manager.map(|manager| {
+                manager.get_relative_pointer(&self.pointer, qh, RelativePointerUserData(self.seat))
+            });
+        }
+    }
+
+    /// Stop locking or confining this pointer to `window`.
+    pub(super) fn remove_constraint(&mut self, window: &WindowId) {
+        self.constraints.retain(|(id, _)| id != window);
+        if self.constraints.is_empty() {
+            if let Some(relative_pointer) = self.relative_pointer.take() {
+                relative_pointer.destroy();
+            }
+        }
+    }
+
+    /// Show or hide the cursor, if this pointer is over `window`.
+    pub(super) fn set_cursor_visible(
+        &self,
+        window: &WindowId,
+        visible: bool,
+        cursor: Option<&mut DefaultCursor>,
+    ) {
+        if self.focus.as_ref() != Some(window) {
+            return;
+        }
+        match cursor {
+            Some(cursor) if visible => cursor.show(&self.pointer, self.enter_serial),
+            // Without a cursor theme, the compositor shows its own cursor the next time the
+            // pointer enters
+            None if visible => {}
+            _ => self.pointer.set_cursor(self.enter_serial, None, 0, 0),
+        }
+    }
+
+    fn pointer_event(&self, seat: SeatName, modifiers: Modifiers) -> PointerEvent {
+        PointerEvent {
+            pointer_id: PointerId(seat.0),
+            is_primary: true,
+            pointer_type: PointerType::Mouse(MouseInfo::default()),
+            time: self.time,
+            pos: self.pos,
+            buttons: self.buttons,
+            modifiers,
+            button: PointerButton::None,
+            focus: false,
+            count: 0,
+            coalesced: Vec::new(),
+            predicted: Vec::new(),
+        }
+    }
+
+    /// Add an axis event to the scrolling of the current frame.
+    fn add_scroll(&mut self, event: wl_pointer::Event, event_clock: &mut EventClock) {
+        match event {
+            wl_pointer::Event::Axis { time, axis, value } => {
+                self.time = Some(event_clock.instant(time));
+                match axis {
+                    WEnum::Value(wl_pointer::Axis::VerticalScroll) => self.scroll.delta.y += value,
+                    WEnum::Value(wl_pointer::Axis::HorizontalScroll) => {
+                        self.scroll.delta.x += value
+                    }
+                    _ => tracing::warn!("unknown pointer axis {axis:?}"),
+                }
+            }
+            wl_pointer::Event::AxisSource { axis_source } => {
+                self.scroll.source = Some(match axis_source {
+                    WEnum::Value(wl_pointer::AxisSource::Wheel)
+                    | WEnum::Value(wl_pointer::AxisSource::WheelTilt) => ScrollSource::Wheel,
+                    WEnum::Value(wl_pointer::AxisSource::Finger) => ScrollSource::Finger,
+                    WEnum::Value(wl_pointer::AxisSource::Continuous) => ScrollSource::Continuous,
+                    _ => ScrollSource::Unknown,
+                });
+            }
+            wl_pointer::Event::AxisStop { time, .. } => {
+                self.time = Some(event_clock.instant(time));
+                self.scroll.stop = true;
+            }
+            // Only sent before version 8; `axis_value120` replaces it
+            wl_pointer::Event::AxisDiscrete { axis, discrete } => {
+                self.scroll.add_detents(axis, f64::from(discrete));
+            }
+            wl_pointer::Event::AxisValue120 { axis, value120 } => {
+                self.scroll.add_detents(axis, f64::from(value120) / 120.0);
+            }
+            _ => {}
+        }
+    }
+
+    /// Turn the axis events since the last frame into the details of a wheel event.
+    fn take_scroll(&mut self) -> Option<MouseInfo> {
+        let scroll = std::mem::take(&mut self.scroll);
+        if scroll.is_empty() {
+            return None;
+        }
+        let scroll_source = scroll.source.unwrap_or_default();
+        // Wheels don't tell us when they start or stop turning
+        let scroll_phase = if scroll_source == ScrollSource::Wheel {
+            ScrollPhase::None
+        } else if scroll.stop {
+            if !std::mem::take(&mut self.scrolling) {
+                return None;
+            }
+            ScrollPhase::Ended
+        } else if std::mem::replace(&mut self.scrolling, true) {
+            ScrollPhase::Changed
+        } else {
+            ScrollPhase::Began
+        };
+        let (wheel_delta, wheel_delta_unit) = match scroll.detents {
+            Some(detents) if scroll_source == ScrollSource::Wheel => {
+                (detents, ScrollDeltaUnit::Lines)
+            }
+            _ => (scroll.delta, ScrollDeltaUnit::Pixels),
+        };
+        Some(MouseInfo {
+            wheel_delta,
+            wheel_delta_unit,
+            scroll_phase,
+            momentum_phase: ScrollPhase::None,
+            scroll_source,
+        })
+    }
+}
+
+/// The default cursor of the user's cursor theme, which we show again after hiding the cursor.
+///
+/// Wayland has no way to ask the compositor for its own cursor back.
+pub(in crate::backend::wayland) struct DefaultCursor {
+    theme: CursorTheme,
+    surface: WlSurface,
+}
+
+impl DefaultCursor {
+    pub(in crate::backend::wayland) fn load(
+        conn: &Connection,
+        shm: &WlShm,
+        compositor: &WlCompositor,
+        qh: &QueueHandle<WaylandState>,
+    ) -> Option<Self> {
+        let theme = match CursorTheme::load(conn, shm.clone(), CURSOR_SIZE) {
+            Ok(theme) => theme,
+            Err(err) => {
+                tracing::warn!("failed to load the cursor theme: {err}");
+                return None;
+            }
+        };
+        Some(DefaultCursor {
+            theme,
+            surface: compositor.create_surface(qh, Default::default()),
+        })
+    }
+
+    /// Show this cursor for `pointer`, which entered the surface with `serial`.
+    fn show(&mut self, pointer: &wl_pointer::WlPointer, serial: u32) {
+        let Some(image) = ["default", "left_ptr"]
+            .into_iter()
+            .find_map(|name| Some(self.theme.get_cursor(name)?[0].clone()))
+        else {
+            tracing::warn!("the cursor theme has no default cursor");
+            return;
+        };
+        let (width, height) = image.dimensions();
+        let (x, y) = image.hotspot();
+        self.surface.attach(Some(&image), 0, 0);
+        self.surface.damage(0, 0, width as i32, height as i32);
+        self.surface.commit();
+        pointer.set_cursor(serial, Some(&self.surface), x as i32, y as i32);
+    }
+}
+
+impl Drop for DefaultCursor {
+    fn drop(&mut self) {
+        self.surface.destroy();
+    }
+}
+
+impl Drop for PointerState {
+    fn drop(&mut self) {
+        if let Some((pinch, swipe)) = self.gestures.take() {
+            pinch.destroy();
+            swipe.destroy();
+        }
+        self.constraints.clear();
+        if let Some(relative_pointer) = self.relative_pointer.take() {
+            relative_pointer.destroy();
+        }
+        if self.pointer.version() >= 3 {
+            self.pointer.release()
+        }
+    }
+}
+
+impl Dispatch<wl_pointer::WlPointer, PointerUserData> for WaylandState {
+    fn event(
+        state: &mut Self,
+        proxy: &wl_pointer::WlPointer,
+        event: <wl_pointer::WlPointer as Proxy>::Event,
+        data: &PointerUserData,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let seat = input_state(&mut state.input_states, data.0);
+        let modifiers = seat.modifiers();
+        let Some(pointer) = seat.pointer_state.as_mut() else {
+            return;
+        };
+        // Before version 5, there are no frame events, so every event stands alone
+        let mut end_of_frame = proxy.version() < 5;
+        let windows = &mut state.windows;
+        let default_cursor = &mut state.default_cursor;
+        let event_clock = &mut state.event_clock;
+        match event {
+            wl_pointer::Event::Enter {
+                serial,
+                surface,
+                surface_x,
+                surface_y,
+            } => {
+                pointer.focus = Some(WindowId::of_surface(&surface));
+                pointer.enter_serial = serial;
+                pointer.pos = Point::new(surface_x, surface_y);
+                // Entering has no timestamp of its own
+                pointer.time = Some(Instant::now());
+                pointer.scrolling = false;
+                let event = pointer.pointer_event(data.0, modifiers);
+                if let Some(window) = focused_window(windows, pointer) {
+                    // The cursor is undefined when the pointer enters, so we show the one we
+                    // want if we have ever changed it
+                    match default_cursor {
+                        _ if !window.cursor_visible => proxy.set_cursor(serial, None, 0, 0),
+                        Some(cursor) => cursor.show(proxy, serial),
+                        None => {}
+                    }
+                    window.set_pointer_state(pointer.buttons, Some(pointer.pos));
+                    window.handler.pointer_move(&event);
+                }
+            }
+            wl_pointer::Event::Leave { .. } => {
+                // The pointer only leaves whilst a button is held if the compositor has taken
+                // it, such as to move the window, so we won't see the buttons being released
+                let cancelled =
+                    (pointer.buttons_held > 0).then(|| pointer.pointer_event(data.0, modifiers));
+                if let Some(window) = focused_window(windows, pointer) {
+                    window.set_pointer_state(PointerButtons::new(), None);
+                    if let Some(event) = cancelled {
+                        window.handler.pointer_cancel(&event);
+                    }
+                    window.handler.pointer_leave();
+                }
+                pointer.focus = None;
+                pointer.buttons_held = 0;
+                pointer.buttons.clear();
+                pointer.grab_serial = None;
+            }
+            wl_pointer::Event::Motion {
+                time,
+                surface_x,
+                surface_y,
+            } => {
+                pointer.pos = Point::new(surface_x, surface_y);
+                pointer.time = Some(event_clock.instant(time));
+                let event = pointer.pointer_event(data.0, modifiers);
+                if let Some(window) = focused_window(windows, pointer) {
+                    window.set_pointer_state(pointer.buttons, Some(pointer.pos));
+                    window.handler.pointer_move(&event);
+                }
+            }
+            wl_pointer::Event::Button {
+                serial,
+                time,
+                button,
+                state: button_state,
+            } => {
+                pointer.time = Some(event_clock.instant(time));
+                let button = pointer_button(button);
+                match button_state {
+                    WEnum::Value(wl_pointer::ButtonState::Pressed) => {
+                        pointer.buttons_held += 1;
+                        pointer.grab_serial = Some(serial);
+                        pointer.buttons.insert(button);
+                        let mut event = pointer.pointer_event(data.0, modifiers);
+                        event.button = button;
+                        event.count = pointer.click_counter.count_for_click(pointer.pos);
+                        if let Some(window) = focused_window(windows, pointer) {
+                            window.set_pointer_state(pointer.buttons, Some(pointer.pos));
+                            window.handler.pointer_down(&event);
+                        }
+                    }
+                    WEnum::Value(wl_pointer::ButtonState::Released) => {
+                        pointer.buttons_held = pointer.buttons_held.saturating_sub(1);
+                        if pointer.buttons_held == 0 {
+                            pointer.grab_serial = None;
+                        }
+                        pointer.buttons.remove(button);
+                        let mut event = pointer.pointer_event(data.0, modifiers);
+                        event.button = button;
+                        if let Some(window) = focused_window(windows, pointer) {
+                            window.set_pointer_state(pointer.buttons, Some(pointer.pos));
+                            window.handler.pointer_up(&event);
+                        }
+                    }
+                    _ => tracing::warn!("unknown pointer button state {button_state:?}"),
+                }
+            }
+            event @ (wl_pointer::Event::Axis { .. }
+            | wl_pointer::Event::AxisSource { .. }
+            | wl_pointer::Event::AxisStop { .. }
+            | wl_pointer::Event::AxisDiscrete { .. }
+            | wl_pointer::Event::AxisValue120 { .. }) => pointer.add_scroll(event, event_clock),
+            wl_pointer::Event::Frame => end_of_frame = true,
+            _ => {}
+        }
+        if end_of_frame {
+            if let Some(mouse_info) = pointer.take_scroll() {
+                let mut event = pointer.pointer_event(data.0, modifiers);
+                event.pointer_type = PointerType::Mouse(mouse_info);
+                if let Some(window) = focused_window(windows, pointer) {
+                    window.handler.wheel(&event);
+                }
+            }
+        }
+    }
+}
+
+impl Dispatch<ZwpPointerGesturesV1, ()> for WaylandState {
+    fn event(
+        _: &mut Self,
+        _: &ZwpPointerGesturesV1,
+        _: <ZwpPointerGesturesV1 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        // zwp_pointer_gestures_v1 has no events
+    }
+}
+
+impl Dispatch<ZwpPointerGesturePinchV1, GestureUserData> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _: &ZwpPointerGesturePinchV1,
+        event: zwp_pointer_gesture_pinch_v1::Event,
+        data: &GestureUserData,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let seat = input_state(&mut state.input_states, data.0);
+        let modifiers = seat.modifiers();
+        let Some(pointer) = seat.pointer_state.as_mut() else {
+            return;
+        };
+        let (phase, scale, rotation, pan) = match event {
+            zwp_pointer_gesture_pinch_v1::Event::Begin { fingers, .. } => {
+                pointer.gesture_fingers = fingers;
+                pointer.pinch_scale = 1.0;
+                (GesturePhase::Began, 1.0, 0.0, Vec2::ZERO)
+            }
+            zwp_pointer_gesture_pinch_v1::Event::Update {
+                dx,
+                dy,
+                scale,
+                rotation,
+                ..
+            } => (GesturePhase::Changed, scale, rotation, Vec2::new(dx, dy)),
+            zwp_pointer_gesture_pinch_v1::Event::End { cancelled, .. } => {
+                let phase = if cancelled != 0 {
+                    GesturePhase::Cancelled
+                } else {
+                    GesturePhase::Ended
+                };
+                (phase, pointer.pinch_scale, 0.0, Vec2::ZERO)
+            }
+            _ => return,
+        };
+        // `zoom` takes the change of scale since the previous event
+        let previous_scale = std::mem::replace(&mut pointer.pinch_scale, scale);
+        let event = GestureEvent {
+            gesture: Gesture::Pinch {
+                scale,
+                rotation: Angle::degrees(rotation),
+                pan,
+            },
+            phase,
+            finger_count: pointer.gesture_fingers,
+            pos: pointer.pos,
+            modifiers,
+        };
+        if let Some(window) = focused_window(&mut state.windows, pointer) {
+            window.handler.gesture(&event);
+            if phase == GesturePhase::Changed && previous_scale != 0.0 {
+                window.handler.zoom(scale / previous_scale - 1.0);
+            }
+        }
+    }
+}
+
+impl Dispatch<ZwpPointerGestureSwipeV1, GestureUserData> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _: &ZwpPointerGestureSwipeV1,
+        event: zwp_pointer_gesture_swipe_v1::Event,
+        data: &GestureUserData,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let seat = input_state(&mut state.input_states, data.0);
+        let modifiers = seat.modifiers();
+        let Some(pointer) = seat.pointer_state.as_mut() else {
+            return;
+        };
+        let (phase, delta) = match event {
+            zwp_pointer_gesture_swipe_v1::Event::Begin { fingers, .. } => {
+                pointer.gesture_fingers = fingers;
+                (GesturePhase::Began, Vec2::ZERO)
+            }
+            zwp_pointer_gesture_swipe_v1::Event::Update { dx, dy, .. } => {
+                (GesturePhase::Changed, Vec2::new(dx, dy))
+            }
+            zwp_pointer_gesture_swipe_v1::Event::End { cancelled, .. } => {
+                let phase = if cancelled != 0 {
+                    GesturePhase::Cancelled
+                } else {
+                    GesturePhase::Ended
+                };
+                (phase, Vec2::ZERO)
+            }
+            _ => return,
+        };
+        let event = GestureEvent {
+            gesture: Gesture::Swipe { delta },
+            phase,
+            finger_count: pointer.gesture_fingers,
+            pos: pointer.pos,
+            modifiers,
+        };
+        if let Some(window) = focused_window(&mut state.windows, pointer) {
+            window.handler.gesture(&event);
+        }
+    }
+}
+
+impl Dispatch<ZwpPointerConstraintsV1, ()> for WaylandState {
+    fn event(
+        _: &mut Self,
+        _: &ZwpPointerConstraintsV1,
+        _: <ZwpPointerConstraintsV1 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        // zwp_pointer_constraints_v1 has no events
+    }
+}
+
+impl Dispatch<ZwpLockedPointerV1, ()> for WaylandState {
+    fn event(
+        _: &mut Self,
+        _: &ZwpLockedPointerV1,
+        _: <ZwpLockedPointerV1 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        // The lock follows the window's focus, which the application already hears about
+    }
+}
+
+impl Dispatch<ZwpConfinedPointerV1, ()> for WaylandState {
+    fn event(
+        _: &mut Self,
+        _: &ZwpConfinedPointerV1,
+        _: <ZwpConfinedPointerV1 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        // The confinement follows the window's focus, as locks do
+    }
+}
+
+impl Dispatch<ZwpRelativePointerManagerV1, ()> for WaylandState {
+    fn event(
+        _: &mut Self,
+        _: &ZwpRelativePointerManagerV1,
+        _: <ZwpRelativePointerManagerV1 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        // zwp_relative_pointer_manager_v1 has no events
+    }
+}
+
+impl Dispatch<ZwpRelativePointerV1, RelativePointerUserData> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _: &ZwpRelativePointerV1,
+        event: zwp_relative_pointer_v1::Event,
+        data: &RelativePointerUserData,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let seat = input_state(&mut state.input_states, data.0);
+        let Some(pointer) = seat.pointer_state.as_mut() else {
+            return;
+        };
+        if let zwp_relative_pointer_v1::Event::RelativeMotion {
+            dx,
+            dy,
+            dx_unaccel,
+            dy_unaccel,
+            ..
+        } = event
+        {
+            // Only windows which asked for a constraint want relative motion
+            let constrained = pointer
+                .constraints
+                .iter()
+                .any(|(id, _)| Some(id) == pointer.focus.as_ref());
+            if !constrained {
+                return;
+            }
+            let event = RelativeMotionEvent {
+                pointer_id: PointerId(data.0 .0),
+                delta: Vec2::new(dx, dy),
+                unaccelerated_delta: Vec2::new(dx_unaccel, dy_unaccel),
+            };
+            if let Some(window) = focused_window(&mut state.windows, pointer) {
+                window.handler.relative_pointer_motion(&event);
+            }
+        }
+    }
+}
+
+fn focused_window<'a>(
+    windows: &'a mut Windows,
+    pointer: &PointerState,
+) -> Option<&'a mut WaylandWindowState> {
+    windows.get_mut(pointer.focus.as_ref()?)
+}
+
+fn pointer_button(button: u32) -> PointerButton {
+    match button {
+        BTN_LEFT => PointerButton::Primary,
+        BTN_RIGHT => PointerButton::Secondary,
+        BTN_MIDDLE => PointerButton::Auxiliary,
+        BTN_SIDE | BTN_BACK => PointerButton::X1,
+        BTN_EXTRA | BTN_FORWARD => PointerButton::X2,
+        _ => PointerButton::None,
+    }
+}
@@ -2,13 +2,16 @@ use smithay_client_toolkit::reexports::{
     client::{protocol::wl_seat, Dispatch, QueueHandle},
     protocols::wp::text_input::zv3::client::{
         zwp_text_input_manager_v3::ZwpTextInputManagerV3,
-        zwp_text_input_v3::{self, ZwpTextInputV3},
+        zwp_text_input_v3::{self, ContentHint, ContentPurpose, ZwpTextInputV3},
     },
 };
 
 use crate::{
     backend::wayland::{window::WindowId, WaylandState},
-    text::{Affinity, InputHandler, Selection},
+    text::{
+        Affinity, InputHandler, InputHints, InputProperties, InputPurpose, PreeditAttributes,
+        PreeditSegment, PreeditStyle, Selection,
+    },
     TextFieldToken,
 };
 
@@ -16,6 +19,37 @@ use super::{input_state, SeatInfo, SeatName};
 
 struct InputUserData(SeatName);
 
+/// The Wayland content type for a text field with `properties`.
+fn content_type(properties: InputProperties) -> (ContentHint, ContentPurpose) {
+    let InputProperties { purpose, hints } = properties;
+    let mut hint = ContentHint::Completion | ContentHint::Spellcheck;
+    if hints.contains(InputHints::NO_AUTOCORRECT) {
+        hint = ContentHint::None;
+    }
+    if !hints.contains(InputHints::NO_AUTO_CAPITALIZE) {
+        hint |= ContentHint::AutoCapitalization;
+    }
+    if hints.contains(InputHints::MULTILINE) {
+        hint |= ContentHint::Multiline;
+    }
+    if hints.contains(InputHints::SENSITIVE) {
+        hint |= ContentHint::SensitiveData;
+    }
+    let purpose = match purpose {
+        InputPurpose::Normal => ContentPurpose::Normal,
+        InputPurpose::Password => {
+            // Completions of a password would give it away
+            hint = ContentHint::HiddenText | ContentHint::SensitiveData;
+            ContentPurpose::Password
+        }
+        InputPurpose::Email => ContentPurpose::Email,
+        InputPurpose::Number => ContentPurpose::Number,
+        InputPurpose::Phone => ContentPurpose::Phone,
+        InputPurpose::Url => ContentPurpose::Url,
+    };
+    (hint, purpose)
+}
+
 pub(super) struct InputState {
     text_input: ZwpTextInputV3,
 
@@ -51,6 +85,8 @@ pub(super) struct InputState {
     ///
     /// [set_surrounding_text]: https://wayland.app/protocols/text-input-unstable-v3#zwp_text_input_v3:request:set_surrounding_text
     buffer_start: Option<usize>,
+    /// The properties of the field, sent as the content type
+    properties: InputProperties,
 }
 
 impl InputState {
@@ -74,6 +110,7 @@ impl InputState {
 
             buffer_start: None,
             token: None,
+            properties: InputProperties::default(),
         }
     }
 
@@ -102,6 +139,10 @@ impl InputState {
         }
     }
 
+    pub(super) fn set_properties(&mut self, properties: InputProperties) {
+        self.properties = properties;
+    }
+
     pub(super) fn remove_field(&mut self) {
         tracing::warn!("disabling text input");
         self.token = None;
@@ -115,7 +156,8 @@ impl InputState {
         cause: zwp_text_input_v3::ChangeCause,
     ) {
         tracing::trace!("Sending Text Input state to Wayland compositor");
-        // input_state.text_input.set_content_type();
+        let (hint, purpose) = content_type(self.properties);
+        self.text_input.set_content_type(hint, purpose);
         let selection = handler.selection();
 
         let selection_range = selection.range();
@@ -125,6 +167,10 @@ impl InputState {
         let mut complete_range = start_line.start..end_line.end;
         self.buffer_start = None;
         'can_set_surrounding_text: {
+            // Passwords and sensitive text must never reach the input method
+            if self.properties.is_secure() {
+                break 'can_set_surrounding_text;
+            }
             // Wayland strings cannot be longer than 4000 bytes
             // Give some margin for error
             if complete_range.len() > 3800 {
@@ -299,12 +345,19 @@ impl InputState {
             let selection_start = range.start;
             handler.replace_range(range, &preedit);
             handler.set_composition_range(Some(selection_start..(selection_start + preedit.len())));
-            let selection_start = selection_start as i32;
+            let attributes =
+                preedit_attributes(preedit.len(), self.new_cursor_begin, self.new_cursor_end);
             // 6. Place cursor inside preedit text.
+            // A hidden cursor is left at the end of the preedit text
+            let cursor = attributes.cursor.unwrap_or(preedit.len());
+            let anchor = attributes
+                .cursor
+                .map_or(cursor, |_| self.new_cursor_begin as usize);
             handler.set_selection(Selection::new(
-                (selection_start + self.new_cursor_begin) as usize,
-                (selection_start + self.new_cursor_end) as usize,
+                selection_start + anchor,
+                selection_start + cursor,
             ));
+            handler.set_preedit_attributes(attributes);
             has_preedit = true;
         } else {
             handler.set_composition_range(None);
@@ -317,6 +370,31 @@ impl InputState {
     }
 }
 
+/// The attributes of a preedit string of `len` bytes, with the cursor from the `preedit_string`
+/// event.
+///
+/// text-input-v3 has no styling for the preedit string, but input methods send the clause being
+/// converted as the cursor range, so that is highlighted and the rest of the text underlined.
+/// Both ends of the cursor are -1 if it should be hidden.
+fn preedit_attributes(len: usize, cursor_begin: i32, cursor_end: i32) -> PreeditAttributes {
+    let valid = |it: i32| usize::try_from(it).ok().filter(|&it| it <= len);
+    let cursor = valid(cursor_begin).zip(valid(cursor_end));
+    let clause = cursor.map_or(len..len, |(begin, end)| begin.min(end)..begin.max(end));
+    let segments = [
+        (0..clause.start, PreeditStyle::Underline),
+        (clause.clone(), PreeditStyle::Highlight),
+        (clause.end..len, PreeditStyle::Underline),
+    ]
+    .into_iter()
+    .filter(|(range, _)| !range.is_empty())
+    .map(|(range, style)| PreeditSegment { range, style })
+    .collect();
+    PreeditAttributes {
+        cursor: cursor.map(|(_, end)| end),
+        segments,
+    }
+}
+
 impl WaylandState {
     fn text_input(&mut self, data: &InputUserData) -> &mut InputState {
         text_input(&mut self.input_states, data)
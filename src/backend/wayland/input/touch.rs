@@ -0,0 +1,169 @@
+use instant::Instant;
+use smithay_client_toolkit::reexports::client::{
+    protocol::{wl_seat, wl_touch},
+    Connection, Dispatch, Proxy, QueueHandle,
+};
+
+use crate::backend::wayland::window::WindowId;
+use crate::kurbo::{Point, Size};
+use crate::pointer::{
+    PointerButton, PointerButtons, PointerEvent, PointerId, PointerType, TouchInfo,
+};
+use crate::Modifiers;
+
+use super::{input_state, SeatName, WaylandState};
+
+/// The seat identifier of this touch device
+struct TouchUserData(SeatName);
+
+pub(super) struct TouchState {
+    touch: wl_touch::WlTouch,
+    /// The touch points which are currently down
+    points: Vec<TouchPoint>,
+}
+
+struct TouchPoint {
+    /// The id given to this point by the compositor, which is unique while it is down
+    id: i32,
+    /// The window which the point went down in, and which receives all of its events
+    window: WindowId,
+    pos: Point,
+    /// When the latest event of the point happened
+    time: Instant,
+    /// Whether this was the first point to go down, of those which are currently down
+    is_primary: bool,
+    /// The size of the contact, if the compositor told us
+    contact_geometry: Size,
+}
+
+impl TouchState {
+    pub(super) fn new(
+        qh: &QueueHandle<WaylandState>,
+        name: SeatName,
+        seat: wl_seat::WlSeat,
+    ) -> Self {
+        TouchState {
+            touch: seat.get_touch(qh, TouchUserData(name)),
+            points: Vec::new(),
+        }
+    }
+}
+
+impl Drop for TouchState {
+    fn drop(&mut self) {
+        if self.touch.version() >= 3 {
+            self.touch.release()
+        }
+    }
+}
+
+impl TouchPoint {
+    fn pointer_event(&self, seat: SeatName, modifiers: Modifiers) -> PointerEvent {
+        PointerEvent {
+            // Keep touches distinct from each other, and from the seat's pointer
+            pointer_id: PointerId(seat.0 | (u64::from(self.id as u32) + 1) << 32),
+            is_primary: self.is_primary,
+            pointer_type: PointerType::Touch(TouchInfo {
+                contact_geometry: self.contact_geometry,
+                pressure: 0.0,
+            }),
+            time: Some(self.time),
+            pos: self.pos,
+            buttons: PointerButtons::new(),
+            modifiers,
+            button: PointerButton::None,
+            focus: false,
+            count: 0,
+            coalesced: Vec::new(),
+            predicted: Vec::new(),
+        }
+    }
+}
+
+impl Dispatch<wl_touch::WlTouch, TouchUserData> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _: &wl_touch::WlTouch,
+        event: wl_touch::Event,
+        data: &TouchUserData,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let seat = input_state(&mut state.input_states, data.0);
+        let modifiers = seat.modifiers();
+        let Some(touch) = seat.touch_state.as_mut() else {
+            return;
+        };
+        let windows = &mut state.windows;
+        let event_clock = &mut state.event_clock;
+        match event {
+            wl_touch::Event::Down {
+                time,
+                surface,
+                id,
+                x,
+                y,
+                ..
+            } => {
+                let point = TouchPoint {
+                    id,
+                    window: WindowId::of_surface(&surface),
+                    pos: Point::new(x, y),
+                    time: event_clock.instant(time),
+                    is_primary: touch.points.is_empty(),
+                    contact_geometry: Size::ZERO,
+                };
+                let mut event = point.pointer_event(data.0, modifiers);
+                event.button = PointerButton::Primary;
+                event.buttons = event.buttons.with(PointerButton::Primary);
+                event.count = 1;
+                if let Some(window) = windows.get_mut(&point.window) {
+                    window.handler.pointer_down(&event);
+                }
+                touch.points.push(point);
+            }
+            wl_touch::Event::Up { time, id, .. } => {
+                let Some(idx) = touch.points.iter().position(|point| point.id == id) else {
+                    return;
+                };
+                let mut point = touch.points.remove(idx);
+                point.time = event_clock.instant(time);
+                let mut event = point.pointer_event(data.0, modifiers);
+                event.button = PointerButton::Primary;
+                if let Some(window) = windows.get_mut(&point.window) {
+                    window.handler.pointer_up(&event);
+                }
+            }
+            wl_touch::Event::Motion { time, id, x, y } => {
+                let Some(point) = touch.points.iter_mut().find(|point| point.id == id) else {
+                    return;
+                };
+                point.pos = Point::new(x, y);
+                point.time = event_clock.instant(time);
+                let mut event = point.pointer_event(data.0, modifiers);
+                event.buttons = event.buttons.with(PointerButton::Primary);
+                if let Some(window) = windows.get_mut(&point.window) {
+                    window.handler.pointer_move(&event);
+                }
+            }
+            // The compositor has taken these touches, for example to use in a gesture
+            wl_touch::Event::Cancel => {
+                for mut point in touch.points.drain(..) {
+                    // Cancelling has no timestamp of its own
+                    point.time = Instant::now();
+                    let event = point.pointer_event(data.0, modifiers);
+                    if let Some(window) = windows.get_mut(&point.window) {
+                        window.handler.pointer_cancel(&event);
+                    }
+                }
+            }
+            // Applies to the following motion events of the point
+            wl_touch::Event::Shape { id, major, minor } => {
+                if let Some(point) = touch.points.iter_mut().find(|point| point.id == id) {
+                    point.contact_geometry = Size::new(major, minor);
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -15,19 +15,27 @@
 //! wayland platform support
 
 use std::{
+    cell::RefCell,
     collections::HashMap,
     rc::Rc,
     sync::mpsc::{Receiver, Sender},
 };
 
+use nix::time::ClockId;
 use smithay_client_toolkit::{
     compositor::CompositorState,
     delegate_registry,
     output::OutputState,
     reexports::{
         calloop::{channel, EventLoop, LoopHandle, LoopSignal},
-        client::QueueHandle,
-        protocols::wp::text_input::zv3::client::zwp_text_input_manager_v3::ZwpTextInputManagerV3,
+        client::protocol::{wl_data_device_manager::WlDataDeviceManager, wl_shm::WlShm},
+        client::{Connection, QueueHandle},
+        protocols::wp::{
+            pointer_constraints::zv1::client::zwp_pointer_constraints_v1::ZwpPointerConstraintsV1,
+            pointer_gestures::zv1::client::zwp_pointer_gestures_v1::ZwpPointerGesturesV1,
+            relative_pointer::zv1::client::zwp_relative_pointer_manager_v1::ZwpRelativePointerManagerV1,
+            text_input::zv3::client::zwp_text_input_manager_v3::ZwpTextInputManagerV3,
+        },
     },
     registry::{ProvidesRegistryState, RegistryState},
     registry_handlers,
@@ -38,17 +46,22 @@ use smithay_client_toolkit::{
 use crate::{AppHandler, IdleToken};
 
 use self::{
-    input::SeatInfo,
+    drag::ActiveDrag,
+    input::{DefaultCursor, SeatInfo},
     window::{WaylandWindowState, WindowAction, WindowId},
 };
 
-use super::shared::xkb::Context;
+use super::shared::linux::event_clock::EventClock;
+use super::shared::xkb::{Context, KeyLookup};
 
 pub mod application;
 pub mod clipboard;
+mod drag;
 pub mod error;
+mod global_shortcuts;
 mod input;
 pub mod menu;
+mod presentation;
 pub mod screen;
 pub mod window;
 
@@ -76,9 +89,8 @@ struct WaylandState {
     pub registry_state: RegistryState,
 
     pub output_state: OutputState,
-    // TODO: Do we need to keep this around
-    // It is unused because(?) wgpu creates the surfaces through RawDisplayHandle(?)
-    pub _compositor_state: CompositorState,
+    // Used to create drag icons. Window surfaces are created through the `Application`'s compositor
+    pub compositor_state: CompositorState,
     // Is used: Keep the XdgShell alive, which is a Weak in all Handles
     pub _xdg_shell_state: Rc<XdgShell>,
     pub wayland_queue: QueueHandle<Self>,
@@ -97,7 +109,24 @@ struct WaylandState {
     pub seats: SeatState,
     pub input_states: Vec<SeatInfo>,
     pub xkb_context: Context,
+    /// Translates keys under the layout of the keyboard which was used last, shared with the
+    /// `Application`
+    pub key_lookup: Rc<RefCell<Option<KeyLookup>>>,
     pub text_input: Option<ZwpTextInputManagerV3>,
+    pub data_device_manager: Option<WlDataDeviceManager>,
+    pub shm: Option<WlShm>,
+    pub pointer_gestures: Option<ZwpPointerGesturesV1>,
+    pub pointer_constraints: Option<ZwpPointerConstraintsV1>,
+    pub relative_pointer_manager: Option<ZwpRelativePointerManagerV1>,
+    /// The clock which presentation times are reported on
+    pub presentation_clock: ClockId,
+    /// Converts the timestamps of input events to `Instant`s
+    pub event_clock: EventClock,
+    /// The drag and drop operation which we are the source of, if any
+    pub drag: Option<ActiveDrag>,
+    pub connection: Connection,
+    /// The cursor of the user's theme, loaded once a window first hides or shows the cursor
+    pub default_cursor: Option<DefaultCursor>,
 }
 
 delegate_registry!(WaylandState);
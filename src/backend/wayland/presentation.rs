@@ -0,0 +1,68 @@
+//! Finding out when frames reach the screen, using `wp_presentation`.
+
+use std::time::Duration;
+
+use nix::time::ClockId;
+use smithay_client_toolkit::reexports::{
+    client::{Connection, Dispatch, QueueHandle},
+    protocols::wp::presentation_time::client::{
+        wp_presentation::{self, WpPresentation},
+        wp_presentation_feedback::{self, WpPresentationFeedback},
+    },
+};
+
+use super::{window::WindowId, WaylandState};
+
+impl Dispatch<WpPresentation, ()> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _: &WpPresentation,
+        event: wp_presentation::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wp_presentation::Event::ClockId { clk_id } = event {
+            state.presentation_clock = ClockId::from_raw(clk_id as _);
+        }
+    }
+}
+
+impl Dispatch<WpPresentationFeedback, WindowId> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _: &WpPresentationFeedback,
+        event: wp_presentation_feedback::Event,
+        window_id: &WindowId,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            wp_presentation_feedback::Event::Presented {
+                tv_sec_hi,
+                tv_sec_lo,
+                tv_nsec,
+                refresh,
+                seq_hi,
+                seq_lo,
+                ..
+            } => {
+                let Some(window) = state.windows.get_mut(window_id) else {
+                    return;
+                };
+                let secs = (u64::from(tv_sec_hi) << 32) | u64::from(tv_sec_lo);
+                let time = Duration::new(secs, tv_nsec);
+                let refresh = Duration::from_nanos(refresh.into());
+                let mut seq = (u64::from(seq_hi) << 32) | u64::from(seq_lo);
+                if seq == 0 && !refresh.is_zero() {
+                    // The output has no refresh counter, so count refreshes since the epoch
+                    seq = (time.as_nanos() / refresh.as_nanos()) as u64;
+                }
+                window.presented(state.presentation_clock, seq, time, refresh);
+            }
+            // The frame was never shown, so we learnt nothing about the display
+            wp_presentation_feedback::Event::Discarded => {}
+            _ => {}
+        }
+    }
+}
@@ -14,10 +14,15 @@
 #![allow(clippy::single_match)]
 
 use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::os::raw::c_void;
 use std::rc::{Rc, Weak};
 use std::sync::mpsc::{self, Sender};
+use std::time::Duration;
 
+#[cfg(feature = "accesskit")]
+use accesskit_unix::Adapter as AccessKitAdapter;
+use nix::time::ClockId;
 use raw_window_handle::{
     HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle,
     WaylandDisplayHandle, WaylandWindowHandle,
@@ -28,6 +33,7 @@ use smithay_client_toolkit::reexports::calloop::{channel, LoopHandle};
 use smithay_client_toolkit::reexports::client::protocol::wl_compositor::WlCompositor;
 use smithay_client_toolkit::reexports::client::protocol::wl_surface::WlSurface;
 use smithay_client_toolkit::reexports::client::{protocol, Connection, Proxy, QueueHandle};
+use smithay_client_toolkit::reexports::protocols::wp::presentation_time::client::wp_presentation::WpPresentation;
 use smithay_client_toolkit::shell::xdg::window::{
     DecorationMode, Window, WindowConfigure, WindowDecorations, WindowHandler,
 };
@@ -38,22 +44,28 @@ use tracing;
 use wayland_backend::client::ObjectId;
 
 use super::application::{self};
+use super::drag::DragRequest;
 use super::input::{
     input_state, SeatName, TextFieldChange, TextInputCell, TextInputProperties, WeakTextInputCell,
 };
 use super::menu::Menu;
 use super::{ActiveAction, IdleAction, WaylandState};
 
+#[cfg(feature = "accesskit")]
+use crate::backend::shared::linux::accessibility::placeholder_tree;
+use crate::backend::shared::linux::frame_clock::FrameClock;
 use crate::{backend, IdleToken, Region, Scalable};
 use crate::{
     dialog::FileDialogOptions,
+    drag::{DragActions, DragImage, DragToken},
     error::Error as ShellError,
     kurbo::{Insets, Point, Rect, Size},
     mouse::{Cursor, CursorDesc},
+    pointer::{PointerButtons, PointerConstraint, PointerId},
     scale::Scale,
-    text::Event,
+    text::{Event, InputProperties},
     window::{self, FileDialogToken, TimerToken, WinHandler, WindowLevel},
-    TextFieldToken,
+    ClipboardFormat, Code, KeyboardLayouts, Modifiers, TextFieldToken,
 };
 
 #[derive(Clone)]
@@ -209,13 +221,19 @@ impl WindowHandle {
         let mut updated = false;
         if props.active_text_field.is_some_and(|it| it == token) {
             props.active_text_field = None;
+            props.active_input_properties = InputProperties::default();
             props.active_text_field_updated = true;
             updated = true;
         }
         if props.next_text_field.is_some_and(|it| it == token) {
             props.next_text_field = None;
+            props.next_input_properties = InputProperties::default();
             updated = true;
         }
+        self.properties()
+            .borrow_mut()
+            .text_field_properties
+            .remove(&token);
 
         if updated {
             props_cell.set(props);
@@ -228,11 +246,42 @@ impl WindowHandle {
         let props_cell = self.text.upgrade().unwrap();
         let mut props = props_cell.get();
         props.next_text_field = active_field;
+        props.next_input_properties = active_field
+            .and_then(|token| {
+                let window_props = self.properties();
+                let window_props = window_props.borrow();
+                window_props.text_field_properties.get(&token).copied()
+            })
+            .unwrap_or_default();
         props_cell.set(props);
 
         self.defer(WindowAction::TextField(TextFieldChange));
     }
 
+    pub fn set_text_field_properties(&self, token: TextFieldToken, properties: InputProperties) {
+        self.properties()
+            .borrow_mut()
+            .text_field_properties
+            .insert(token, properties);
+        let props_cell = self.text.upgrade().unwrap();
+        let mut props = props_cell.get();
+        let mut updated = false;
+        if props.next_text_field.is_some_and(|it| it == token) {
+            props.next_input_properties = properties;
+            updated = true;
+        }
+        if props.active_text_field.is_some_and(|it| it == token) {
+            // The field needs to be synced again for the compositor to see the new content type
+            props.active_input_properties = properties;
+            props.active_text_field_updated = true;
+            updated = true;
+        }
+        if updated {
+            props_cell.set(props);
+            self.defer(WindowAction::TextField(TextFieldChange));
+        }
+    }
+
     pub fn update_text_field(&self, token: TextFieldToken, update: Event) {
         let props_cell = self.text.upgrade().unwrap();
         let mut props = props_cell.get();
@@ -310,6 +359,60 @@ impl WindowHandle {
         tracing::warn!("show_context_menu not implement for wayland");
     }
 
+    pub fn start_drag(
+        &self,
+        formats: &[ClipboardFormat],
+        actions: DragActions,
+        image: Option<DragImage>,
+    ) -> Option<DragToken> {
+        let token = DragToken::next();
+        // Whether the drag can actually be started is only known on the event loop,
+        // so failures are reported through `drag_finished`
+        self.defer(WindowAction::StartDrag(DragRequest {
+            token,
+            formats: formats.to_vec(),
+            actions,
+            image,
+        }));
+        Some(token)
+    }
+
+    pub fn capture_pointer(&self, _pointer_id: PointerId) {
+        // Wayland has no explicit grabs: the compositor keeps sending a pointer's events to
+        // the surface it is over when a button is pressed until all buttons are released,
+        // and a touch's events to the surface it went down on.
+    }
+
+    pub fn release_pointer_capture(&self, _pointer_id: PointerId) {}
+
+    pub fn set_pointer_constraint(&self, constraint: PointerConstraint) {
+        self.defer(WindowAction::SetPointerConstraint(constraint));
+    }
+
+    pub fn set_cursor_visible(&self, visible: bool) {
+        self.defer(WindowAction::SetCursorVisible(visible));
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        self.properties().borrow().modifiers
+    }
+
+    pub fn pressed_keys(&self) -> Vec<Code> {
+        self.properties().borrow().pressed_keys.clone()
+    }
+
+    pub fn keyboard_layouts(&self) -> KeyboardLayouts {
+        self.properties().borrow().keyboard_layouts.clone()
+    }
+
+    pub fn pointer_buttons(&self) -> PointerButtons {
+        self.properties().borrow().pointer_buttons
+    }
+
+    pub fn pointer_position(&self) -> Option<Point> {
+        self.properties().borrow().pointer_position
+    }
+
     pub fn set_title(&self, title: &str) {
         let props = self.properties();
         let props = props.borrow();
@@ -319,9 +422,13 @@ impl WindowHandle {
     #[cfg(feature = "accesskit")]
     pub fn update_accesskit_if_active(
         &self,
-        _update_factory: impl FnOnce() -> accesskit::TreeUpdate,
+        update_factory: impl FnOnce() -> accesskit::TreeUpdate,
     ) {
-        // AccessKit doesn't yet support this backend.
+        // Don't hold the properties borrowed while running user code
+        let adapter = self.properties().borrow().accesskit_adapter.clone();
+        if let Some(adapter) = adapter {
+            adapter.update(update_factory());
+        }
     }
 }
 
@@ -368,6 +475,20 @@ unsafe impl HasRawDisplayHandle for WindowHandle {
     }
 }
 
+#[cfg(feature = "accesskit")]
+struct AccessKitActionHandler {
+    idle_handle: IdleHandle,
+}
+
+#[cfg(feature = "accesskit")]
+impl accesskit::ActionHandler for AccessKitActionHandler {
+    fn do_action(&mut self, request: accesskit::ActionRequest) {
+        self.idle_handle.add_idle_callback(move |handler| {
+            handler.accesskit_action(request);
+        });
+    }
+}
+
 #[derive(Clone)]
 pub struct IdleHandle {
     window: WindowId,
@@ -432,6 +553,7 @@ pub(crate) struct WindowBuilder {
     resizable: bool,
     show_titlebar: bool,
     compositor: WlCompositor,
+    presentation: Option<Rc<WpPresentation>>,
     wayland_queue: QueueHandle<WaylandState>,
     loop_handle: LoopHandle<'static, WaylandState>,
     xdg_state: Weak<XdgShell>,
@@ -454,6 +576,7 @@ impl WindowBuilder {
             resizable: true,
             show_titlebar: true,
             compositor: app.compositor,
+            presentation: app.presentation,
             wayland_queue: app.wayland_queue,
             loop_handle: app.loop_handle,
             xdg_state: app.xdg_shell,
@@ -552,6 +675,17 @@ impl WindowBuilder {
             will_repaint: false,
             pending_frame_callback: false,
             configured: false,
+            presentation: self.presentation,
+            // The clock is replaced once the compositor tells us which it uses
+            frame_clock: FrameClock::new(ClockId::CLOCK_MONOTONIC, None),
+            modifiers: Modifiers::empty(),
+            pressed_keys: Vec::new(),
+            keyboard_layouts: KeyboardLayouts::default(),
+            pointer_buttons: PointerButtons::new(),
+            pointer_position: None,
+            text_field_properties: HashMap::new(),
+            #[cfg(feature = "accesskit")]
+            accesskit_adapter: None,
         };
         let properties_strong = Rc::new(RefCell::new(properties));
 
@@ -561,6 +695,8 @@ impl WindowBuilder {
             next_text_field: None,
             active_text_field_updated: false,
             active_text_layout_changed: false,
+            active_input_properties: InputProperties::default(),
+            next_input_properties: InputProperties::default(),
         }));
         let handle = WindowHandle {
             idle_sender: self.idle_sender,
@@ -578,6 +714,7 @@ impl WindowBuilder {
                     properties: properties_strong,
                     text_input_seat: None,
                     text,
+                    cursor_visible: true,
                     handle: Some(handle.clone()),
                 }),
             ))
@@ -615,6 +752,8 @@ pub(super) struct WaylandWindowState {
     // Cheap to clone, but kept in an option to track whether
     // `connect` has been sent
     handle: Option<WindowHandle>,
+    /// Whether the cursor is shown whilst it is over this window
+    pub cursor_visible: bool,
 }
 
 struct WindowProperties {
@@ -647,6 +786,24 @@ struct WindowProperties {
     pending_frame_callback: bool,
     // We can't draw before being configured
     configured: bool,
+    /// Used to find out when our frames are shown, if the compositor supports it
+    presentation: Option<Rc<WpPresentation>>,
+    /// The refreshes of the output, as reported by `wp_presentation` feedback
+    frame_clock: FrameClock,
+    /// The state of the keyboard focused on this window, which the seat keeps up to date
+    modifiers: Modifiers,
+    pressed_keys: Vec<Code>,
+    /// The layouts of the keyboard which last focused this window
+    keyboard_layouts: KeyboardLayouts,
+    /// The state of the pointer over this window, which the seat keeps up to date
+    pointer_buttons: PointerButtons,
+    pointer_position: Option<Point>,
+    /// The properties set for each text field, which the focused field's are copied from
+    text_field_properties: HashMap<TextFieldToken, InputProperties>,
+    // Wayland doesn't tell clients where their windows are, so we can't report the root window
+    // bounds, and node positions are relative to the window
+    #[cfg(feature = "accesskit")]
+    accesskit_adapter: Option<Rc<AccessKitAdapter>>,
 }
 
 impl WindowProperties {
@@ -707,7 +864,7 @@ enum PaintContext {
 
 impl WaylandWindowState {
     fn do_paint(&mut self, force: bool, context: PaintContext) {
-        {
+        let timing = {
             let mut props = self.properties.borrow_mut();
             if matches!(context, PaintContext::Frame) {
                 props.pending_frame_callback = false;
@@ -723,6 +880,11 @@ impl WaylandWindowState {
                 return;
             }
             props.will_repaint = false;
+            if !matches!(context, PaintContext::Frame) {
+                // This paint wasn't paced by the display, so no frames were missed
+                props.frame_clock.cancel_target();
+            }
+            let timing = props.frame_clock.frame_timing();
             // If there is not a frame callback in flight, we request it here
             // This branch could be skipped e.g. on `configure`, which ignores frame throttling hints and
             // always paints eagerly, even if there is a frame callback running
@@ -731,9 +893,19 @@ impl WaylandWindowState {
                 props.pending_frame_callback = true;
                 let surface = props.wayland_window.wl_surface();
                 surface.frame(&props.wayland_queue.clone(), surface.clone());
+                if let Some(presentation) = &props.presentation {
+                    // Find out when the frame about to be painted is shown
+                    presentation.feedback(
+                        surface,
+                        &props.wayland_queue,
+                        WindowId::of_surface(surface),
+                    );
+                }
+                props.frame_clock.next_target();
             }
-        }
-        self.handler.prepare_paint();
+            timing
+        };
+        self.handler.prepare_paint(timing);
         // TODO: Apply invalid properly
         // When forcing, should mark the entire region as damaged
         let mut region = Region::EMPTY;
@@ -750,6 +922,75 @@ impl WaylandWindowState {
         self.handler.paint(&region);
     }
 
+    /// Record that a frame of this window was shown at refresh `seq` of its output.
+    pub(super) fn presented(
+        &mut self,
+        clock: ClockId,
+        seq: u64,
+        time: Duration,
+        refresh: Duration,
+    ) {
+        let mut props = self.properties.borrow_mut();
+        props.frame_clock.set_clock(clock);
+        props.frame_clock.refreshed(seq, time);
+        props.frame_clock.set_refresh_interval(refresh);
+    }
+
+    pub(super) fn wl_surface(&self) -> WlSurface {
+        self.properties.borrow().wayland_window.wl_surface().clone()
+    }
+
+    /// Create the AccessKit adapter for this window.
+    ///
+    /// `accesskit_unix` only creates an adapter if the AT-SPI bus is running, so the handler's
+    /// tree is only requested in that case, in place of the placeholder it starts with.
+    #[cfg(feature = "accesskit")]
+    fn init_accesskit(&mut self, idle_handle: IdleHandle) {
+        let action_handler = Box::new(AccessKitActionHandler { idle_handle });
+        let is_focused = self.text_input_seat.is_some();
+        let Some(adapter) = AccessKitAdapter::new(placeholder_tree, is_focused, action_handler)
+        else {
+            return;
+        };
+        adapter.update(self.handler.accesskit_tree());
+        self.properties.borrow_mut().accesskit_adapter = Some(Rc::new(adapter));
+    }
+
+    /// Tell AccessKit whether this window has the keyboard focus.
+    #[cfg(feature = "accesskit")]
+    pub(super) fn update_accesskit_focus(&self, is_focused: bool) {
+        let adapter = self.properties.borrow().accesskit_adapter.clone();
+        if let Some(adapter) = adapter {
+            adapter.update_window_focus_state(is_focused);
+        }
+    }
+
+    /// Record the state of the keyboard focused on this window, which the handler can query.
+    pub(super) fn set_keyboard_state(&self, modifiers: Modifiers, pressed_keys: &[Code]) {
+        let mut props = self.properties.borrow_mut();
+        props.modifiers = modifiers;
+        props.pressed_keys.clear();
+        props.pressed_keys.extend_from_slice(pressed_keys);
+    }
+
+    /// Record the layouts of the keyboard focused on this window, returning whether they
+    /// changed.
+    pub(super) fn set_keyboard_layouts(&self, layouts: KeyboardLayouts) -> bool {
+        let mut props = self.properties.borrow_mut();
+        if props.keyboard_layouts == layouts {
+            return false;
+        }
+        props.keyboard_layouts = layouts;
+        true
+    }
+
+    /// Record the state of the pointer over this window, which the handler can query.
+    pub(super) fn set_pointer_state(&self, buttons: PointerButtons, position: Option<Point>) {
+        let mut props = self.properties.borrow_mut();
+        props.pointer_buttons = buttons;
+        props.pointer_position = position;
+    }
+
     pub(super) fn set_input_seat(&mut self, seat: SeatName) {
         assert!(self.text_input_seat.is_none());
         self.text_input_seat = Some(seat);
@@ -838,9 +1079,15 @@ impl WindowHandler for WaylandState {
             return;
         };
         if let Some(handle) = window.handle.take() {
+            #[cfg(feature = "accesskit")]
+            let idle_handle = handle.get_idle_handle();
             window.handler.connect(&crate::WindowHandle(
                 backend::window::WindowHandle::Wayland(handle),
             ));
+            #[cfg(feature = "accesskit")]
+            if let Some(idle_handle) = idle_handle {
+                window.init_accesskit(idle_handle);
+            }
         }
         // TODO: Actually use the suggestions from requested_size
         let display_size;
@@ -865,6 +1112,9 @@ pub(super) enum WindowAction {
     Create(WaylandWindowState),
     AnimationRequested,
     TextField(TextFieldChange),
+    StartDrag(DragRequest),
+    SetPointerConstraint(PointerConstraint),
+    SetCursorVisible(bool),
 }
 
 impl WindowAction {
@@ -885,6 +1135,7 @@ impl WindowAction {
                 window.do_paint(true, PaintContext::Requested);
             }
             WindowAction::Close => {
+                state.set_pointer_constraint(&window_id, PointerConstraint::None);
                 // Remove the window from tracking
                 {
                     let Some(win) = state.windows.remove(&window_id) else {
@@ -923,6 +1174,13 @@ impl WindowAction {
                     &window_id,
                 );
             }
+            WindowAction::StartDrag(request) => state.start_drag(&window_id, request),
+            WindowAction::SetPointerConstraint(constraint) => {
+                state.set_pointer_constraint(&window_id, constraint)
+            }
+            WindowAction::SetCursorVisible(visible) => {
+                state.set_cursor_visible(&window_id, visible)
+            }
         }
     }
 }
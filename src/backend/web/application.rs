@@ -15,6 +15,7 @@
 //! Web implementation of features at the application scope.
 
 use crate::application::AppHandler;
+use crate::{Code, HotKey, KbKey, Modifiers};
 
 use super::clipboard::Clipboard;
 use super::error::Error;
@@ -35,6 +36,26 @@ impl Application {
         Clipboard
     }
 
+    pub fn key_for_code(&self, _code: Code, _mods: Modifiers) -> Option<KbKey> {
+        tracing::warn!("Application::key_for_code unimplemented for web.");
+        None
+    }
+
+    pub fn code_for_key(&self, _key: &KbKey) -> Option<(Code, Modifiers)> {
+        tracing::warn!("Application::code_for_key unimplemented for web.");
+        None
+    }
+
+    pub fn register_global_hotkey(&self, _id: u32, _hotkey: &HotKey) -> Result<(), anyhow::Error> {
+        Err(anyhow::anyhow!(
+            "Application::register_global_hotkey unimplemented for web."
+        ))
+    }
+
+    pub fn unregister_global_hotkey(&self, _id: u32) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+
     pub fn get_locale() -> String {
         web_sys::window()
             .and_then(|w| w.navigator().language())
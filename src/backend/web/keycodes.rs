@@ -32,6 +32,7 @@ pub(crate) fn convert_keyboard_event(
         mods,
         repeat: event.repeat(),
         is_composing: event.is_composing(),
+        time: None,
     }
 }
 
@@ -34,21 +34,24 @@ use super::application::Application;
 use super::error::Error;
 use super::keycodes::convert_keyboard_event;
 use super::menu::Menu;
+use crate::clipboard::ClipboardFormat;
 use crate::common_util::{ClickCounter, IdleCallback};
 use crate::dialog::{FileDialogOptions, FileDialogType};
+use crate::drag::{DragActions, DragImage, DragToken};
 use crate::error::Error as ShellError;
 use crate::scale::{Scale, ScaledArea};
 
-use crate::keyboard::{KeyState, Modifiers};
+use crate::keyboard::{Code, KeyState, KeyboardLayouts, Modifiers};
 use crate::mouse::{Cursor, CursorDesc};
 use crate::pointer::{
-    MouseInfo, PointerButton, PointerButtons, PointerEvent, PointerId, PointerType,
+    MouseInfo, PointerButton, PointerButtons, PointerConstraint, PointerEvent, PointerId,
+    PointerType, ScrollDeltaUnit,
 };
 use crate::region::Region;
-use crate::text::{simulate_input, Event};
+use crate::text::{simulate_input, Event, InputProperties};
 use crate::window;
 use crate::window::{
-    FileDialogToken, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowLevel,
+    FileDialogToken, FrameTiming, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowLevel,
 };
 
 // This is a macro instead of a function since KeyboardEvent and MouseEvent has identical functions
@@ -133,7 +136,10 @@ pub struct CustomCursor;
 
 impl WindowState {
     fn render(&self) {
-        self.handler.borrow_mut().prepare_paint();
+        // TODO: report frame timing, using the requestAnimationFrame timestamp
+        self.handler
+            .borrow_mut()
+            .prepare_paint(FrameTiming::default());
 
         let mut piet_ctx = piet_common::Piet::new(self.context.clone(), self.window.clone());
         if let Err(e) = piet_ctx.with_save(|ctx| {
@@ -205,15 +211,16 @@ fn setup_mouse_down_callback(ws: &Rc<WindowState>) {
             let event = PointerEvent {
                 pointer_id: PointerId(0),
                 is_primary: true,
-                pointer_type: PointerType::Mouse(MouseInfo {
-                    wheel_delta: Vec2::ZERO,
-                }),
+                pointer_type: PointerType::Mouse(MouseInfo::default()),
+                time: None,
                 pos,
                 buttons: get_buttons(event.buttons()),
                 modifiers: get_modifiers!(event),
                 button,
                 focus: false,
                 count,
+                coalesced: Vec::new(),
+                predicted: Vec::new(),
             };
             state.handler.borrow_mut().pointer_down(&event);
         }
@@ -227,15 +234,16 @@ fn setup_mouse_up_callback(ws: &Rc<WindowState>) {
             let event = PointerEvent {
                 pointer_id: PointerId(0),
                 is_primary: true,
-                pointer_type: PointerType::Mouse(MouseInfo {
-                    wheel_delta: Vec2::ZERO,
-                }),
+                pointer_type: PointerType::Mouse(MouseInfo::default()),
+                time: None,
                 pos: Point::new(event.offset_x() as f64, event.offset_y() as f64),
                 buttons: get_buttons(event.buttons()),
                 modifiers: get_modifiers!(event),
                 button,
                 focus: false,
                 count: 0,
+                coalesced: Vec::new(),
+                predicted: Vec::new(),
             };
             state.handler.borrow_mut().pointer_up(&event);
         }
@@ -248,15 +256,16 @@ fn setup_mouse_move_callback(ws: &Rc<WindowState>) {
         let event = PointerEvent {
             pointer_id: PointerId(0),
             is_primary: true,
-            pointer_type: PointerType::Mouse(MouseInfo {
-                wheel_delta: Vec2::ZERO,
-            }),
+            pointer_type: PointerType::Mouse(MouseInfo::default()),
+            time: None,
             pos: Point::new(event.offset_x() as f64, event.offset_y() as f64),
             buttons: get_buttons(event.buttons()),
             modifiers: get_modifiers!(event),
             button: PointerButton::None,
             focus: false,
             count: 0,
+            coalesced: Vec::new(),
+            predicted: Vec::new(),
         };
         state.handler.borrow_mut().pointer_move(&event);
     });
@@ -270,30 +279,34 @@ fn setup_scroll_callback(ws: &Rc<WindowState>) {
         let dx = event.delta_x();
         let dy = event.delta_y();
 
-        // The value 35.0 was manually picked to produce similar behavior to mac/linux.
-        let wheel_delta = match delta_mode {
-            web_sys::WheelEvent::DOM_DELTA_PIXEL => Vec2::new(dx, dy),
-            web_sys::WheelEvent::DOM_DELTA_LINE => Vec2::new(35.0 * dx, 35.0 * dy),
-            web_sys::WheelEvent::DOM_DELTA_PAGE => {
-                let size_dp = state.area.get().size_dp();
-                Vec2::new(size_dp.width * dx, size_dp.height * dy)
-            }
+        let wheel_delta_unit = match delta_mode {
+            web_sys::WheelEvent::DOM_DELTA_PIXEL => ScrollDeltaUnit::Pixels,
+            web_sys::WheelEvent::DOM_DELTA_LINE => ScrollDeltaUnit::Lines,
+            web_sys::WheelEvent::DOM_DELTA_PAGE => ScrollDeltaUnit::Pages,
             _ => {
                 warn!("Invalid deltaMode in WheelEvent: {}", delta_mode);
                 return;
             }
         };
+        let mouse_info = MouseInfo {
+            wheel_delta: Vec2::new(dx, dy),
+            wheel_delta_unit,
+            ..Default::default()
+        };
 
         let event = PointerEvent {
             pointer_id: PointerId(0),
             is_primary: true,
-            pointer_type: PointerType::Mouse(MouseInfo { wheel_delta }),
+            pointer_type: PointerType::Mouse(mouse_info),
+            time: None,
             pos: Point::new(event.offset_x() as f64, event.offset_y() as f64),
             buttons: get_buttons(event.buttons()),
             modifiers: get_modifiers!(event),
             button: PointerButton::None,
             focus: false,
             count: 0,
+            coalesced: Vec::new(),
+            predicted: Vec::new(),
         };
         state.handler.borrow_mut().wheel(&event);
     });
@@ -618,6 +631,10 @@ impl WindowHandle {
         }
     }
 
+    pub fn set_text_field_properties(&self, _token: TextFieldToken, _properties: InputProperties) {
+        // no-op for now, until we get a properly implemented text input
+    }
+
     pub fn update_text_field(&self, _token: TextFieldToken, _update: Event) {
         // no-op for now, until we get a properly implemented text input
     }
@@ -696,6 +713,31 @@ impl WindowHandle {
         Err(ShellError::Platform(Error::Unimplemented))
     }
 
+    pub fn modifiers(&self) -> Modifiers {
+        warn!("WindowHandle::modifiers unimplemented for web.");
+        Modifiers::empty()
+    }
+
+    pub fn pressed_keys(&self) -> Vec<Code> {
+        warn!("WindowHandle::pressed_keys unimplemented for web.");
+        Vec::new()
+    }
+
+    pub fn keyboard_layouts(&self) -> KeyboardLayouts {
+        warn!("WindowHandle::keyboard_layouts unimplemented for web.");
+        KeyboardLayouts::default()
+    }
+
+    pub fn pointer_buttons(&self) -> PointerButtons {
+        warn!("WindowHandle::pointer_buttons unimplemented for web.");
+        PointerButtons::new()
+    }
+
+    pub fn pointer_position(&self) -> Option<Point> {
+        warn!("WindowHandle::pointer_position unimplemented for web.");
+        None
+    }
+
     /// Get a handle that can be used to schedule an idle task.
     pub fn get_idle_handle(&self) -> Option<IdleHandle> {
         self.0.upgrade().map(|w| IdleHandle {
@@ -722,6 +764,32 @@ impl WindowHandle {
         warn!("show_context_menu unimplemented for web");
     }
 
+    pub fn start_drag(
+        &self,
+        _formats: &[ClipboardFormat],
+        _actions: DragActions,
+        _image: Option<DragImage>,
+    ) -> Option<DragToken> {
+        warn!("WindowHandle::start_drag unimplemented for web.");
+        None
+    }
+
+    pub fn capture_pointer(&self, _pointer_id: PointerId) {
+        warn!("WindowHandle::capture_pointer unimplemented for web.");
+    }
+
+    pub fn release_pointer_capture(&self, _pointer_id: PointerId) {
+        warn!("WindowHandle::release_pointer_capture unimplemented for web.");
+    }
+
+    pub fn set_pointer_constraint(&self, _constraint: PointerConstraint) {
+        warn!("WindowHandle::set_pointer_constraint unimplemented for web.");
+    }
+
+    pub fn set_cursor_visible(&self, _visible: bool) {
+        warn!("WindowHandle::set_cursor_visible unimplemented for web.");
+    }
+
     pub fn set_title(&self, title: &str) {
         if let Some(state) = self.0.upgrade() {
             state.canvas.set_title(title)
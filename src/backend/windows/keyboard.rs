@@ -625,6 +625,7 @@ impl KeyboardState {
                         is_composing: false,
                         location,
                         repeat,
+                        time: None,
                     };
                     Some(event)
                 } else {
@@ -650,6 +651,7 @@ impl KeyboardState {
                     is_composing: false,
                     location,
                     repeat,
+                    time: None,
                 };
                 Some(event)
             }
@@ -684,6 +686,7 @@ impl KeyboardState {
                         is_composing: false,
                         location,
                         repeat,
+                        time: None,
                     };
                     Some(event)
                 } else {
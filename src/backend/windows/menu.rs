@@ -15,7 +15,6 @@
 //! Safe wrapper for menus.
 
 use std::collections::HashMap;
-use std::fmt::Write as _;
 use std::mem;
 use std::ptr::null;
 
@@ -25,7 +24,7 @@ use winapi::um::winuser::*;
 
 use super::util::ToWide;
 use crate::hotkey::HotKey;
-use crate::keyboard::{KbKey, Modifiers};
+use crate::keyboard::Modifiers;
 
 /// A menu object, which can be either a top-level menubar or a
 /// submenu.
@@ -105,7 +104,7 @@ impl Menu {
         let mut anno_text = text.to_string();
         if let Some(key) = key {
             anno_text.push('\t');
-            format_hotkey(key, &mut anno_text);
+            anno_text.push_str(&key.display_string());
         }
         unsafe {
             let mut flags = MF_STRING;
@@ -164,7 +163,8 @@ fn convert_hotkey(id: u32, key: &HotKey) -> Option<ACCEL> {
         virt_key |= FSHIFT;
     }
 
-    let raw_key = if let Some(vk_code) = super::keyboard::key_to_vk(&key.key) {
+    let display_key = key.display_key();
+    let raw_key = if let Some(vk_code) = super::keyboard::key_to_vk(&display_key) {
         let mod_code = vk_code >> 8;
         if mod_code & 0x1 != 0 {
             virt_key |= FSHIFT;
@@ -177,7 +177,10 @@ fn convert_hotkey(id: u32, key: &HotKey) -> Option<ACCEL> {
         }
         vk_code & 0x00ff
     } else {
-        tracing::error!("Failed to convert key {:?} into virtual key code", key.key);
+        tracing::error!(
+            "Failed to convert key {:?} into virtual key code",
+            display_key
+        );
         return None;
     };
 
@@ -187,40 +190,3 @@ fn convert_hotkey(id: u32, key: &HotKey) -> Option<ACCEL> {
         cmd: id as u16,
     })
 }
-
-/// Format the hotkey in a Windows-native way.
-fn format_hotkey(key: &HotKey, s: &mut String) {
-    let key_mods: Modifiers = key.mods.into();
-    if key_mods.ctrl() {
-        s.push_str("Ctrl+");
-    }
-    if key_mods.shift() {
-        s.push_str("Shift+");
-    }
-    if key_mods.alt() {
-        s.push_str("Alt+");
-    }
-    if key_mods.meta() {
-        s.push_str("Windows+");
-    }
-    match &key.key {
-        KbKey::Character(c) => match c.as_str() {
-            "+" => s.push_str("Plus"),
-            "-" => s.push_str("Minus"),
-            " " => s.push_str("Space"),
-            _ => s.extend(c.chars().flat_map(|c| c.to_uppercase())),
-        },
-        KbKey::Escape => s.push_str("Esc"),
-        KbKey::Delete => s.push_str("Del"),
-        KbKey::Insert => s.push_str("Ins"),
-        KbKey::PageUp => s.push_str("PgUp"),
-        KbKey::PageDown => s.push_str("PgDn"),
-        // These names match LibreOffice.
-        KbKey::ArrowLeft => s.push_str("Left"),
-        KbKey::ArrowRight => s.push_str("Right"),
-        KbKey::ArrowUp => s.push_str("Up"),
-        KbKey::ArrowDown => s.push_str("Down"),
-        _ => write!(s, "{}", key.key)
-            .unwrap_or_else(|err| tracing::warn!("Failed to convert hotkey to string: {}", err)),
-    }
-}
@@ -59,20 +59,23 @@ use super::menu::Menu;
 use super::timers::TimerSlots;
 use super::util::{self, ToWide, OPTIONAL_FUNCTIONS};
 
+use crate::clipboard::ClipboardFormat;
 use crate::common_util::IdleCallback;
 use crate::dialog::{FileDialogOptions, FileDialogType, FileInfo};
+use crate::drag::{DragActions, DragImage, DragToken};
 use crate::error::Error as ShellError;
-use crate::keyboard::{KbKey, KeyState};
+use crate::keyboard::{Code, KbKey, KeyState, KeyboardLayouts, Modifiers};
 use crate::mouse::{Cursor, CursorDesc};
 use crate::pointer::{
-    MouseInfo, PointerButton, PointerButtons, PointerEvent, PointerId, PointerType,
+    MouseInfo, PointerButton, PointerButtons, PointerConstraint, PointerEvent, PointerId,
+    PointerType,
 };
 use crate::region::Region;
 use crate::scale::{Scalable, Scale, ScaledArea};
-use crate::text::{simulate_input, Event};
+use crate::text::{simulate_input, Event, InputProperties};
 use crate::window;
 use crate::window::{
-    FileDialogToken, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowLevel,
+    FileDialogToken, FrameTiming, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowLevel,
 };
 
 /// The backend target DPI.
@@ -773,7 +776,8 @@ impl WndProc for MyWndProc {
                 self.with_wnd_state(|s| {
                     // We call prepare_paint before GetUpdateRect, so that anything invalidated during
                     // prepare_paint will be reflected in GetUpdateRect.
-                    s.handler.prepare_paint();
+                    // TODO: report frame timing, using DwmGetCompositionTimingInfo
+                    s.handler.prepare_paint(FrameTiming::default());
 
                     let mut rect: RECT = mem::zeroed();
                     // TODO: use GetUpdateRgn for more conservative invalidation
@@ -970,10 +974,13 @@ impl WndProc for MyWndProc {
                     let down_state = LOWORD(wparam as u32) as usize;
                     let modifiers = s.keyboard_state.get_modifiers();
                     let is_shift = modifiers.shift();
+                    // One detent of the wheel is `WHEEL_DELTA`, but precise devices report
+                    // fractions of that.
+                    let lines = system_delta / WHEEL_DELTA as f64;
                     let wheel_delta = match msg {
-                        WM_MOUSEWHEEL if is_shift => Vec2::new(-system_delta, 0.),
-                        WM_MOUSEWHEEL => Vec2::new(0., -system_delta),
-                        WM_MOUSEHWHEEL => Vec2::new(system_delta, 0.),
+                        WM_MOUSEWHEEL if is_shift => Vec2::new(-lines, 0.),
+                        WM_MOUSEWHEEL => Vec2::new(0., -lines),
+                        WM_MOUSEHWHEEL => Vec2::new(lines, 0.),
                         _ => unreachable!(),
                     };
 
@@ -994,13 +1001,19 @@ impl WndProc for MyWndProc {
                     let event = PointerEvent {
                         pointer_id: PointerId(0),
                         is_primary: true,
-                        pointer_type: PointerType::Mouse(MouseInfo { wheel_delta }),
+                        pointer_type: PointerType::Mouse(MouseInfo {
+                            wheel_delta,
+                            ..Default::default()
+                        }),
+                        time: None,
                         pos: Point::new(p.x as f64, p.y as f64).to_dp(self.scale()),
                         buttons: get_buttons(down_state),
                         modifiers,
                         button: PointerButton::None,
                         focus: false,
                         count: 0,
+                        coalesced: Vec::new(),
+                        predicted: Vec::new(),
                     };
                     s.handler.wheel(&event);
                     true
@@ -1042,15 +1055,16 @@ impl WndProc for MyWndProc {
                     let event = PointerEvent {
                         pointer_id: PointerId(0),
                         is_primary: true,
-                        pointer_type: PointerType::Mouse(MouseInfo {
-                            wheel_delta: Vec2::ZERO,
-                        }),
+                        pointer_type: PointerType::Mouse(MouseInfo::default()),
+                        time: None,
                         pos: Point::new(x as f64, y as f64).to_dp(self.scale()),
                         buttons: get_buttons(wparam),
                         modifiers: s.keyboard_state.get_modifiers(),
                         button: PointerButton::None,
                         focus: false,
                         count: 0,
+                        coalesced: Vec::new(),
+                        predicted: Vec::new(),
                     };
                     s.handler.pointer_move(&event);
                 });
@@ -1129,15 +1143,16 @@ impl WndProc for MyWndProc {
                         let event = PointerEvent {
                             pointer_id: PointerId(0),
                             is_primary: true,
-                            pointer_type: PointerType::Mouse(MouseInfo {
-                                wheel_delta: Vec2::ZERO,
-                            }),
+                            pointer_type: PointerType::Mouse(MouseInfo::default()),
+                            time: None,
                             pos: Point::new(x as f64, y as f64).to_dp(self.scale()),
                             buttons: get_buttons(wparam),
                             modifiers: s.keyboard_state.get_modifiers(),
                             button,
                             focus: false,
                             count,
+                            coalesced: Vec::new(),
+                            predicted: Vec::new(),
                         };
                         if count > 0 {
                             s.enter_pointer_capture(hwnd, button);
@@ -1888,6 +1903,32 @@ impl WindowHandle {
         self.defer(DeferredOp::ContextMenu(menu, pos));
     }
 
+    pub fn start_drag(
+        &self,
+        _formats: &[ClipboardFormat],
+        _actions: DragActions,
+        _image: Option<DragImage>,
+    ) -> Option<DragToken> {
+        warn!("WindowHandle::start_drag is currently unimplemented for Windows.");
+        None
+    }
+
+    pub fn capture_pointer(&self, _pointer_id: PointerId) {
+        warn!("WindowHandle::capture_pointer is currently unimplemented for Windows.");
+    }
+
+    pub fn release_pointer_capture(&self, _pointer_id: PointerId) {
+        warn!("WindowHandle::release_pointer_capture is currently unimplemented for Windows.");
+    }
+
+    pub fn set_pointer_constraint(&self, _constraint: PointerConstraint) {
+        warn!("WindowHandle::set_pointer_constraint is currently unimplemented for Windows.");
+    }
+
+    pub fn set_cursor_visible(&self, _visible: bool) {
+        warn!("WindowHandle::set_cursor_visible is currently unimplemented for Windows.");
+    }
+
     pub fn add_text_field(&self) -> TextFieldToken {
         TextFieldToken::next()
     }
@@ -1906,6 +1947,10 @@ impl WindowHandle {
         }
     }
 
+    pub fn set_text_field_properties(&self, _token: TextFieldToken, _properties: InputProperties) {
+        // noop until we get a real text input implementation
+    }
+
     pub fn update_text_field(&self, _token: TextFieldToken, _update: Event) {
         // noop until we get a real text input implementation
     }
@@ -2023,6 +2068,31 @@ impl WindowHandle {
         self.state.upgrade().map(|w| w.is_focusable).unwrap_or(true)
     }
 
+    pub fn modifiers(&self) -> Modifiers {
+        warn!("WindowHandle::modifiers is currently unimplemented for Windows.");
+        Modifiers::empty()
+    }
+
+    pub fn pressed_keys(&self) -> Vec<Code> {
+        warn!("WindowHandle::pressed_keys is currently unimplemented for Windows.");
+        Vec::new()
+    }
+
+    pub fn keyboard_layouts(&self) -> KeyboardLayouts {
+        warn!("WindowHandle::keyboard_layouts is currently unimplemented for Windows.");
+        KeyboardLayouts::default()
+    }
+
+    pub fn pointer_buttons(&self) -> PointerButtons {
+        warn!("WindowHandle::pointer_buttons is currently unimplemented for Windows.");
+        PointerButtons::new()
+    }
+
+    pub fn pointer_position(&self) -> Option<Point> {
+        warn!("WindowHandle::pointer_position is currently unimplemented for Windows.");
+        None
+    }
+
     /// Get a handle that can be used to schedule an idle task.
     pub fn get_idle_handle(&self) -> Option<IdleHandle> {
         self.state.upgrade().map(|w| IdleHandle {
@@ -23,8 +23,13 @@ use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Context, Error};
 use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::present::{self, ConnectionExt as _};
 use x11rb::protocol::render::{self, ConnectionExt as _, Pictformat};
-use x11rb::protocol::xinput::ChangeReason;
+use x11rb::protocol::sync::{self, ConnectionExt as _};
+use x11rb::protocol::xfixes::{self, ConnectionExt as _};
+use x11rb::protocol::xinput::{
+    self, ChangeReason, ConnectionExt as _, PointerEventFlags, XIEventMask,
+};
 use x11rb::protocol::xkb::{EventType, MapPart, SelectEventsAux};
 use x11rb::protocol::xproto::{
     self, ConnectionExt as _, CreateWindowAux, EventMask, Timestamp, Visualtype, WindowClass,
@@ -36,13 +41,20 @@ use x11rb::resource_manager::{
 use x11rb::xcb_ffi::XCBConnection;
 
 use crate::application::AppHandler;
+use crate::common_util::ClickCounter;
+use crate::gesture::GesturePhase;
+use crate::{Code, HotKey, KbKey, KeyboardLayouts, Modifiers};
 
 use super::clipboard::Clipboard;
+use super::global_hotkeys::GlobalHotKeys;
+use super::ime::Ime;
 use super::pointer::{DeviceInfo, PointersState};
 use super::util;
 use super::window::Window;
-use crate::backend::shared::linux;
+use super::xsettings::XSettings;
+use crate::backend::shared::linux::event_clock::EventClock;
 use crate::backend::shared::xkb::{self};
+use crate::backend::shared::{hardware_keycode_to_code, linux};
 
 // This creates a `struct WindowAtoms` containing the specified atoms as members (along with some
 // convenience methods to intern and query those atoms). We use the following atoms:
@@ -64,6 +76,17 @@ use crate::backend::shared::xkb::{self};
 //
 // https://www.x.org/releases/X11R7.6/doc/xorg-docs/specs/ICCCM/icccm.html#window_deletion
 //
+// _NET_WM_SYNC_REQUEST
+//
+// Including this atom in the WM_PROTOCOLS property on each window makes the window manager
+// wait for us to paint each new size before showing it, which stops resizing from flickering.
+//
+// https://specifications.freedesktop.org/wm-spec/wm-spec-1.3.html#idm45805407643248
+//
+// _NET_WM_SYNC_REQUEST_COUNTER
+//
+// A property containing the XSync counter which we update once we've painted a new size.
+//
 // _NET_WM_PID
 //
 // A property containing the PID of the process that created the window.
@@ -99,6 +122,8 @@ x11rb::atom_manager! {
     pub(crate) AppAtoms: AppAtomsCookie {
         WM_PROTOCOLS,
         WM_DELETE_WINDOW,
+        _NET_WM_SYNC_REQUEST,
+        _NET_WM_SYNC_REQUEST_COUNTER,
         _NET_WM_PID,
         _NET_WM_NAME,
         UTF8_STRING,
@@ -117,6 +142,12 @@ x11rb::atom_manager! {
         ABS_XTILT: b"Abs Tilt X",
         ABS_YTILT: b"Abs Tilt Y",
         ABS_WHEEL: b"Abs Wheel",
+        ABS_DISTANCE: b"Abs Distance",
+        WACOM_SERIAL_IDS: b"Wacom Serial IDs",
+        WACOM_TOOL_TYPE: b"Wacom Tool Type",
+        STYLUS,
+        ERASER,
+        CURSOR,
     }
 }
 
@@ -206,6 +237,38 @@ pub(crate) struct AppInner {
     render_argb32_pictformat_cursor: Option<Pictformat>,
     /// The attached input devices, with internal mutability because X events can make them change.
     pointers: RefCell<PointersState>,
+    /// The connection to the input method, if one is running.
+    ime: Option<Ime>,
+    /// Whether the present extension is available, to synchronize frames with the display.
+    present_supported: bool,
+    /// Whether the sync extension is available, to synchronize resizing with the window manager.
+    sync_supported: bool,
+    /// The major version of the xfixes extension, or 0 if it isn't available. Version 4 can
+    /// hide the cursor, and version 5 added pointer barriers.
+    xfixes_version: u32,
+    /// The window which the pointer is locked or confined to, if any, which receives the raw
+    /// motion events of the pointer.
+    raw_motion_window: Cell<Option<u32>>,
+    /// Converts the timestamps of events to `Instant`s.
+    event_clock: RefCell<EventClock>,
+    /// The modifiers which are active in the xkb state, which windows can query even while
+    /// `state` is borrowed to handle a key event.
+    modifiers: Cell<Modifiers>,
+    /// The keyboard layouts of the xkb keymap, which windows can query like `modifiers`.
+    keyboard_layouts: RefCell<KeyboardLayouts>,
+    /// Translates keys under the active layout, which is likewise usable during key events.
+    key_lookup: RefCell<Option<xkb::KeyLookup>>,
+    /// Used to load the keymap of the core keyboard again, when it changes.
+    xkb_context: RefCell<xkb::Context>,
+    xkb_device_id: xkb::DeviceId,
+    /// Our best guess at the refresh interval of the screen, before it can be measured.
+    refresh_interval: Option<Duration>,
+    /// The desktop's settings, such as the double-click interval.
+    xsettings: XSettings,
+    /// The global hotkeys which we grabbed on the root window.
+    global_hotkeys: RefCell<GlobalHotKeys>,
+    /// The handler which `Application::run` was given, which hears of global hotkeys.
+    app_handler: RefCell<Option<Box<dyn AppHandler>>>,
 }
 
 /// The mutable `Application` state.
@@ -226,6 +289,8 @@ pub(crate) struct Cursors {
     pub not_allowed: Option<xproto::Cursor>,
     pub row_resize: Option<xproto::Cursor>,
     pub col_resize: Option<xproto::Cursor>,
+    /// A blank cursor, used for hiding the cursor while it's over one of our windows.
+    pub invisible: Option<xproto::Cursor>,
 }
 
 #[derive(Clone)]
@@ -237,7 +302,8 @@ impl Application {
         Ok(Application { inner })
     }
 
-    pub fn run(self, _handler: Option<Box<dyn AppHandler>>) {
+    pub fn run(self, handler: Option<Box<dyn AppHandler>>) {
+        self.inner.app_handler.replace(handler);
         if let Err(e) = self.inner.run_inner() {
             tracing::error!("{}", e);
         }
@@ -298,23 +364,21 @@ impl AppInner {
             .core_keyboard_device_id(&connection)
             .context("get core keyboard device id")?;
 
-        let keymap = xkb_context
-            .keymap_from_x11_device(&connection, &device_id)
-            .context("key map from device")?;
-
         connection
             .xkb_select_events(
                 device_id.0 as u16,
                 EventType::default(),
-                EventType::STATE_NOTIFY,
+                // The keymap is loaded again when the keyboard or its layouts change
+                EventType::STATE_NOTIFY | EventType::NEW_KEYBOARD_NOTIFY | EventType::MAP_NOTIFY,
                 MapPart::default(),
                 MapPart::default(),
                 &SelectEventsAux::default(),
             )
             .context("Subscribing to State notify events")?;
-        let xkb_state = xkb_context
-            .state_from_x11_keymap(&keymap, &connection, &device_id)
-            .context("State from keymap and device")?;
+        let xkb_state = AppInner::load_xkb_state(&connection, &mut xkb_context, &device_id)?;
+        let modifiers = Cell::new(xkb_state.active_modifiers());
+        let keyboard_layouts = RefCell::new(xkb_state.layouts());
+        let key_lookup = RefCell::new(xkb_state.key_lookup());
         let window_id = AppInner::create_event_window(&connection, screen_num)?;
         let state = RefCell::new(State {
             quitting: false,
@@ -324,6 +388,36 @@ impl AppInner {
 
         let (idle_read, idle_write) = nix::unistd::pipe2(nix::fcntl::OFlag::O_NONBLOCK)?;
 
+        let ime = match Ime::connect(idle_write) {
+            Ok(ime) => Some(ime),
+            Err(e) => {
+                tracing::info!("Input method unavailable, falling back to simulated input: {e:#}");
+                None
+            }
+        };
+
+        let present_supported = connection
+            .extension_information(present::X11_EXTENSION_NAME)?
+            .and_then(|_| connection.present_query_version(1, 0).ok())
+            .map(|cookie| cookie.reply().is_ok())
+            .unwrap_or(false);
+        let sync_supported = connection
+            .extension_information(sync::X11_EXTENSION_NAME)?
+            .and_then(|_| connection.sync_initialize(3, 1).ok())
+            .map(|cookie| cookie.reply().is_ok())
+            .unwrap_or(false);
+        let xfixes_version = connection
+            .extension_information(xfixes::X11_EXTENSION_NAME)?
+            .and_then(|_| connection.xfixes_query_version(5, 0).ok())
+            .and_then(|cookie| cookie.reply().ok())
+            .map_or(0, |reply| reply.major_version);
+        let refresh_interval = util::refresh_rate(&connection, window_id)
+            .map(|rate| Duration::from_secs_f64(1.0 / rate));
+        let xsettings = XSettings::read(&connection, screen_num).unwrap_or_else(|e| {
+            tracing::warn!("failed to read xsettings: {e:#}");
+            XSettings::default()
+        });
+
         let pictformats = connection.render_query_pict_formats()?;
         let render_create_cursor_supported = matches!(connection
             .extension_information(render::X11_EXTENSION_NAME)?
@@ -370,6 +464,9 @@ impl AppInner {
             not_allowed: load_cursor("not-allowed"),
             row_resize: load_cursor("row-resize"),
             col_resize: load_cursor("col-resize"),
+            invisible: AppInner::create_invisible_cursor(&connection, screen_num)
+                .map_err(|e| tracing::warn!("Unable to create invisible cursor, error: {}", e))
+                .ok(),
         };
 
         let atoms = AppAtoms::new(&connection)?
@@ -415,9 +512,88 @@ impl AppInner {
             argb_visual_type,
             render_argb32_pictformat_cursor,
             pointers: RefCell::new(pointers),
+            ime,
+            present_supported,
+            sync_supported,
+            xfixes_version,
+            raw_motion_window: Cell::new(None),
+            event_clock: RefCell::new(EventClock::new()),
+            modifiers,
+            keyboard_layouts,
+            key_lookup,
+            xkb_context: RefCell::new(xkb_context),
+            xkb_device_id: device_id,
+            refresh_interval,
+            xsettings,
+            global_hotkeys: RefCell::new(GlobalHotKeys::default()),
+            app_handler: RefCell::new(None),
         }))
     }
 
+    /// Load the keymap of the keyboard `device_id`, and its state.
+    fn load_xkb_state(
+        connection: &XCBConnection,
+        xkb_context: &mut xkb::Context,
+        device_id: &xkb::DeviceId,
+    ) -> Result<xkb::KeyEventsState, Error> {
+        use x11rb::protocol::xkb::ConnectionExt;
+        let keymap = xkb_context
+            .keymap_from_x11_device(connection, device_id)
+            .context("key map from device")?;
+        let mut xkb_state = xkb_context
+            .state_from_x11_keymap(&keymap, connection, device_id)
+            .context("State from keymap and device")?;
+        // Start from the modifiers which are already held, rather than waiting for them to change
+        let initial_state = connection
+            .xkb_get_state(device_id.0 as u16)?
+            .reply()
+            .context("get xkb state")?;
+        xkb_state.update_xkb_state(xkb::ActiveModifiers {
+            base_mods: initial_state.base_mods.into(),
+            latched_mods: initial_state.latched_mods.into(),
+            locked_mods: initial_state.locked_mods.into(),
+            base_layout: initial_state.base_group as u32,
+            latched_layout: initial_state.latched_group as u32,
+            locked_layout: initial_state.locked_group.into(),
+        });
+        Ok(xkb_state)
+    }
+
+    /// Load the keymap again after the keyboard or its layouts changed, such as after running
+    /// `setxkbmap`, so that the new layouts take effect.
+    fn reload_keymap(&self) -> Result<(), Error> {
+        let xkb_state = AppInner::load_xkb_state(
+            self.connection(),
+            &mut self.xkb_context.borrow_mut(),
+            &self.xkb_device_id,
+        )?;
+        let mut state = borrow_mut!(self.state)?;
+        state.xkb_state = xkb_state;
+        self.modifiers.set(state.xkb_state.active_modifiers());
+        self.key_lookup.replace(state.xkb_state.key_lookup());
+        let layouts = state.xkb_state.layouts();
+        drop(state);
+        self.regrab_global_hotkeys();
+        self.set_keyboard_layouts(layouts)
+    }
+
+    /// Record the keyboard layouts, and tell every window if they changed.
+    fn set_keyboard_layouts(&self, layouts: KeyboardLayouts) -> Result<(), Error> {
+        if *self.keyboard_layouts.borrow() == layouts {
+            return Ok(());
+        }
+        self.keyboard_layouts.replace(layouts);
+        let windows = borrow!(self.state)?
+            .windows
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        for w in windows {
+            w.handle_keyboard_layout_changed();
+        }
+        Ok(())
+    }
+
     /// Return the ARGB32 pictformat of the server, but only if RENDER's CreateCursor is supported
     #[inline]
     pub(crate) fn render_argb32_pictformat_cursor(&self) -> Option<Pictformat> {
@@ -463,6 +639,37 @@ impl AppInner {
         Ok(id)
     }
 
+    /// Create a cursor with a single transparent pixel.
+    fn create_invisible_cursor(
+        conn: &XCBConnection,
+        screen_num: usize,
+    ) -> Result<xproto::Cursor, Error> {
+        let root = conn.setup().roots[screen_num].root;
+        let pixmap = conn.generate_id()?;
+        conn.create_pixmap(1, pixmap, root, 1, 1)?;
+        let gc = conn.generate_id()?;
+        conn.create_gc(gc, pixmap, &xproto::CreateGCAux::new().foreground(0))?;
+        conn.poly_fill_rectangle(
+            pixmap,
+            gc,
+            &[xproto::Rectangle {
+                x: 0,
+                y: 0,
+                width: 1,
+                height: 1,
+            }],
+        )?;
+        let cursor = conn.generate_id()?;
+        // The pixmap doubles as the mask, so the only pixel is transparent.
+        let result = conn
+            .create_cursor(cursor, pixmap, pixmap, 0, 0, 0, 0, 0, 0, 0, 0)?
+            .check()
+            .context("create invisible cursor");
+        conn.free_gc(gc)?;
+        conn.free_pixmap(pixmap)?;
+        result.map(|()| cursor)
+    }
+
     pub(crate) fn add_window(&self, id: u32, window: Rc<Window>) -> Result<(), Error> {
         borrow_mut!(self.state)?.windows.insert(id, window);
         Ok(())
@@ -483,6 +690,10 @@ impl AppInner {
             .ok_or_else(|| anyhow!("No window with id {}", id))
     }
 
+    fn root_window(&self) -> xproto::Window {
+        self.connection().setup().roots[self.screen_num()].root
+    }
+
     #[inline]
     pub(crate) fn connection(&self) -> &XCBConnection {
         &self.shared.connection
@@ -520,6 +731,10 @@ impl AppInner {
         self.pointers.borrow().device_info(id).cloned()
     }
 
+    pub(crate) fn gestures_supported(&self) -> bool {
+        self.pointers.borrow().gestures_supported
+    }
+
     fn reinitialize_pointers(&self) {
         match super::pointer::initialize_pointers(
             &self.shared.connection,
@@ -540,6 +755,173 @@ impl AppInner {
         self.root_visual_type
     }
 
+    /// Whether windows can use the present extension to synchronize frames with the display.
+    pub(crate) fn present_supported(&self) -> bool {
+        self.present_supported
+    }
+
+    /// Whether windows can take part in the `_NET_WM_SYNC_REQUEST` protocol.
+    pub(crate) fn sync_supported(&self) -> bool {
+        self.sync_supported
+    }
+
+    /// The major version of the xfixes extension, or 0 if it isn't available.
+    pub(crate) fn xfixes_version(&self) -> u32 {
+        self.xfixes_version
+    }
+
+    pub(crate) fn raw_motion_window(&self) -> Option<u32> {
+        self.raw_motion_window.get()
+    }
+
+    /// Send the raw motion of the pointer to `window`, or stop listening to it.
+    ///
+    /// Raw events are only delivered to the root window, so we can't select them per window.
+    pub(crate) fn set_raw_motion_window(&self, window: Option<u32>) -> Result<(), Error> {
+        let listening = self.raw_motion_window.replace(window).is_some();
+        if listening == window.is_some() {
+            return Ok(());
+        }
+        let mask = if window.is_some() {
+            XIEventMask::RAW_MOTION
+        } else {
+            XIEventMask::from(0u32)
+        };
+        let conn = self.connection();
+        conn.xinput_xi_select_events(
+            conn.setup().roots[self.screen_num()].root,
+            &[xinput::EventMask {
+                deviceid: xinput::Device::ALL_MASTER.into(),
+                mask: vec![mask],
+            }],
+        )?
+        .check()?;
+        Ok(())
+    }
+
+    /// The `Instant` at which an event with server time `time` happened.
+    pub(crate) fn event_time(&self, time: Timestamp) -> Instant {
+        self.event_clock.borrow_mut().instant(time)
+    }
+
+    /// The keyboard modifiers which are currently active.
+    pub(crate) fn modifiers(&self) -> Modifiers {
+        self.modifiers.get()
+    }
+
+    /// The layouts of the keymap, and which of them is active.
+    pub(crate) fn keyboard_layouts(&self) -> KeyboardLayouts {
+        self.keyboard_layouts.borrow().clone()
+    }
+
+    /// The key which `code` produces with `mods` held, under the active layout.
+    pub(crate) fn key_for_code(&self, code: Code, mods: Modifiers) -> Option<KbKey> {
+        self.key_lookup.borrow().as_ref()?.key_for_code(code, mods)
+    }
+
+    /// The physical key which produces `key` under the active layout, and the modifiers for it.
+    pub(crate) fn code_for_key(&self, key: &KbKey) -> Option<(Code, Modifiers)> {
+        self.key_lookup.borrow().as_ref()?.code_for_key(key)
+    }
+
+    /// Grab `hotkey` on the root window, so that we hear of it whichever window has focus.
+    ///
+    /// Hotkeys of logical keys are grabbed on the physical key which produces them in the
+    /// active layout.
+    pub(crate) fn register_global_hotkey(&self, id: u32, hotkey: &HotKey) -> Result<(), Error> {
+        let (keycode, mods) = self.hotkey_grab(hotkey)?;
+        self.global_hotkeys.borrow_mut().grab(
+            self.connection(),
+            self.root_window(),
+            id,
+            hotkey,
+            keycode,
+            mods,
+        )
+    }
+
+    /// The key and modifiers to grab for `hotkey` under the active layout.
+    fn hotkey_grab(&self, hotkey: &HotKey) -> Result<(xproto::Keycode, Modifiers), Error> {
+        let mut mods: Modifiers = hotkey.mods.into();
+        let code = match hotkey.code {
+            Some(code) => code,
+            None => {
+                let (code, level_mods) = self.code_for_key(&hotkey.key).ok_or_else(|| {
+                    anyhow!("no key produces {:?} in the active layout", hotkey.key)
+                })?;
+                mods |= level_mods;
+                code
+            }
+        };
+        let keycode = (8..=u8::MAX)
+            .find(|&keycode| hardware_keycode_to_code(keycode.into()) == code)
+            .ok_or_else(|| anyhow!("{code} has no X11 keycode"))?;
+        Ok((keycode, mods))
+    }
+
+    /// Grab the global hotkeys again after the keymap or the layout changed, which may have
+    /// moved them to other keys.
+    ///
+    /// Hotkeys which can't be grabbed any more are unregistered, and the `AppHandler` is told.
+    fn regrab_global_hotkeys(&self) {
+        let result = self.global_hotkeys.borrow_mut().regrab(
+            self.connection(),
+            self.root_window(),
+            |hotkey| self.hotkey_grab(hotkey),
+        );
+        let failed = match result {
+            Ok(failed) => failed,
+            Err(err) => {
+                tracing::warn!("failed to grab the global hotkeys again: {err:#}");
+                return;
+            }
+        };
+        for (id, hotkey, err) in failed {
+            tracing::warn!("failed to grab the global hotkey {hotkey} again: {err:#}");
+            if let Some(handler) = self.app_handler.borrow_mut().as_mut() {
+                handler.global_hotkey_failed(id, err.into());
+            }
+        }
+    }
+
+    /// Release the grab of the global hotkey `id`.
+    pub(crate) fn unregister_global_hotkey(&self, id: u32) -> Result<(), Error> {
+        self.global_hotkeys
+            .borrow_mut()
+            .ungrab(self.connection(), self.root_window(), id)
+    }
+
+    /// Tell the `AppHandler` about a key press on the root window, which is for one of our
+    /// global hotkeys.
+    fn handle_global_hotkey(&self, keycode: xproto::Keycode, state: xproto::KeyButMask) {
+        let Some(id) = self.global_hotkeys.borrow().id_for(keycode, state) else {
+            return;
+        };
+        if let Some(handler) = self.app_handler.borrow_mut().as_mut() {
+            handler.global_hotkey(id);
+        }
+    }
+
+    pub(crate) fn refresh_interval(&self) -> Option<Duration> {
+        self.refresh_interval
+    }
+
+    /// A click counter which uses the desktop's double-click interval and distance.
+    pub(crate) fn click_counter(&self) -> ClickCounter {
+        let counter = ClickCounter::default();
+        if let Some(interval) = self.xsettings.integer("Net/DoubleClickTime") {
+            counter.set_interval_ms(interval.max(0) as u64);
+        }
+        if let Some(distance) = self.xsettings.integer("Net/DoubleClickDistance") {
+            counter.set_distance(distance.into());
+        }
+        counter
+    }
+
+    pub(crate) fn ime(&self) -> Option<&Ime> {
+        self.ime.as_ref()
+    }
+
     #[inline]
     pub(crate) fn atoms(&self) -> &AppAtoms {
         &self.shared.atoms
@@ -576,6 +958,9 @@ impl AppInner {
                     .context("EXPOSE - failed to get window")?;
                 w.handle_expose(ev).context("EXPOSE - failed to handle")?;
             }
+            Event::KeyPress(ev) if ev.event == self.root_window() => {
+                self.handle_global_hotkey(ev.detail, ev.state);
+            }
             Event::KeyPress(ev) => {
                 let w = self
                     .window(ev.event)
@@ -585,11 +970,13 @@ impl AppInner {
 
                 w.handle_key_event(
                     hw_keycode as u32,
+                    ev.state.into(),
                     &mut state.xkb_state,
                     keyboard_types::KeyState::Down,
                     // Detecting whether the key press is a repeat is a massive pain on x11
                     // so just don't do it and hope that's fine
                     false,
+                    ev.time,
                 );
             }
             Event::XkbStateNotify(ev) => {
@@ -602,7 +989,23 @@ impl AppInner {
                     latched_layout: ev.latched_group as u32,
                     locked_layout: ev.locked_group.into(),
                 });
+                self.modifiers.set(state.xkb_state.active_modifiers());
+                let layouts = state.xkb_state.layouts();
+                let layout_changed = *self.keyboard_layouts.borrow() != layouts;
+                if layout_changed {
+                    self.key_lookup.replace(state.xkb_state.key_lookup());
+                }
+                drop(state);
+                if layout_changed {
+                    self.regrab_global_hotkeys();
+                }
+                self.set_keyboard_layouts(layouts)?;
+            }
+            Event::XkbNewKeyboardNotify(_) | Event::XkbMapNotify(_) => {
+                self.reload_keymap()?;
             }
+            // The release of a global hotkey
+            Event::KeyRelease(ev) if ev.event == self.root_window() => {}
             Event::KeyRelease(ev) => {
                 let w = self
                     .window(ev.event)
@@ -612,17 +1015,59 @@ impl AppInner {
                 let mut state = borrow_mut!(self.state)?;
                 w.handle_key_event(
                     hw_keycode as u32,
+                    ev.state.into(),
                     &mut state.xkb_state,
                     keyboard_types::KeyState::Up,
                     false,
+                    ev.time,
                 );
             }
-            Event::XinputHierarchy(_) => {
+            Event::XinputHierarchy(ev) => {
                 self.reinitialize_pointers();
+                // Pens which were unplugged won't tell us that they left proximity
+                let present = self
+                    .pointers
+                    .borrow()
+                    .device_infos
+                    .keys()
+                    .copied()
+                    .collect::<Vec<_>>();
+                let windows = borrow!(self.state)?
+                    .windows
+                    .values()
+                    .cloned()
+                    .collect::<Vec<_>>();
+                for w in windows {
+                    w.pens_proximity_out(|id| !present.contains(&id), ev.time);
+                }
             }
             Event::XinputDeviceChanged(ev) if ev.reason == ChangeReason::DEVICE_CHANGE => {
                 self.reinitialize_pointers();
             }
+            // The Wacom driver changes this property when a tool comes into or out of proximity
+            Event::XinputProperty(ev) if ev.property == self.shared.atoms.WACOM_SERIAL_IDS => {
+                let serial = super::pointer::read_tool_serial(
+                    &self.shared.connection,
+                    ev.deviceid,
+                    &self.shared.atoms,
+                )
+                .context("PROPERTY - failed to read the tool serial")?
+                .unwrap_or(0);
+                let previous = self
+                    .pointers
+                    .borrow_mut()
+                    .set_tool_serial(ev.deviceid, serial);
+                if let Some(tool) = previous.filter(|tool| tool.serial != Some(serial.into())) {
+                    let windows = borrow!(self.state)?
+                        .windows
+                        .values()
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    for w in windows {
+                        w.handle_pen_proximity_out(ev.deviceid, tool, ev.time);
+                    }
+                }
+            }
             Event::XinputButtonPress(ev) => {
                 let w = self
                     .window(ev.event)
@@ -631,8 +1076,16 @@ impl AppInner {
                 // X doesn't have dedicated scroll events: it uses mouse buttons instead.
                 // Buttons 4/5 are vertical; 6/7 are horizontal.
                 if ev.detail >= 4 && ev.detail <= 7 {
-                    w.handle_wheel(ev)
-                        .context("BUTTON_PRESS - failed to handle wheel")?;
+                    // Devices with smooth scrolling also send these buttons for old clients,
+                    // but we handle their scroll valuators instead
+                    let emulated = (ev.flags | PointerEventFlags::POINTER_EMULATED) == ev.flags;
+                    let smooth = self
+                        .pointer_device(ev.sourceid)
+                        .is_some_and(|device| !device.scroll_valuators.is_empty());
+                    if !(emulated && smooth) {
+                        w.handle_wheel(ev)
+                            .context("BUTTON_PRESS - failed to handle wheel")?;
+                    }
                 } else {
                     w.handle_button_press(ev)?;
                 }
@@ -649,10 +1102,52 @@ impl AppInner {
                 }
             }
             Event::XinputMotion(ev) => {
+                self.handle_motion(std::slice::from_ref(ev))?;
+            }
+            Event::XinputRawMotion(ev) => {
+                // We might still get a few events after the window has stopped listening
+                if let Some(window) = self.raw_motion_window.get() {
+                    let w = self
+                        .window(window)
+                        .context("RAW_MOTION - failed to get window")?;
+                    w.handle_raw_motion(ev);
+                }
+            }
+            Event::XinputGesturePinchBegin(ev) => {
+                let w = self
+                    .window(ev.event)
+                    .context("GESTURE_PINCH_BEGIN - failed to get window")?;
+                w.handle_gesture_pinch(ev, GesturePhase::Began);
+            }
+            Event::XinputGesturePinchUpdate(ev) => {
+                let w = self
+                    .window(ev.event)
+                    .context("GESTURE_PINCH_UPDATE - failed to get window")?;
+                w.handle_gesture_pinch(ev, GesturePhase::Changed);
+            }
+            Event::XinputGesturePinchEnd(ev) => {
                 let w = self
                     .window(ev.event)
-                    .context("MOTION_NOTIFY - failed to get window")?;
-                w.handle_motion_notify(ev)?;
+                    .context("GESTURE_PINCH_END - failed to get window")?;
+                w.handle_gesture_pinch(ev, GesturePhase::Ended);
+            }
+            Event::XinputGestureSwipeBegin(ev) => {
+                let w = self
+                    .window(ev.event)
+                    .context("GESTURE_SWIPE_BEGIN - failed to get window")?;
+                w.handle_gesture_swipe(ev, GesturePhase::Began);
+            }
+            Event::XinputGestureSwipeUpdate(ev) => {
+                let w = self
+                    .window(ev.event)
+                    .context("GESTURE_SWIPE_UPDATE - failed to get window")?;
+                w.handle_gesture_swipe(ev, GesturePhase::Changed);
+            }
+            Event::XinputGestureSwipeEnd(ev) => {
+                let w = self
+                    .window(ev.event)
+                    .context("GESTURE_SWIPE_END - failed to get window")?;
+                w.handle_gesture_swipe(ev, GesturePhase::Ended);
             }
             Event::XinputTouchBegin(ev) => {
                 let w = self
@@ -676,6 +1171,8 @@ impl AppInner {
                 let w = self
                     .window(ev.event)
                     .context("LEAVE_NOTIFY - failed to get window")?;
+                // We don't see the scroll valuators change while the pointer is elsewhere
+                self.pointers.borrow_mut().reset_scroll_valuators();
                 w.handle_leave_notify(ev)?;
             }
             Event::ClientMessage(ev) => {
@@ -738,6 +1235,13 @@ impl AppInner {
                     .handle_property_notify(*ev)
                     .context("PROPERTY_NOTIFY event handling for primary")?;
             }
+            Event::PresentCompleteNotify(ev) => {
+                let w = self
+                    .window(ev.window)
+                    .context("PRESENT_COMPLETE_NOTIFY - failed to get window")?;
+                w.handle_complete_notify(ev)
+                    .context("PRESENT_COMPLETE_NOTIFY - failed to handle")?;
+            }
             Event::FocusIn(ev) => {
                 let w = self
                     .window(ev.event)
@@ -768,16 +1272,70 @@ impl AppInner {
         Ok(false)
     }
 
+    /// Handle consecutive motion events of one device in one window.
+    fn handle_motion(&self, events: &[xinput::MotionEvent]) -> Result<(), Error> {
+        let Some(first) = events.first() else {
+            return Ok(());
+        };
+        let w = self
+            .window(first.event)
+            .context("MOTION_NOTIFY - failed to get window")?;
+        // Scrolling is reported through the valuators of motion events, so split the batch at
+        // each scroll and only merge the movements in between
+        let mut start = 0;
+        for (idx, ev) in events.iter().enumerate() {
+            let scroll_delta = self.pointers.borrow_mut().scroll_delta(
+                ev.sourceid,
+                &ev.valuator_mask,
+                &ev.axisvalues,
+            );
+            if let Some(delta) = scroll_delta {
+                if start < idx {
+                    w.handle_motion_notify(&events[start..idx])?;
+                }
+                w.handle_smooth_scroll(ev, delta)?;
+                start = idx + 1;
+            }
+        }
+        if start < events.len() {
+            w.handle_motion_notify(&events[start..])?;
+        }
+        Ok(())
+    }
+
+    /// Collect the motion events of the same device and window which are queued right after
+    /// `first`, so that they can be delivered together. Also returns the event after them, if
+    /// one is queued.
+    fn coalesce_motion(
+        &self,
+        first: xinput::MotionEvent,
+    ) -> Result<(Vec<xinput::MotionEvent>, Option<Event>), Error> {
+        let mut batch = vec![first];
+        loop {
+            match self.shared.connection.poll_for_event()? {
+                Some(Event::XinputMotion(ev))
+                    if ev.deviceid == batch[0].deviceid
+                        && ev.sourceid == batch[0].sourceid
+                        && ev.event == batch[0].event =>
+                {
+                    batch.push(ev);
+                }
+                next => return Ok((batch, next)),
+            }
+        }
+    }
+
     fn run_inner(&self) -> Result<(), Error> {
-        // Try to figure out the refresh rate of the current screen. We run the idle loop at that
-        // rate. The rate-limiting of the idle loop has two purposes:
+        // We run the idle loop at the refresh rate of the current screen. The rate-limiting of
+        // the idle loop has two purposes:
         //  - When the present extension is disabled, we paint in the idle loop. By limiting the
         //    idle loop to the monitor's refresh rate, we aren't painting unnecessarily.
         //  - By running idle commands at a limited rate, we limit spurious wake-ups: if the X11
         //    connection is otherwise idle, we'll wake up at most once per frame, run *all* the
         //    pending idle commands, and then go back to sleep.
-        let refresh_rate = util::refresh_rate(self.connection(), self.window_id).unwrap_or(60.0);
-        let timeout = Duration::from_millis((1000.0 / refresh_rate) as u64);
+        let timeout = self
+            .refresh_interval
+            .unwrap_or(Duration::from_micros(16_667));
         let mut last_idle_time = Instant::now();
         loop {
             // Figure out when the next wakeup needs to happen
@@ -816,7 +1374,16 @@ impl AppInner {
             }
 
             while let Some(ev) = event {
-                match self.handle_event(&ev) {
+                // Devices such as pens can move much more often than we can handle, so
+                // consecutive motion events are merged into one
+                let (result, next) = match ev {
+                    Event::XinputMotion(ev) => {
+                        let (batch, next) = self.coalesce_motion(ev)?;
+                        (self.handle_motion(&batch).map(|()| false), next)
+                    }
+                    ev => (self.handle_event(&ev), None),
+                };
+                match result {
                     Ok(quit) => {
                         if quit {
                             return Ok(());
@@ -826,9 +1393,27 @@ impl AppInner {
                         tracing::error!("Error handling event: {:#}", e);
                     }
                 }
-                event = self.shared.connection.poll_for_event()?;
+                event = match next {
+                    Some(next) => Some(next),
+                    None => self.shared.connection.poll_for_event()?,
+                };
+            }
+
+            // Apply the last of any burst of resizes only once all pending events are handled
+            if let Ok(state) = self.state.try_borrow() {
+                let values = state.windows.values().cloned().collect::<Vec<_>>();
+                drop(state);
+                for w in values {
+                    if let Err(e) = w.apply_pending_configure() {
+                        tracing::error!("Error applying window configuration: {:#}", e);
+                    }
+                }
+            } else {
+                tracing::error!("In configure handling, application state already borrowed");
             }
 
+            self.handle_ime_events();
+
             let now = Instant::now();
             if let Some(timeout) = next_timeout {
                 if timeout <= now {
@@ -858,6 +1443,22 @@ impl AppInner {
         }
     }
 
+    /// Apply the updates sent by the input method to the text field which has its focus.
+    fn handle_ime_events(&self) {
+        let Some(ime) = self.ime() else {
+            return;
+        };
+        while let Some(event) = ime.next_event() {
+            let Some(window) = ime.focused_window() else {
+                continue;
+            };
+            match self.window(window) {
+                Ok(w) => w.handle_ime_event(ime, event),
+                Err(e) => tracing::error!("IME - failed to get window: {e:#}"),
+            }
+        }
+    }
+
     fn finalize_quit(&self) {
         log_x11!(self.shared.connection.destroy_window(self.window_id));
         if let Err(e) = nix::unistd::close(self.idle_read) {
@@ -882,6 +1483,26 @@ impl AppHandle {
     }
 }
 
+/// Wakes up the event loop; `idle_write` should be the writing end of the idle pipe.
+pub(crate) fn wake_idle_pipe(idle_write: RawFd) {
+    loop {
+        match nix::unistd::write(idle_write, &[0]) {
+            Err(nix::errno::Errno::EINTR) => {}
+            // The pipe is full, so the event loop is going to wake up anyway
+            Err(nix::errno::Errno::EAGAIN) => {
+                break;
+            }
+            Err(e) => {
+                tracing::error!("Failed to write to idle pipe: {}", e);
+                break;
+            }
+            Ok(_) => {
+                break;
+            }
+        }
+    }
+}
+
 /// Clears out our idle pipe; `idle_read` should be the reading end of a pipe that was opened with
 /// O_NONBLOCK.
 fn drain_idle_pipe(idle_read: RawFd) -> Result<(), Error> {
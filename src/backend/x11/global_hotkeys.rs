@@ -0,0 +1,305 @@
+//! Global hotkeys, which we grab on the root window.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Error};
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{ConnectionExt, GrabMode, KeyButMask, Keycode, ModMask, Window};
+use x11rb::xcb_ffi::XCBConnection;
+
+use crate::{HotKey, Modifiers};
+
+/// The combinations of the lock modifiers, which grabs only match exactly, so that each
+/// hotkey is grabbed once with every one of them. NumLock is Mod2 on practically every keymap.
+fn lock_variants() -> [ModMask; 4] {
+    [
+        ModMask::from(0u16),
+        ModMask::LOCK,
+        ModMask::M2,
+        ModMask::LOCK | ModMask::M2,
+    ]
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Grab {
+    keycode: Keycode,
+    mods: ModMask,
+}
+
+/// A registered hotkey, and the key which we grabbed for it.
+#[derive(Debug)]
+struct Registration {
+    hotkey: HotKey,
+    grab: Grab,
+}
+
+/// The global hotkeys which are registered, by the id they were registered with.
+#[derive(Debug, Default)]
+pub(crate) struct GlobalHotKeys {
+    registrations: HashMap<u32, Registration>,
+}
+
+impl GlobalHotKeys {
+    /// Grab `keycode` with `mods` held on `root` for `hotkey`, registered as `id`, in place of
+    /// its previous grab if it had one.
+    pub fn grab(
+        &mut self,
+        conn: &XCBConnection,
+        root: Window,
+        id: u32,
+        hotkey: &HotKey,
+        keycode: Keycode,
+        mods: Modifiers,
+    ) -> Result<(), Error> {
+        let grab = Grab {
+            keycode,
+            mods: mod_mask(mods),
+        };
+        if let Some(registration) = self.registrations.get_mut(&id) {
+            if registration.grab == grab {
+                registration.hotkey = hotkey.clone();
+                return Ok(());
+            }
+        }
+        if self.registrations.values().any(|other| other.grab == grab) {
+            return Err(anyhow::anyhow!(
+                "the hotkey is already registered with another id"
+            ));
+        }
+        grab_key(conn, root, grab)?;
+        let registration = Registration {
+            hotkey: hotkey.clone(),
+            grab,
+        };
+        if let Some(previous) = self.registrations.insert(id, registration) {
+            ungrab(conn, root, previous.grab)?;
+        }
+        conn.flush()?;
+        Ok(())
+    }
+
+    /// Release the grab of the hotkey `id`, if it has one.
+    pub fn ungrab(&mut self, conn: &XCBConnection, root: Window, id: u32) -> Result<(), Error> {
+        if let Some(registration) = self.registrations.remove(&id) {
+            ungrab(conn, root, registration.grab)?;
+            conn.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Grab every hotkey again after the keymap or the layout changed, with `find` giving the
+    /// key and modifiers which now produce a hotkey.
+    ///
+    /// Hotkeys may swap keys, such as Ctrl+A and Ctrl+Q when switching between QWERTY and
+    /// AZERTY, so all of the keys which moved are released before any are grabbed again.
+    /// The hotkeys which can't be grabbed any more are unregistered and returned.
+    pub fn regrab(
+        &mut self,
+        conn: &XCBConnection,
+        root: Window,
+        find: impl FnMut(&HotKey) -> Result<(Keycode, Modifiers), Error>,
+    ) -> Result<Vec<(u32, HotKey, Error)>, Error> {
+        let changes = self.moved_grabs(find);
+        for (id, _) in &changes {
+            ungrab(conn, root, self.registrations[id].grab)?;
+        }
+        let mut failed = Vec::new();
+        for (id, grab) in changes {
+            match grab.and_then(|grab| grab_key(conn, root, grab).map(|()| grab)) {
+                Ok(grab) => self.registrations.get_mut(&id).unwrap().grab = grab,
+                Err(err) => {
+                    let registration = self.registrations.remove(&id).unwrap();
+                    failed.push((id, registration.hotkey, err));
+                }
+            }
+        }
+        conn.flush()?;
+        Ok(failed)
+    }
+
+    /// The new grabs of the hotkeys whose keys moved, or why they can't be grabbed any more,
+    /// such as no key producing them or another hotkey already needing their new key.
+    ///
+    /// Hotkeys are taken in the order of their ids, so that the one registered with the
+    /// lower id keeps a key which two of them now need.
+    fn moved_grabs(
+        &self,
+        mut find: impl FnMut(&HotKey) -> Result<(Keycode, Modifiers), Error>,
+    ) -> Vec<(u32, Result<Grab, Error>)> {
+        let mut ids = self.registrations.keys().copied().collect::<Vec<_>>();
+        ids.sort_unstable();
+        let mut taken = Vec::new();
+        let mut changes = Vec::new();
+        for id in ids {
+            let registration = &self.registrations[&id];
+            let grab = find(&registration.hotkey).map(|(keycode, mods)| Grab {
+                keycode,
+                mods: mod_mask(mods),
+            });
+            match grab {
+                Ok(grab) if taken.contains(&grab) => changes.push((
+                    id,
+                    Err(anyhow::anyhow!(
+                        "the hotkey now needs the same key as another hotkey"
+                    )),
+                )),
+                Ok(grab) => {
+                    taken.push(grab);
+                    if grab != registration.grab {
+                        changes.push((id, Ok(grab)));
+                    }
+                }
+                Err(err) => changes.push((id, Err(err))),
+            }
+        }
+        changes
+    }
+
+    /// The hotkey which a key press on the root window is for, if any.
+    pub fn id_for(&self, keycode: Keycode, state: KeyButMask) -> Option<u32> {
+        let locks = u16::from(ModMask::LOCK) | u16::from(ModMask::M2);
+        let mods = u16::from(state) & 0xff & !locks;
+        self.registrations
+            .iter()
+            .find(|(_, registration)| {
+                let grab = registration.grab;
+                grab.keycode == keycode && u16::from(grab.mods) == mods
+            })
+            .map(|(id, _)| *id)
+    }
+}
+
+/// Grab the key of `grab` on `root` with every combination of the lock modifiers.
+fn grab_key(conn: &XCBConnection, root: Window, grab: Grab) -> Result<(), Error> {
+    // Send all the grabs before waiting for any of them to fail
+    let cookies = lock_variants()
+        .into_iter()
+        .map(|lock| {
+            conn.grab_key(
+                false,
+                root,
+                grab.mods | lock,
+                grab.keycode,
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let result = cookies
+        .into_iter()
+        .try_for_each(|cookie| cookie.check())
+        .context("the hotkey is taken by another application");
+    if result.is_err() {
+        // Release the variants which we did get
+        ungrab(conn, root, grab)?;
+    }
+    result
+}
+
+fn ungrab(conn: &XCBConnection, root: Window, grab: Grab) -> Result<(), Error> {
+    for lock in lock_variants() {
+        conn.ungrab_key(grab.keycode, root, grab.mods | lock)?;
+    }
+    Ok(())
+}
+
+/// The core protocol modifiers of `mods`, with the usual mapping of Alt to Mod1 and of the
+/// logo key to Mod4.
+fn mod_mask(mods: Modifiers) -> ModMask {
+    let mut mask = ModMask::from(0u16);
+    for (modifier, bit) in [
+        (Modifiers::SHIFT, ModMask::SHIFT),
+        (Modifiers::CONTROL, ModMask::CONTROL),
+        (Modifiers::ALT, ModMask::M1),
+        (Modifiers::META, ModMask::M4),
+    ] {
+        if mods.contains(modifier) {
+            mask |= bit;
+        }
+    }
+    mask
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{KbKey, RawMods};
+
+    #[test]
+    fn modifier_masks() {
+        assert_eq!(u16::from(mod_mask(Modifiers::empty())), 0);
+        let mods = Modifiers::SHIFT | Modifiers::CONTROL | Modifiers::ALT | Modifiers::META;
+        let mask = ModMask::SHIFT | ModMask::CONTROL | ModMask::M1 | ModMask::M4;
+        assert_eq!(mod_mask(mods), mask);
+        // Lock modifiers aren't part of a hotkey
+        assert_eq!(mod_mask(Modifiers::CAPS_LOCK | Modifiers::ALT), ModMask::M1);
+    }
+
+    #[test]
+    fn ids_ignore_locks() {
+        let mut hotkeys = GlobalHotKeys::default();
+        let grab = Grab {
+            keycode: 45,
+            mods: mod_mask(Modifiers::CONTROL | Modifiers::SHIFT),
+        };
+        let hotkey = HotKey::new(RawMods::CtrlShift, "K");
+        hotkeys
+            .registrations
+            .insert(7, Registration { hotkey, grab });
+
+        let ctrl_shift = u16::from(KeyButMask::CONTROL | KeyButMask::SHIFT);
+        for locks in [0, u16::from(KeyButMask::LOCK), u16::from(KeyButMask::MOD2)] {
+            // The pointer buttons are in the state too
+            let state = ctrl_shift | locks | u16::from(KeyButMask::BUTTON1);
+            assert_eq!(hotkeys.id_for(45, state.into()), Some(7));
+        }
+        assert_eq!(hotkeys.id_for(46, ctrl_shift.into()), None);
+        let ctrl = KeyButMask::CONTROL;
+        assert_eq!(hotkeys.id_for(45, ctrl), None);
+    }
+
+    /// A layout which produces each of `keys` with Ctrl on the given keycode.
+    fn layout<'a>(
+        keys: &'a [(&str, Keycode)],
+    ) -> impl FnMut(&HotKey) -> Result<(Keycode, Modifiers), Error> + 'a {
+        move |hotkey| {
+            keys.iter()
+                .find(|(key, _)| hotkey.key == KbKey::Character(key.to_string()))
+                .map(|(_, keycode)| (*keycode, Modifiers::CONTROL))
+                .ok_or_else(|| anyhow::anyhow!("no key produces {:?}", hotkey.key))
+        }
+    }
+
+    #[test]
+    fn swapped_keys_regrab() {
+        let qwerty = [("a", 38), ("q", 24), ("w", 25), ("1", 10)];
+        let mut hotkeys = GlobalHotKeys::default();
+        let grab = |keycode| Grab {
+            keycode,
+            mods: mod_mask(Modifiers::CONTROL),
+        };
+        for (id, (key, keycode)) in (1..).zip(qwerty) {
+            let hotkey = HotKey::new(RawMods::Ctrl, key);
+            let grab = grab(keycode);
+            hotkeys
+                .registrations
+                .insert(id, Registration { hotkey, grab });
+        }
+        assert!(hotkeys.moved_grabs(layout(&qwerty)).is_empty());
+
+        // A and Q swap keys, which mustn't look like a conflict. This layout also puts W on
+        // the key which A now needs, and has no key for 1.
+        let changes = hotkeys.moved_grabs(layout(&[("a", 24), ("q", 38), ("w", 24)]));
+        let changes = changes
+            .into_iter()
+            .map(|(id, grab)| (id, grab.ok()))
+            .collect::<Vec<_>>();
+        let expected = [
+            (1, Some(grab(24))),
+            (2, Some(grab(38))),
+            (3, None),
+            (4, None),
+        ];
+        assert_eq!(changes, expected);
+    }
+}
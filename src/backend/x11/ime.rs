@@ -0,0 +1,330 @@
+//! Input method support for X11, using the IBus D-Bus protocol.
+//!
+//! IBus (and fcitx5, through its IBus frontend) exposes input contexts over a private D-Bus
+//! connection, whose address is advertised in a file under `~/.config/ibus/bus`. We create a
+//! single input context for the application, and move it between windows as they gain and lose
+//! focus. Key presses in text fields are offered to the input context first; the preedit and
+//! commit strings it sends back are then applied to the focused text field's `InputHandler`.
+//!
+//! All the D-Bus round trips happen on background threads, and the event loop only waits a
+//! short while for the input method to decide whether it wants a key press, so that a slow or
+//! hung IBus can't stall keyboard input.
+
+use std::cell::Cell;
+use std::os::unix::io::RawFd;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Error};
+use zbus::blocking::{ConnectionBuilder, Proxy, SignalIterator};
+use zbus::export::serde::Serialize;
+use zbus::zvariant::{DynamicType, OwnedObjectPath, OwnedValue, Value};
+
+use super::application::wake_idle_pipe;
+
+const IBUS_SERVICE: &str = "org.freedesktop.IBus";
+const IBUS_PATH: &str = "/org/freedesktop/IBus";
+const IBUS_INTERFACE: &str = "org.freedesktop.IBus";
+const INPUT_CONTEXT_INTERFACE: &str = "org.freedesktop.IBus.InputContext";
+
+// From `IBusCapabilite` in ibustypes.h
+const IBUS_CAP_PREEDIT_TEXT: u32 = 1 << 0;
+const IBUS_CAP_FOCUS: u32 = 1 << 3;
+// From `IBusModifierType` in ibustypes.h
+const IBUS_RELEASE_MASK: u32 = 1 << 30;
+
+/// How long a key event waits for the input method, before it is handled as though the input
+/// method didn't want it.
+const KEY_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// A key event for the input method, as its keysym, evdev keycode and IBus modifier state.
+type KeyRequest = (u32, u32, u32);
+
+/// An update from the input method, to be applied to the focused text field.
+#[derive(Debug)]
+pub(crate) enum ImeEvent {
+    /// Replace the composition (or the selection, if there is none) with this text.
+    Commit(String),
+    /// Replace the composition (or the selection) with this text, and mark it as composing.
+    ///
+    /// `cursor` is the position of the caret within `text`, in chars.
+    Preedit { text: String, cursor: usize },
+}
+
+/// The application's connection to the input method.
+pub(crate) struct Ime {
+    context: Proxy<'static>,
+    events: Receiver<ImeEvent>,
+    /// Sends key events to the thread which offers them to the input method.
+    keys: Sender<KeyRequest>,
+    /// Whether the input method wanted each key event, in the order they were sent.
+    key_results: Receiver<bool>,
+    /// The number of key events which timed out, and which the input method has yet to answer.
+    late_keys: Cell<usize>,
+    /// The window whose text field currently has the input context's focus.
+    focus: Cell<Option<u32>>,
+    /// Whether the input method currently has a (non-empty) preedit string.
+    composing: Cell<bool>,
+}
+
+impl Ime {
+    /// Connect to the running IBus daemon, if any, and create an input context.
+    ///
+    /// `idle_pipe` is written to whenever the input method sends an update, so that the event
+    /// loop wakes up to apply it.
+    pub(crate) fn connect(idle_pipe: RawFd) -> Result<Ime, Error> {
+        let address = ibus_address().context("find the IBus address")?;
+        let conn = ConnectionBuilder::address(address.as_str())?
+            .build()
+            .context("connect to IBus")?;
+        let ibus = Proxy::new(&conn, IBUS_SERVICE, IBUS_PATH, IBUS_INTERFACE)?;
+        let path: OwnedObjectPath = ibus
+            .call("CreateInputContext", &("glazier",))
+            .context("create IBus input context")?;
+        let context = Proxy::new_owned(
+            conn,
+            IBUS_SERVICE,
+            path.into_inner(),
+            INPUT_CONTEXT_INTERFACE,
+        )?;
+        context.call_noreply(
+            "SetCapabilities",
+            &(IBUS_CAP_PREEDIT_TEXT | IBUS_CAP_FOCUS,),
+        )?;
+
+        let (sender, events) = mpsc::channel();
+        spawn_listener(context.receive_all_signals()?, sender, idle_pipe)?;
+        let (keys, requests) = mpsc::channel();
+        let (results, key_results) = mpsc::channel();
+        spawn_key_processor(context.clone(), requests, results)?;
+
+        Ok(Ime {
+            context,
+            events,
+            keys,
+            key_results,
+            late_keys: Cell::new(0),
+            focus: Cell::new(None),
+            composing: Cell::new(false),
+        })
+    }
+
+    /// The window whose text field has the input method's focus, if any.
+    pub(crate) fn focused_window(&self) -> Option<u32> {
+        self.focus.get()
+    }
+
+    /// Whether the input method is in the middle of a composition.
+    pub(crate) fn is_composing(&self) -> bool {
+        self.composing.get()
+    }
+
+    /// Give the input method's focus to a text field in `window`.
+    pub(crate) fn focus_in(&self, window: u32) {
+        if self.focus.replace(Some(window)) != Some(window) {
+            self.call("FocusIn", &());
+        }
+    }
+
+    /// Take the input method's focus away from `window`, if it has it.
+    pub(crate) fn focus_out(&self, window: u32) {
+        if self.focus.get() == Some(window) {
+            self.focus.set(None);
+            self.composing.set(false);
+            self.call("FocusOut", &());
+        }
+    }
+
+    /// Discard any ongoing composition, for example because the text field changed.
+    pub(crate) fn reset(&self) {
+        self.composing.set(false);
+        self.call("Reset", &());
+    }
+
+    /// Tell the input method where the text being edited is, in root window coordinates, so
+    /// that it can place its candidate window next to it.
+    pub(crate) fn set_cursor_location(&self, x: i32, y: i32, width: i32, height: i32) {
+        self.call("SetCursorLocation", &(x, y, width, height));
+    }
+
+    /// Offer a key event to the input method.
+    ///
+    /// `keycode` is the X11 keycode and `state` the X11 modifier state of the event. Returns
+    /// `true` if the input method consumed the key, in which case it must not be processed
+    /// any further.
+    ///
+    /// This waits at most [`KEY_TIMEOUT`] for the input method. Whilst it is still busy with
+    /// key events which took longer than that, further ones aren't offered to it at all.
+    pub(crate) fn process_key(&self, keysym: u32, keycode: u32, state: u16, down: bool) -> bool {
+        while self.late_keys.get() > 0 {
+            if self.key_results.try_recv().is_err() {
+                return false;
+            }
+            self.late_keys.set(self.late_keys.get() - 1);
+        }
+        let mut state = u32::from(state);
+        if !down {
+            state |= IBUS_RELEASE_MASK;
+        }
+        // IBus uses evdev keycodes, which are offset by 8 from X11 keycodes
+        let keycode = keycode.saturating_sub(8);
+        if self.keys.send((keysym, keycode, state)).is_err() {
+            return false;
+        }
+        match self.key_results.recv_timeout(KEY_TIMEOUT) {
+            Ok(handled) => handled,
+            Err(RecvTimeoutError::Timeout) => {
+                tracing::warn!("IBus took too long to process a key event");
+                self.late_keys.set(1);
+                false
+            }
+            Err(RecvTimeoutError::Disconnected) => false,
+        }
+    }
+
+    /// Take the next pending update from the input method.
+    pub(crate) fn next_event(&self) -> Option<ImeEvent> {
+        let event = self.events.try_recv().ok()?;
+        if let ImeEvent::Preedit { text, .. } = &event {
+            self.composing.set(!text.is_empty());
+        } else {
+            self.composing.set(false);
+        }
+        Some(event)
+    }
+
+    fn call<B>(&self, method: &str, body: &B)
+    where
+        B: Serialize + DynamicType,
+    {
+        if let Err(e) = self.context.call_noreply(method, body) {
+            tracing::warn!("IBus {method} failed: {e}");
+        }
+    }
+}
+
+/// Listen for the input context's signals on a background thread, forwarding them to the
+/// event loop.
+fn spawn_listener(
+    signals: SignalIterator<'static>,
+    sender: Sender<ImeEvent>,
+    idle_pipe: RawFd,
+) -> Result<(), Error> {
+    std::thread::Builder::new()
+        .name("ibus".into())
+        .spawn(move || {
+            for message in signals {
+                let Some(member) = message.member() else {
+                    continue;
+                };
+                let event = match member.as_str() {
+                    "CommitText" => message
+                        .body::<(OwnedValue,)>()
+                        .ok()
+                        .and_then(|(text,)| ibus_text(&text))
+                        .map(ImeEvent::Commit),
+                    "UpdatePreeditText" => message.body::<(OwnedValue, u32, bool)>().ok().and_then(
+                        |(text, cursor, visible)| {
+                            let text = if visible {
+                                ibus_text(&text)?
+                            } else {
+                                String::new()
+                            };
+                            Some(ImeEvent::Preedit {
+                                text,
+                                cursor: cursor as usize,
+                            })
+                        },
+                    ),
+                    "HidePreeditText" => Some(ImeEvent::Preedit {
+                        text: String::new(),
+                        cursor: 0,
+                    }),
+                    _ => None,
+                };
+                if let Some(event) = event {
+                    if sender.send(event).is_err() {
+                        // The application has quit
+                        break;
+                    }
+                    wake_idle_pipe(idle_pipe);
+                }
+            }
+        })?;
+    Ok(())
+}
+
+/// Offer the key events sent to `requests` to the input method on a background thread, and
+/// send whether it wanted each of them to `results`.
+fn spawn_key_processor(
+    context: Proxy<'static>,
+    requests: Receiver<KeyRequest>,
+    results: Sender<bool>,
+) -> Result<(), Error> {
+    std::thread::Builder::new()
+        .name("ibus-keys".into())
+        .spawn(move || {
+            for request in requests {
+                let handled = match context.call::<_, _, bool>("ProcessKeyEvent", &request) {
+                    Ok(handled) => handled,
+                    Err(e) => {
+                        tracing::warn!("IBus ProcessKeyEvent failed: {e}");
+                        false
+                    }
+                };
+                if results.send(handled).is_err() {
+                    // The application has quit
+                    break;
+                }
+            }
+        })?;
+    Ok(())
+}
+
+/// Extract the string from a serialized `IBusText`, which has the signature `(sa{sv}sv)`.
+fn ibus_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Value(inner) => ibus_text(inner),
+        Value::Structure(text) => match text.fields().get(2)? {
+            Value::Str(s) => Some(s.as_str().to_owned()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Find the address of the IBus daemon's D-Bus connection.
+///
+/// This is either given by `IBUS_ADDRESS`, or stored in a file named after the machine id and
+/// the X11 display.
+fn ibus_address() -> Result<String, Error> {
+    if let Ok(address) = std::env::var("IBUS_ADDRESS") {
+        return Ok(address);
+    }
+    let machine_id = std::fs::read_to_string("/var/lib/dbus/machine-id")
+        .or_else(|_| std::fs::read_to_string("/etc/machine-id"))
+        .context("read machine id")?;
+    let display = std::env::var("DISPLAY").context("DISPLAY is not set")?;
+    let (host, display) = display
+        .rsplit_once(':')
+        .ok_or_else(|| anyhow!("invalid DISPLAY {display:?}"))?;
+    let host = if host.is_empty() { "unix" } else { host };
+    let display = display.split('.').next().unwrap_or(display);
+
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .ok_or_else(|| anyhow!("couldn't find the config directory"))?;
+    let path = config
+        .join("ibus")
+        .join("bus")
+        .join(format!("{}-{host}-{display}", machine_id.trim()));
+    let contents =
+        std::fs::read_to_string(&path).with_context(|| format!("read {}", path.display()))?;
+    contents
+        .lines()
+        .find_map(|line| line.strip_prefix("IBUS_ADDRESS="))
+        .map(str::to_owned)
+        .ok_or_else(|| anyhow!("no IBUS_ADDRESS in {}", path.display()))
+}
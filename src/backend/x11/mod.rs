@@ -14,8 +14,6 @@
 
 //! X11 implementation of glazier.
 
-// TODO(x11/render_improvements): screen is currently flashing when resizing in perftest.
-//     Might be related to the "sleep scheduler" in XWindow::render()?
 // TODO(x11/render_improvements): double-buffering / present strategies / etc?
 
 // # Notes on error handling in X11
@@ -37,7 +35,10 @@ pub mod application;
 pub mod clipboard;
 pub mod dialog;
 pub mod error;
+pub mod global_hotkeys;
+pub mod ime;
 pub mod menu;
 pub mod pointer;
 pub mod screen;
 pub mod window;
+pub mod xsettings;
@@ -1,11 +1,16 @@
 use std::collections::HashMap;
 
+use crate::kurbo::Vec2;
+use crate::pointer::{PenTool, PenToolType};
 use anyhow::bail;
 use memchr::memmem;
 use x11rb::{
-    protocol::xinput::{
-        self, ConnectionExt as _, DeviceClass, DeviceClassData, DeviceType, EventMask, Fp3232,
-        XIDeviceInfo, XIEventMask,
+    protocol::{
+        xinput::{
+            self, ConnectionExt as _, DeviceClass, DeviceClassData, DeviceType, EventMask, Fp3232,
+            ScrollType, XIDeviceInfo, XIEventMask, XIGetPropertyItems,
+        },
+        xproto::{Atom, AtomEnum},
     },
     xcb_ffi::XCBConnection,
 };
@@ -15,8 +20,15 @@ use super::application::AppAtoms;
 #[derive(Clone, Debug, Default)]
 pub struct PointersState {
     pub device_infos: HashMap<u16, DeviceInfo>,
+    /// Whether the server supports xinput 2.4, which added touchpad gestures.
+    pub gestures_supported: bool,
 }
 
+// The masks for the xinput 2.4 gesture events, whose types are 27 (pinch begin) to 32 (swipe
+// end), and so straddle the first two words of the event mask. x11rb doesn't have these yet.
+const GESTURE_EVENTS_MASK_LOW: u32 = 0b11111 << 27;
+const GESTURE_EVENTS_MASK_HIGH: u32 = 0b1;
+
 #[derive(Clone, Debug)]
 pub struct ValuatorInfo {
     pub idx: usize,
@@ -31,6 +43,16 @@ impl ValuatorInfo {
             .get(self.idx)
             .map(|x| fixed_to_floating(*x).clamp(self.min, self.max))
     }
+
+    /// Read the valuator, scaled to the range `[0.0, 1.0]`.
+    pub fn read_normalized(&self, axisvalues: &[Fp3232]) -> Option<f64> {
+        let raw = self.read(axisvalues)?;
+        if self.max > self.min {
+            Some((raw - self.min) / (self.max - self.min))
+        } else {
+            None
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -45,6 +67,107 @@ impl PointersState {
     pub fn device_info(&self, id: u16) -> Option<&DeviceInfo> {
         self.device_infos.get(&id)
     }
+
+    /// The distance scrolled by a motion event from device `id`, in scroll increments.
+    ///
+    /// Returns `None` if the event didn't change any scroll valuators.
+    pub fn scroll_delta(&mut self, id: u16, mask: &[u32], axisvalues: &[Fp3232]) -> Option<Vec2> {
+        let device = self.device_infos.get_mut(&id)?;
+        let mut delta = None;
+        for scroll in &mut device.scroll_valuators {
+            let Some(value) = read_masked(mask, axisvalues, scroll.idx) else {
+                continue;
+            };
+            // The first value after the pointer enters a window (or the device changes) only
+            // tells us where the valuator is now
+            let Some(last_value) = scroll.last_value.replace(value) else {
+                continue;
+            };
+            let change = (value - last_value) / scroll.increment;
+            let delta = delta.get_or_insert(Vec2::ZERO);
+            if scroll.horizontal {
+                delta.x += change;
+            } else {
+                delta.y += change;
+            }
+        }
+        delta
+    }
+
+    /// Record that the tool tracked by device `id` changed, returning the tool which was
+    /// previously in proximity, if any.
+    ///
+    /// A `serial` of zero means that no tool is in proximity.
+    pub fn set_tool_serial(&mut self, id: u16, serial: u32) -> Option<PenTool> {
+        let device = self.device_infos.get_mut(&id)?;
+        let previous = device.tool.serial.map(|_| device.tool);
+        device.tool.serial = (serial != 0).then_some(u64::from(serial));
+        previous
+    }
+
+    /// Forget the positions of the scroll valuators, because we may have missed the events which
+    /// changed them (for example while the pointer was outside our windows).
+    pub fn reset_scroll_valuators(&mut self) {
+        for device in self.device_infos.values_mut() {
+            for scroll in &mut device.scroll_valuators {
+                scroll.last_value = None;
+            }
+        }
+    }
+}
+
+/// A valuator which reports smooth scrolling.
+#[derive(Clone, Debug)]
+pub struct ScrollValuator {
+    pub idx: usize,
+    pub horizontal: bool,
+    /// The change of the valuator which corresponds to one step of a mouse wheel.
+    pub increment: f64,
+    pub last_value: Option<f64>,
+}
+
+impl ScrollValuator {
+    fn find_all(classes: &[DeviceClass]) -> Vec<ScrollValuator> {
+        classes
+            .iter()
+            .filter_map(|cl| match &cl.data {
+                DeviceClassData::Scroll(scroll) => {
+                    let increment = fixed_to_floating(scroll.increment);
+                    if increment == 0.0 {
+                        return None;
+                    }
+                    // The valuator class with the same number holds its current value
+                    let last_value = classes.iter().find_map(|cl| match &cl.data {
+                        DeviceClassData::Valuator(val) if val.number == scroll.number => {
+                            Some(fixed_to_floating(val.value))
+                        }
+                        _ => None,
+                    });
+                    Some(ScrollValuator {
+                        idx: scroll.number as usize,
+                        horizontal: scroll.scroll_type == ScrollType::HORIZONTAL,
+                        increment,
+                        last_value,
+                    })
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Read valuator `idx` from an event, whose `axisvalues` only contain the valuators set in
+/// `mask`.
+pub fn read_masked(mask: &[u32], axisvalues: &[Fp3232], idx: usize) -> Option<f64> {
+    let (word, bit) = (idx / 32, idx % 32);
+    if mask.get(word)? & (1 << bit) == 0 {
+        return None;
+    }
+    let preceding = mask[..word].iter().map(|m| m.count_ones()).sum::<u32>()
+        + (mask[word] & ((1 << bit) - 1)).count_ones();
+    axisvalues
+        .get(preceding as usize)
+        .map(|value| fixed_to_floating(*value))
 }
 
 #[derive(Clone, Debug, Default)]
@@ -52,6 +175,7 @@ pub struct PenValuators {
     pub pressure: Option<ValuatorInfo>,
     pub x_tilt: Option<ValuatorInfo>,
     pub y_tilt: Option<ValuatorInfo>,
+    pub distance: Option<ValuatorInfo>,
 }
 
 impl PenValuators {
@@ -71,6 +195,8 @@ impl PenValuators {
                     ret.x_tilt = Some(info);
                 } else if val.label == atoms.ABS_YTILT && ret.y_tilt.is_none() {
                     ret.y_tilt = Some(info);
+                } else if val.label == atoms.ABS_DISTANCE && ret.distance.is_none() {
+                    ret.distance = Some(info);
                 }
             }
         }
@@ -87,8 +213,15 @@ pub struct DeviceInfo {
     pub id: u16,
     pub name: Vec<u8>,
     pub device_type: DeviceType,
+    /// For a slave device, the master device it is attached to; for a master device, the
+    /// master device it is paired with.
+    pub attachment: u16,
     pub device_kind: DeviceKind,
     pub valuators: PenValuators,
+    pub scroll_valuators: Vec<ScrollValuator>,
+    /// For pens, the tool which the device reports. Its serial is only set while the tool is
+    /// in proximity.
+    pub tool: PenTool,
 }
 
 impl std::fmt::Debug for DeviceInfo {
@@ -97,8 +230,11 @@ impl std::fmt::Debug for DeviceInfo {
             .field("id", &self.id)
             .field("name", &String::from_utf8_lossy(&self.name))
             .field("device_type", &self.device_type)
+            .field("attachment", &self.attachment)
             .field("device_kind", &self.device_kind)
             .field("valuators", &self.valuators)
+            .field("scroll_valuators", &self.scroll_valuators)
+            .field("tool", &self.tool)
             .finish()
     }
 }
@@ -116,19 +252,55 @@ pub fn fixed_to_floating(x: Fp3232) -> f64 {
 }
 
 impl DeviceInfo {
+    /// The master pointer which this device moves.
+    pub fn master_pointer(&self) -> u16 {
+        if self.device_type == DeviceType::MASTER_POINTER {
+            self.id
+        } else {
+            self.attachment
+        }
+    }
+
     pub(crate) fn new(dev: XIDeviceInfo, atoms: &AppAtoms) -> DeviceInfo {
         let mut ret = DeviceInfo {
             id: dev.deviceid,
             name: dev.name,
             device_type: dev.type_,
+            attachment: dev.attachment,
             device_kind: DeviceKind::Mouse,
             valuators: PenValuators::new(&dev.classes, atoms),
+            scroll_valuators: ScrollValuator::find_all(&dev.classes),
+            tool: PenTool::default(),
         };
 
         ret.detect_device_kind(&dev.classes);
         ret
     }
 
+    /// Find out which tool a pen device is for, and which one is in proximity.
+    ///
+    /// The Wacom driver makes a device for each kind of tool, and describes them with its own
+    /// properties. For other drivers, we can only go by the device kind.
+    fn read_tool(&mut self, conn: &XCBConnection, atoms: &AppAtoms) -> anyhow::Result<()> {
+        let tool_type = read_property(conn, self.id, atoms.WACOM_TOOL_TYPE, 1)?
+            .first()
+            .copied();
+        self.tool.tool_type = match tool_type {
+            Some(atom) if atom == atoms.STYLUS => PenToolType::Pen,
+            Some(atom) if atom == atoms.ERASER => PenToolType::Eraser,
+            Some(atom) if atom == atoms.CURSOR => PenToolType::Mouse,
+            _ => match self.device_kind {
+                DeviceKind::Pen => PenToolType::Pen,
+                DeviceKind::Eraser => PenToolType::Eraser,
+                DeviceKind::Touch | DeviceKind::Mouse => PenToolType::Unknown,
+            },
+        };
+        if let Some(serial) = read_tool_serial(conn, self.id, atoms)? {
+            self.tool.serial = (serial != 0).then_some(u64::from(serial));
+        }
+        Ok(())
+    }
+
     // xinput doesn't tell us directly what "kind" a pointer device is, so we need to infer it.
     // We mainly do this by looking at the `DeviceClass`es: if there's a touch-related class,
     // we declare it as a touch device. Otherwise, if it has pressure or tilt classes, we declare
@@ -161,8 +333,10 @@ pub(crate) fn initialize_pointers(
     atoms: &AppAtoms,
     window: u32,
 ) -> anyhow::Result<PointersState> {
-    let version = conn.xinput_get_extension_version(b"xinput")?.reply()?;
-    if (version.server_major, version.server_minor) < (2, 2) {
+    // Announcing our version is needed to get smooth scrolling (2.1), touch (2.2) and
+    // gesture (2.4) events
+    let version = conn.xinput_xi_query_version(2, 4)?.reply()?;
+    if (version.major_version, version.minor_version) < (2, 2) {
         // xinput 2.2 added multitouch; xorg has supported it since 2012
         bail!("xinput version {version:?} found, but we require at least 2.2");
     }
@@ -174,7 +348,12 @@ pub(crate) fn initialize_pointers(
     for dev in devices.infos {
         if dev.type_ == DeviceType::MASTER_POINTER || dev.type_ == DeviceType::SLAVE_POINTER {
             let id = dev.deviceid;
-            let info = DeviceInfo::new(dev, atoms);
+            let mut info = DeviceInfo::new(dev, atoms);
+            if matches!(info.device_kind, DeviceKind::Pen | DeviceKind::Eraser) {
+                if let Err(e) = info.read_tool(conn, atoms) {
+                    tracing::warn!("failed to read the tool of pen device {id}: {e}");
+                }
+            }
             tracing::debug!("found pointer device {info:?}");
             device_infos.insert(id, info);
         }
@@ -183,27 +362,74 @@ pub(crate) fn initialize_pointers(
         window,
         &[EventMask {
             deviceid: xinput::Device::ALL.into(),
-            mask: vec![(XIEventMask::DEVICE_CHANGED | XIEventMask::HIERARCHY)],
+            // Property changes tell us when pens come into and out of proximity
+            mask: vec![
+                (XIEventMask::DEVICE_CHANGED | XIEventMask::HIERARCHY | XIEventMask::PROPERTY),
+            ],
         }],
     )?
     .check()?;
 
-    Ok(PointersState { device_infos })
+    Ok(PointersState {
+        device_infos,
+        gestures_supported: (version.major_version, version.minor_version) >= (2, 4),
+    })
 }
 
-pub(crate) fn enable_window_pointers(conn: &XCBConnection, window: u32) -> anyhow::Result<()> {
+/// Read the serial number of the tool which pen device `id` is tracking, from the properties
+/// of the Wacom driver.
+///
+/// Returns `Some(0)` if no tool is in proximity, and `None` if the device doesn't say.
+pub(crate) fn read_tool_serial(
+    conn: &XCBConnection,
+    id: u16,
+    atoms: &AppAtoms,
+) -> anyhow::Result<Option<u32>> {
+    // The tablet id, the serial and id of the previous tool, and the serial and id of the
+    // current tool
+    let serial_ids = read_property(conn, id, atoms.WACOM_SERIAL_IDS, 5)?;
+    Ok(serial_ids.get(3).copied())
+}
+
+/// Read up to `len` 32-bit values of a device property, which is empty if the device doesn't
+/// have it.
+fn read_property(
+    conn: &XCBConnection,
+    id: u16,
+    property: Atom,
+    len: u32,
+) -> anyhow::Result<Vec<u32>> {
+    let reply = conn
+        .xinput_xi_get_property(id, false, property, AtomEnum::ANY.into(), 0, len)?
+        .reply()?;
+    Ok(match reply.items {
+        XIGetPropertyItems::Data32(values) => values,
+        _ => Vec::new(),
+    })
+}
+
+pub(crate) fn enable_window_pointers(
+    conn: &XCBConnection,
+    window: u32,
+    gestures: bool,
+) -> anyhow::Result<()> {
+    let mut mask = vec![
+        (XIEventMask::BUTTON_PRESS
+            | XIEventMask::BUTTON_RELEASE
+            | XIEventMask::MOTION
+            | XIEventMask::TOUCH_BEGIN
+            | XIEventMask::TOUCH_UPDATE
+            | XIEventMask::TOUCH_END),
+    ];
+    if gestures {
+        mask[0] |= XIEventMask::from(GESTURE_EVENTS_MASK_LOW);
+        mask.push(XIEventMask::from(GESTURE_EVENTS_MASK_HIGH));
+    }
     conn.xinput_xi_select_events(
         window,
         &[EventMask {
             deviceid: xinput::Device::ALL_MASTER.into(),
-            mask: vec![
-                (XIEventMask::BUTTON_PRESS
-                    | XIEventMask::BUTTON_RELEASE
-                    | XIEventMask::MOTION
-                    | XIEventMask::TOUCH_BEGIN
-                    | XIEventMask::TOUCH_UPDATE
-                    | XIEventMask::TOUCH_END),
-            ],
+            mask,
         }],
     )?
     .check()?;
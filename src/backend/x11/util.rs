@@ -25,15 +25,24 @@ use x11rb::xcb_ffi::XCBConnection;
 // See: https://github.com/rtbo/rust-xcb/blob/master/examples/randr_screen_modes.rs
 pub fn refresh_rate(conn: &XCBConnection, window_id: Window) -> Option<f64> {
     let try_refresh_rate = || -> Result<f64, Error> {
-        let reply = conn.randr_get_screen_resources(window_id)?.reply()?;
+        let reply = conn
+            .randr_get_screen_resources_current(window_id)?
+            .reply()?;
 
-        // TODO(x11/render_improvements): Figure out a more correct way of getting the screen's refresh rate.
-        //     Or maybe we don't even need this function if I figure out a better way to schedule redraws?
-        //     Assuming the first mode is the one we want to use. This is probably a bug on some setups.
-        //     Any better way to find the correct one?
-        reply
-            .modes
-            .first()
+        // This is only an initial guess: windows on other monitors measure their refresh rate
+        // with the present extension once they start animating.
+        // Use the mode of the first enabled CRTC, falling back to the first mode.
+        let active_mode = reply.crtcs.iter().find_map(|&crtc| {
+            let info = conn
+                .randr_get_crtc_info(crtc, reply.config_timestamp)
+                .ok()?
+                .reply()
+                .ok()?;
+            (info.mode != 0).then_some(info.mode)
+        });
+        active_mode
+            .and_then(|mode| reply.modes.iter().find(|mode_info| mode_info.id == mode))
+            .or_else(|| reply.modes.first())
             .ok_or_else(|| anyhow!("didn't get any modes"))
             .and_then(|mode_info| {
                 let flags = mode_info.mode_flags;
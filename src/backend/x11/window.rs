@@ -15,57 +15,79 @@
 //! X11 window creation and window management.
 
 use std::cell::{Cell, RefCell};
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, HashMap};
 use std::convert::TryFrom;
 use std::os::unix::io::RawFd;
 use std::panic::Location;
 use std::rc::{Rc, Weak};
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use crate::backend::shared::xkb::{xkb_simulate_input, KeyEventsState};
+use crate::backend::shared::xkb::{scancode_to_code, xkb_simulate_input, KeyEventsState};
+use crate::gesture::{Gesture, GestureEvent, GesturePhase};
 use crate::pointer::{
-    Angle, MouseInfo, PenInclination, PenInfo, PointerId, PointerType, TouchInfo,
+    Angle, MouseInfo, PenInclination, PenInfo, PenTool, PenToolType, PointerConstraint, PointerId,
+    PointerType, RelativeMotionEvent, ScrollDeltaUnit, ScrollSource, TouchInfo,
 };
 use crate::scale::Scalable;
 use anyhow::{anyhow, Context, Error};
+use nix::time::ClockId;
 use tracing::{error, warn};
 use x11rb::connection::Connection;
 use x11rb::errors::ReplyOrIdError;
 use x11rb::properties::{WmHints, WmHintsState, WmSizeHints};
+use x11rb::protocol::present::{self, CompleteKind, CompleteNotifyEvent, ConnectionExt as _};
 use x11rb::protocol::render::Pictformat;
-use x11rb::protocol::xinput::{self, DeviceType, ModifierInfo, TouchEventFlags};
+use x11rb::protocol::sync::{self, ConnectionExt as _};
+use x11rb::protocol::xfixes::{self, ConnectionExt as _};
+use x11rb::protocol::xinput::{
+    self, ConnectionExt as _, DeviceType, GesturePinchEventFlags, GestureSwipeEventFlags,
+    GrabOwner, ModifierInfo, TouchEventFlags, XIEventMask,
+};
 use x11rb::protocol::xproto::{
     self, AtomEnum, ChangeWindowAttributesAux, ColormapAlloc, ConfigureNotifyEvent,
-    ConfigureWindowAux, ConnectionExt, EventMask, ImageOrder as X11ImageOrder, KeyButMask,
-    PropMode, WindowClass,
+    ConfigureWindowAux, ConnectionExt, EventMask, GrabMode, GrabStatus,
+    ImageOrder as X11ImageOrder, KeyButMask, NotifyMode, PropMode, Timestamp, WindowClass,
 };
 use x11rb::wrapper::ConnectionExt as _;
 use x11rb::xcb_ffi::XCBConnection;
 
+#[cfg(feature = "accesskit")]
+use accesskit_unix::Adapter as AccessKitAdapter;
+#[cfg(feature = "accesskit")]
+use once_cell::unsync::OnceCell;
 use raw_window_handle::{
     HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle, XcbDisplayHandle,
     XcbWindowHandle,
 };
 
+#[cfg(feature = "accesskit")]
+use crate::backend::shared::linux::accessibility::placeholder_tree;
+use crate::backend::shared::linux::frame_clock::FrameClock;
 use crate::backend::shared::Timer;
-use crate::common_util::IdleCallback;
+use crate::clipboard::ClipboardFormat;
+use crate::common_util::{ClickCounter, IdleCallback};
 use crate::dialog::FileDialogOptions;
+use crate::drag::{DragActions, DragImage, DragToken};
 use crate::error::Error as ShellError;
-use crate::keyboard::{KeyState, Modifiers};
+use crate::keyboard::{Code, KeyState, KeyboardLayouts, Modifiers};
 use crate::kurbo::{Insets, Point, Rect, Size, Vec2};
 use crate::mouse::{Cursor, CursorDesc};
 use crate::region::Region;
 use crate::scale::Scale;
-use crate::text::Event;
+use crate::text::{
+    Event, InputProperties, PreeditAttributes, PreeditSegment, PreeditStyle, Selection,
+};
 use crate::window::{
     FileDialogToken, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowLevel,
 };
 use crate::{window, PointerButton, PointerButtons, PointerEvent, ScaledArea};
 
-use super::application::Application;
+use super::application::{wake_idle_pipe, Application};
 use super::dialog;
+use super::ime::{Ime, ImeEvent};
 use super::menu::Menu;
+use super::pointer::read_masked;
 
 fn size_hints(resizable: bool, size: Size, min_size: Size) -> WmSizeHints {
     let mut size_hints = WmSizeHints::new();
@@ -283,7 +305,7 @@ impl WindowBuilder {
         .check()
         .context("create window")?;
 
-        super::pointer::enable_window_pointers(conn, id)?;
+        super::pointer::enable_window_pointers(conn, id, self.app.gestures_supported())?;
 
         if let Some(colormap) = cw_values.colormap {
             conn.free_colormap(colormap)?;
@@ -341,8 +363,31 @@ impl WindowBuilder {
             // GTK (actually glib) goes fishing in /proc (platform_get_argv0()). We pass.
         }
 
+        // The window manager waits for us to update this counter after we've painted a new size
+        let sync_counter = if self.app.sync_supported() {
+            let counter = conn.generate_id()?;
+            conn.sync_create_counter(counter, sync::Int64 { hi: 0, lo: 0 })?
+                .check()
+                .context("create sync counter")?;
+            conn.change_property32(
+                PropMode::REPLACE,
+                id,
+                atoms._NET_WM_SYNC_REQUEST_COUNTER,
+                AtomEnum::CARDINAL,
+                &[counter],
+            )?
+            .check()
+            .context("set _NET_WM_SYNC_REQUEST_COUNTER")?;
+            Some(counter)
+        } else {
+            None
+        };
+
         // Replace the window's WM_PROTOCOLS with the following.
-        let protocols = [atoms.WM_DELETE_WINDOW];
+        let mut protocols = vec![atoms.WM_DELETE_WINDOW];
+        if sync_counter.is_some() {
+            protocols.push(atoms._NET_WM_SYNC_REQUEST);
+        }
         conn.change_property32(
             PropMode::REPLACE,
             id,
@@ -412,9 +457,38 @@ impl WindowBuilder {
             next_text_field: Cell::new(None),
             active_text_field: Cell::new(None),
             need_to_reset_compose: Cell::new(false),
+            text_field_properties: RefCell::new(HashMap::new()),
             parent,
+            // The present extension reports times on CLOCK_MONOTONIC, in microseconds
+            frame_clock: RefCell::new(FrameClock::new(
+                ClockId::CLOCK_MONOTONIC,
+                self.app.refresh_interval(),
+            )),
+            frame_requested: Cell::new(false),
+            pinch_scale: Cell::new(1.0),
+            captured_devices: RefCell::new(Vec::new()),
+            grabbing_pointer: Cell::new(None),
+            has_focus: Cell::new(false),
+            pointer_constraint: Cell::new(PointerConstraint::None),
+            pointer_barriers: RefCell::new(Vec::new()),
+            cursor_hidden: Cell::new(false),
+            cursor: Cell::new(None),
+            pens_in_proximity: RefCell::new(Vec::new()),
+            click_counter: self.app.click_counter(),
+            buttons: Cell::new(PointerButtons::new()),
+            sync_counter,
+            pending_sync_value: Cell::new(None),
+            pending_size: Cell::new(None),
+            #[cfg(feature = "accesskit")]
+            accesskit_adapter: OnceCell::new(),
         });
 
+        if self.app.present_supported() {
+            let conn = self.app.connection();
+            let eid = conn.generate_id()?;
+            log_x11!(conn.present_select_input(eid, id, present::EventMask::COMPLETE_NOTIFY));
+        }
+
         window.set_title(&self.title);
         if let Some(pos) = self.position {
             window.set_position(pos);
@@ -422,6 +496,8 @@ impl WindowBuilder {
 
         let handle = WindowHandle::new(id, visual_type.visual_id, Rc::downgrade(&window));
         window.connect(handle.clone())?;
+        #[cfg(feature = "accesskit")]
+        window.init_accesskit();
 
         self.app.add_window(id, window)?;
 
@@ -466,7 +542,46 @@ pub(crate) struct Window {
     next_text_field: Cell<Option<TextFieldToken>>,
     active_text_field: Cell<Option<TextFieldToken>>,
     need_to_reset_compose: Cell<bool>,
+    /// The properties set for each text field. Secure fields are kept away from the input method.
+    text_field_properties: RefCell<HashMap<TextFieldToken, InputProperties>>,
     parent: Weak<Window>,
+    /// The refreshes of the display, as reported by the present extension.
+    frame_clock: RefCell<FrameClock>,
+    /// Whether we've asked the present extension to notify us of the next refresh, and so will
+    /// render when it does.
+    frame_requested: Cell<bool>,
+    /// The scale of the ongoing pinch gesture, as of its previous event.
+    pinch_scale: Cell<f64>,
+    /// The master pointer devices which we've grabbed for `capture_pointer`.
+    captured_devices: RefCell<Vec<u16>>,
+    /// The pointer whose button press started the implicit grab we hold, if any. If another
+    /// client takes the grab from us, we won't see that pointer's buttons being released.
+    grabbing_pointer: Cell<Option<PointerId>>,
+    has_focus: Cell<bool>,
+    /// The constraint requested through `set_pointer_constraint`, which applies while the
+    /// window has focus.
+    pointer_constraint: Cell<PointerConstraint>,
+    /// The barriers which currently enforce `pointer_constraint`.
+    pointer_barriers: RefCell<Vec<xfixes::Barrier>>,
+    /// Whether the cursor is hidden while it's over this window.
+    cursor_hidden: Cell<bool>,
+    /// The cursor requested through `set_cursor`, which is shown whenever it isn't hidden.
+    cursor: Cell<Option<xproto::Cursor>>,
+    /// The pen devices which we've told the handler are in proximity, with their tools.
+    pens_in_proximity: RefCell<Vec<(u16, PenTool)>>,
+    click_counter: ClickCounter,
+    /// The buttons which were held down as of the latest button event.
+    buttons: Cell<PointerButtons>,
+    /// The counter we update once we've painted a new size, if the sync extension is available.
+    sync_counter: Option<sync::Counter>,
+    /// The value requested by the last `_NET_WM_SYNC_REQUEST`, which the counter is set to once
+    /// we've painted the configuration that follows it.
+    pending_sync_value: Cell<Option<sync::Int64>>,
+    /// The size (in px) from the latest `ConfigureNotify`. Resizing usually sends a burst of
+    /// these, so we only apply the last one once all pending events have been handled.
+    pending_size: Cell<Option<Size>>,
+    #[cfg(feature = "accesskit")]
+    accesskit_adapter: OnceCell<AccessKitAdapter>,
 }
 
 #[derive(Clone, PartialEq, Eq)]
@@ -544,7 +659,8 @@ impl Window {
     }
 
     fn render(&self) -> Result<(), Error> {
-        self.with_handler(|h| h.prepare_paint());
+        let timing = borrow_mut!(self.frame_clock)?.frame_timing();
+        self.with_handler(|h| h.prepare_paint(timing));
 
         if self.destroyed() {
             return Ok(());
@@ -652,23 +768,53 @@ impl Window {
         Ok(())
     }
 
-    /// Redraw more-or-less now.
-    ///
-    /// "More-or-less" because if we're already waiting on a present, we defer the drawing until it
-    /// completes.
+    /// Redraw now, without waiting for the next refresh of the display.
     fn redraw_now(&self) -> Result<(), Error> {
+        // This frame isn't paced by the display, so no frames were missed
+        borrow_mut!(self.frame_clock)?.cancel_target();
         self.render()?;
         Ok(())
     }
 
-    /// Schedule a redraw on the idle loop, or if we are waiting on present then schedule it for
-    /// when the current present finishes.
+    /// Schedule a redraw for the next refresh of the display, or on the idle loop if the present
+    /// extension isn't available.
     fn request_anim_frame(&self) {
-        let idle = IdleHandle {
-            queue: Arc::clone(&self.idle_queue),
-            pipe: self.idle_pipe,
-        };
-        idle.schedule_redraw();
+        if self.app.present_supported() {
+            if self.frame_requested.replace(true) || self.destroyed() {
+                return;
+            }
+            // If we haven't seen a refresh yet, the notification comes immediately, and tells
+            // us the current refresh counter
+            let target_msc = match self.frame_clock.try_borrow_mut() {
+                Ok(mut clock) => clock.next_target().unwrap_or(0),
+                Err(_) => 0,
+            };
+            log_x11!(self
+                .app
+                .connection()
+                .present_notify_msc(self.id, 0, target_msc, 0, 0));
+        } else {
+            let idle = IdleHandle {
+                queue: Arc::clone(&self.idle_queue),
+                pipe: self.idle_pipe,
+            };
+            idle.schedule_redraw();
+        }
+    }
+
+    pub fn handle_complete_notify(&self, event: &CompleteNotifyEvent) -> Result<(), Error> {
+        if event.kind != CompleteKind::NOTIFY_MSC {
+            return Ok(());
+        }
+        borrow_mut!(self.frame_clock)?.refreshed(event.msc, Duration::from_micros(event.ust));
+        if self.frame_requested.replace(false) {
+            self.render()?;
+            if !self.frame_requested.get() {
+                // The handler didn't ask for another frame, so the animation has stopped
+                borrow_mut!(self.frame_clock)?.cancel_target();
+            }
+        }
+        Ok(())
     }
 
     fn invalidate(&self) {
@@ -734,8 +880,15 @@ impl Window {
             warn!("Unable to load cursor {:?}", cursor);
             return;
         }
+        self.cursor.set(cursor);
+        if !self.cursor_hidden.get() {
+            self.apply_cursor(cursor);
+        }
+    }
+
+    fn apply_cursor(&self, cursor: Option<xproto::Cursor>) {
         let conn = self.app.connection();
-        let changes = ChangeWindowAttributesAux::new().cursor(cursor);
+        let changes = ChangeWindowAttributesAux::new().cursor(cursor.unwrap_or(x11rb::NONE));
         if let Err(e) = conn.change_window_attributes(self.id, &changes) {
             error!("Changing cursor window attribute failed {}", e);
         };
@@ -766,16 +919,37 @@ impl Window {
     pub fn handle_key_event(
         &self,
         scancode: u32,
+        x11_state: u16,
         xkb_state: &mut KeyEventsState,
         key_state: KeyState,
         is_repeat: bool,
+        time: Timestamp,
     ) {
         // This is a horrible hack, but the X11 backend is not actively maintained anyway
         self.with_handler(|handler| {
             let keysym = xkb_state.get_one_sym(scancode);
-            let event = xkb_state.key_event(scancode, keysym, key_state, is_repeat);
+            let mut event = xkb_state.key_event(scancode, keysym, key_state, is_repeat);
+            event.time = Some(self.app.event_time(time));
+            let ime = self
+                .app
+                .ime()
+                .filter(|ime| ime.focused_window() == Some(self.id));
             match key_state {
                 KeyState::Down => {
+                    // While the input method is composing, it gets the first look at key
+                    // presses, so that e.g. Enter confirms the composition instead of
+                    // activating a default button.
+                    let ime_composing = ime.is_some_and(|ime| ime.is_composing());
+                    let was_secure = self
+                        .active_text_field
+                        .get()
+                        .is_some_and(|field| self.text_field_properties(field).is_secure());
+                    if let Some(ime) = ime.filter(|_| ime_composing && !was_secure) {
+                        event.is_composing = true;
+                        if ime.process_key(keysym.raw(), scancode, x11_state, true) {
+                            return;
+                        }
+                    }
                     if handler.key_down(&event) {
                         // The keypress was handled by the user, nothing to do
                         return;
@@ -789,14 +963,36 @@ impl Window {
                         // case. We get the same behaviour on macOS (?)
                         return;
                     };
+                    let secure = self.text_field_properties(field_token).is_secure();
+                    // Secure fields, such as passwords, are kept from the input method, so
+                    // their keys are simulated instead
+                    if let Some(ime) = self.app.ime().filter(|_| !secure) {
+                        let newly_focused = ime.focused_window() != Some(self.id);
+                        ime.focus_in(self.id);
+                        if newly_focused {
+                            self.update_ime_cursor_location(ime, handler, field_token);
+                        }
+                        if !ime_composing
+                            && ime.process_key(keysym.raw(), scancode, x11_state, true)
+                        {
+                            // The input method will send us the resulting text, if any
+                            return;
+                        }
+                    }
                     let mut input_handler = handler.acquire_input_lock(field_token, true);
-                    // Because there is no *other* IME on this backend, we meet the criteria for this method
+                    // The input method, if there is one, didn't want this key press, so we
+                    // meet the criteria for this method
                     xkb_simulate_input(xkb_state, keysym, &event, &mut *input_handler);
                     handler.release_input_lock(field_token);
                 }
                 KeyState::Up => {
                     handler.key_up(&event);
-                    self.reset_text_fields_if_needed(xkb_state, handler);
+                    let next_field = self.reset_text_fields_if_needed(xkb_state, handler);
+                    // The input method may have been sent away, if the field is secure
+                    let ime = ime.filter(|ime| ime.focused_window() == Some(self.id));
+                    if let Some(ime) = ime.filter(|_| next_field.is_some()) {
+                        ime.process_key(keysym.raw(), scancode, x11_state, false);
+                    }
                 }
             }
         });
@@ -809,13 +1005,27 @@ impl Window {
     ) -> Option<TextFieldToken> {
         let next_field = self.next_text_field.get();
         let need_to_reset_compose = self.need_to_reset_compose.take();
+        let ime = self
+            .app
+            .ime()
+            .filter(|ime| ime.focused_window() == Some(self.id));
+        let ime_composing = ime.is_some_and(|ime| ime.is_composing());
+        let next_secure =
+            next_field.is_some_and(|field| self.text_field_properties(field).is_secure());
         {
             let previous_field = self.active_text_field.get();
             // In theory, this should be more proactive - but I'm not sure how to implement that
             // and researching that isn't a high priority
             if next_field != previous_field {
+                if let Some(ime) = ime {
+                    if next_field.is_some() && !next_secure {
+                        ime.reset();
+                    } else {
+                        ime.focus_out(self.id);
+                    }
+                }
                 // If the active field has changed, the composition doesn't make any sense
-                if xkb_state.cancel_composing() {
+                if xkb_state.cancel_composing() || ime_composing {
                     // If we previously were composing, the previous field must have existed
                     // However, the previous field may also have been deleted, so we need to only
                     // reset it if it were enabled
@@ -828,8 +1038,17 @@ impl Window {
                 self.active_text_field.set(next_field);
             }
         }
+        if need_to_reset_compose {
+            if let Some(ime) = ime {
+                if next_secure {
+                    ime.focus_out(self.id);
+                } else {
+                    ime.reset();
+                }
+            }
+        }
         // Shadow previous, as we know it may be outdated, and text_field should be used instead
-        if need_to_reset_compose && xkb_state.cancel_composing() {
+        if need_to_reset_compose && (xkb_state.cancel_composing() || ime_composing) {
             if let Some(text_field) = next_field {
                 // Please note: This might be superfluous
                 let mut ime = handler.acquire_input_lock(text_field, true);
@@ -840,35 +1059,126 @@ impl Window {
         next_field
     }
 
+    fn text_field_properties(&self, token: TextFieldToken) -> InputProperties {
+        let properties = self.text_field_properties.borrow();
+        properties.get(&token).copied().unwrap_or_default()
+    }
+
+    /// Apply an update from the input method to the active text field.
+    pub(crate) fn handle_ime_event(&self, ime: &Ime, event: ImeEvent) {
+        self.with_handler(|handler| {
+            let Some(field) = self.active_text_field.get() else {
+                return;
+            };
+            let mut input = handler.acquire_input_lock(field, true);
+            let composition = input.composition_range();
+            match event {
+                ImeEvent::Commit(text) => {
+                    let range = composition.unwrap_or_else(|| input.selection().range());
+                    // replace_range places the caret after the inserted text
+                    input.replace_range(range, &text);
+                    input.set_composition_range(None);
+                }
+                ImeEvent::Preedit { text, .. } if text.is_empty() => {
+                    if let Some(range) = composition {
+                        input.replace_range(range, "");
+                        input.set_composition_range(None);
+                    }
+                }
+                ImeEvent::Preedit { text, cursor } => {
+                    let range = composition.unwrap_or_else(|| input.selection().range());
+                    let start = range.start;
+                    input.replace_range(range, &text);
+                    input.set_composition_range(Some(start..start + text.len()));
+                    let cursor = text
+                        .char_indices()
+                        .nth(cursor)
+                        .map_or(text.len(), |(idx, _)| idx);
+                    input.set_selection(Selection::caret(start + cursor));
+                    // The styling in the input method's attribute list isn't read yet
+                    input.set_preedit_attributes(PreeditAttributes {
+                        cursor: Some(cursor),
+                        segments: vec![PreeditSegment {
+                            range: 0..text.len(),
+                            style: PreeditStyle::Underline,
+                        }],
+                    });
+                }
+            }
+            handler.release_input_lock(field);
+            self.update_ime_cursor_location(ime, handler, field);
+        });
+    }
+
+    /// Tell the input method where the caret (or the composition, if there is one) of `field`
+    /// is, so that it can place its candidate window next to it.
+    fn update_ime_cursor_location(
+        &self,
+        ime: &Ime,
+        handler: &mut dyn WinHandler,
+        field: TextFieldToken,
+    ) {
+        let input = handler.acquire_input_lock(field, false);
+        let range = input.composition_range().unwrap_or_else(|| {
+            let caret = input.selection().active;
+            caret..caret
+        });
+        let rect = input.slice_bounding_box(range);
+        handler.release_input_lock(field);
+        let Some(rect) = rect else {
+            return;
+        };
+
+        let rect = rect.to_px(self.scale.get()).expand();
+        match self.to_root_px(rect.origin()) {
+            Ok(origin) => ime.set_cursor_location(
+                origin.x as i32,
+                origin.y as i32,
+                rect.width() as i32,
+                rect.height() as i32,
+            ),
+            Err(e) => error!("Failed to position the input method's candidate window: {e}"),
+        }
+    }
+
+    /// Converts a point in this window's pixel coordinates to the root window's.
+    fn to_root_px(&self, point: Point) -> Result<Point, Error> {
+        let conn = self.app.connection();
+        let root = conn.setup().roots[self.app.screen_num()].root;
+        let origin = conn
+            .translate_coordinates(self.id, root, point.x as i16, point.y as i16)?
+            .reply()?;
+        Ok(Point::new(origin.dst_x as _, origin.dst_y as _))
+    }
+
     fn base_pointer_event(
         &self,
         x: i32,
         y: i32,
         mods: ModifierInfo,
-        detail: u32,
+        buttons: PointerButtons,
         src_id: u16,
+        time: Timestamp,
     ) -> PointerEvent {
         // In x11rb, xinput x and y coordinates are i32's but in the protocol they're fixed-precision FP1616s
         // https://github.com/psychon/x11rb/blob/dacfba5e2a8eef4b80df75d9bec9061c3d98d279/xcb-proto-1.15.2/src/xinput.xml#L2374
         let (ev_x, ev_y) = (x as f64 / 65536.0, y as f64 / 65536.0);
         let scale = self.scale.get();
-        let mods = mods.base | mods.locked | mods.latched;
-        // TODO: what are the high 16 bits for? Maybe virtual modifiers?
-        let mods = (mods as u16).into();
-        let button = pointer_button(detail);
+        let mods = modifier_state(mods);
 
         PointerEvent {
             pointer_id: PointerId(src_id as u64),
             is_primary: false,
-            pointer_type: PointerType::Mouse(MouseInfo {
-                wheel_delta: Default::default(),
-            }),
+            pointer_type: PointerType::Mouse(MouseInfo::default()),
+            time: Some(self.app.event_time(time)),
             pos: Point::new(ev_x, ev_y).to_dp(scale),
-            buttons: pointer_buttons(mods),
+            buttons,
             modifiers: key_mods(mods),
-            button,
+            button: PointerButton::None,
             focus: false,
             count: 0,
+            coalesced: Vec::new(),
+            predicted: Vec::new(),
         }
     }
 
@@ -890,7 +1200,14 @@ impl Window {
             pointer_type,
             button,
             pointer_id: PointerId(ev.sourceid as u64 | (ev.detail as u64) << 32),
-            ..self.base_pointer_event(ev.event_x, ev.event_y, ev.mods, ev.detail, ev.sourceid)
+            ..self.base_pointer_event(
+                ev.event_x,
+                ev.event_y,
+                ev.mods,
+                PointerButtons::new(),
+                ev.sourceid,
+                ev.time,
+            )
         }
     }
 
@@ -927,11 +1244,19 @@ impl Window {
 
             let inclination = PenInclination::from_tilt(x_tilt, y_tilt).unwrap_or_default();
 
+            let distance = src_device
+                .valuators
+                .distance
+                .as_ref()
+                .and_then(|val| val.read_normalized(&ev.axisvalues));
+
             let pen_info = PenInfo {
                 pressure,
                 tangential_pressure: 0.0,
                 inclination,
                 twist: Angle::degrees(0.0),
+                distance,
+                tool: src_device.tool,
             };
 
             match src_device.device_kind {
@@ -939,40 +1264,133 @@ impl Window {
                 super::pointer::DeviceKind::Eraser => PointerType::Eraser(pen_info),
                 // TODO: support touch
                 super::pointer::DeviceKind::Touch | super::pointer::DeviceKind::Mouse => {
-                    PointerType::Mouse(MouseInfo {
-                        wheel_delta: Vec2::ZERO,
-                    })
+                    PointerType::Mouse(MouseInfo::default())
                 }
             }
         } else {
-            PointerType::Mouse(MouseInfo {
-                wheel_delta: Vec2::ZERO,
-            })
+            PointerType::Mouse(MouseInfo::default())
         };
 
-        PointerEvent {
+        let mut pointer_ev = PointerEvent {
             is_primary,
             pointer_type,
-            ..self.base_pointer_event(ev.event_x, ev.event_y, ev.mods, ev.detail, ev.sourceid)
+            button: pointer_button(ev.detail),
+            ..self.base_pointer_event(
+                ev.event_x,
+                ev.event_y,
+                ev.mods,
+                xi_pointer_buttons(&ev.button_mask),
+                ev.sourceid,
+                ev.time,
+            )
+        };
+        if let PointerType::Pen(_) | PointerType::Eraser(_) = pointer_ev.pointer_type {
+            let eraser = matches!(pointer_ev.pointer_type, PointerType::Eraser(_));
+            pointer_ev.button = pen_button(pointer_ev.button, eraser);
+            pointer_ev.buttons = pen_buttons(pointer_ev.buttons, eraser);
+        }
+        pointer_ev
+    }
+
+    /// Tell the handler about a pen which we haven't seen in this window since it came into
+    /// proximity, before delivering `pointer_ev`.
+    fn pen_proximity_in(&self, sourceid: u16, pointer_ev: &PointerEvent) {
+        let tool = match &pointer_ev.pointer_type {
+            PointerType::Pen(info) | PointerType::Eraser(info) => info.tool,
+            _ => return,
+        };
+        match self.pens_in_proximity.try_borrow_mut() {
+            Ok(mut pens) if !pens.iter().any(|(id, _)| *id == sourceid) => {
+                pens.push((sourceid, tool))
+            }
+            Ok(_) => return,
+            Err(e) => {
+                error!("Failed to borrow the pens in proximity: {}", e);
+                return;
+            }
+        }
+        self.with_handler(|h| h.pen_proximity_in(pointer_ev));
+    }
+
+    /// The tool of pen device `sourceid` moved out of proximity.
+    pub fn handle_pen_proximity_out(&self, sourceid: u16, tool: PenTool, time: Timestamp) {
+        match self.pens_in_proximity.try_borrow_mut() {
+            Ok(mut pens) => {
+                let Some(idx) = pens.iter().position(|(id, _)| *id == sourceid) else {
+                    return;
+                };
+                pens.remove(idx);
+            }
+            Err(e) => {
+                error!("Failed to borrow the pens in proximity: {}", e);
+                return;
+            }
+        }
+        let pen_info = PenInfo {
+            tool,
+            ..PenInfo::default()
+        };
+        let pointer_ev = PointerEvent {
+            pointer_id: PointerId(sourceid as u64),
+            is_primary: false,
+            pointer_type: if tool.tool_type == PenToolType::Eraser {
+                PointerType::Eraser(pen_info)
+            } else {
+                PointerType::Pen(pen_info)
+            },
+            time: Some(self.app.event_time(time)),
+            ..PointerEvent::default()
+        };
+        self.with_handler(|h| h.pen_proximity_out(&pointer_ev));
+    }
+
+    /// Move the pens for which `remove` returns true out of proximity, for when we won't hear
+    /// from the Wacom driver about them leaving, like when the pointer leaves the window or the
+    /// device is unplugged.
+    pub fn pens_proximity_out(&self, mut remove: impl FnMut(u16) -> bool, time: Timestamp) {
+        let pens = match self.pens_in_proximity.try_borrow() {
+            Ok(pens) => pens
+                .iter()
+                .filter(|(id, _)| remove(*id))
+                .copied()
+                .collect::<Vec<_>>(),
+            Err(e) => {
+                error!("Failed to borrow the pens in proximity: {}", e);
+                return;
+            }
+        };
+        for (sourceid, tool) in pens {
+            self.handle_pen_proximity_out(sourceid, tool, time);
         }
     }
 
     pub fn handle_button_press(&self, ev: &xinput::ButtonPressEvent) -> Result<(), Error> {
         let mut pointer_ev = self.pointer_event(ev);
+        self.pen_proximity_in(ev.sourceid, &pointer_ev);
         // The xcb state field doesn't include the newly pressed button, but
         // druid wants it to be included.
         pointer_ev.buttons = pointer_ev.buttons.with(pointer_ev.button);
-        // TODO: detect the count
-        pointer_ev.count = 1;
+        self.buttons.set(pointer_ev.buttons);
+        let pos_px = pointer_ev.pos.to_px(self.scale.get());
+        pointer_ev.count = match pointer_ev.time {
+            Some(time) => self.click_counter.count_for_click_at(pos_px, time),
+            None => self.click_counter.count_for_click(pos_px),
+        };
+        self.grabbing_pointer.set(Some(pointer_ev.pointer_id));
         self.with_handler(|h| h.pointer_down(&pointer_ev));
         Ok(())
     }
 
     pub fn handle_button_release(&self, ev: &xinput::ButtonPressEvent) -> Result<(), Error> {
         let mut pointer_ev = self.pointer_event(ev);
+        self.pen_proximity_in(ev.sourceid, &pointer_ev);
         // The xcb state includes the newly released button, but druid
         // doesn't want it.
         pointer_ev.buttons = pointer_ev.buttons.without(pointer_ev.button);
+        self.buttons.set(pointer_ev.buttons);
+        if pointer_ev.buttons.is_empty() {
+            self.grabbing_pointer.set(None);
+        }
         self.with_handler(|h| h.pointer_up(&pointer_ev));
         Ok(())
     }
@@ -1000,19 +1418,21 @@ impl Window {
     pub fn handle_wheel(&self, ev: &xinput::ButtonPressEvent) -> Result<(), Error> {
         let mut pointer_ev = self.pointer_event(ev);
 
-        // We use a delta of 120 per tick to match the behavior of Windows.
         let is_shift = pointer_ev.modifiers.shift();
         let delta = match ev.detail {
-            4 if is_shift => (-120.0, 0.0),
-            4 => (0.0, -120.0),
-            5 if is_shift => (120.0, 0.0),
-            5 => (0.0, 120.0),
-            6 => (-120.0, 0.0),
-            7 => (120.0, 0.0),
+            4 if is_shift => (-1.0, 0.0),
+            4 => (0.0, -1.0),
+            5 if is_shift => (1.0, 0.0),
+            5 => (0.0, 1.0),
+            6 => (-1.0, 0.0),
+            7 => (1.0, 0.0),
             _ => return Err(anyhow!("unexpected mouse wheel button: {}", ev.detail)),
         };
         pointer_ev.pointer_type = PointerType::Mouse(MouseInfo {
             wheel_delta: delta.into(),
+            wheel_delta_unit: ScrollDeltaUnit::Lines,
+            scroll_source: ScrollSource::Wheel,
+            ..Default::default()
         });
         pointer_ev.button = PointerButton::None;
 
@@ -1020,30 +1440,381 @@ impl Window {
         Ok(())
     }
 
-    pub fn handle_motion_notify(&self, ev: &xinput::ButtonPressEvent) -> Result<(), Error> {
+    /// Handle a change of the scroll valuators, where `delta` is in scroll increments.
+    ///
+    /// XInput doesn't tell us what kind of device is scrolling, or when a touchpad
+    /// gesture starts and ends.
+    pub fn handle_smooth_scroll(
+        &self,
+        ev: &xinput::ButtonPressEvent,
+        delta: Vec2,
+    ) -> Result<(), Error> {
         let mut pointer_ev = self.pointer_event(ev);
+        // Like for wheel buttons, shift turns vertical scrolling into horizontal scrolling
+        let delta = if pointer_ev.modifiers.shift() && delta.x == 0.0 {
+            Vec2::new(delta.y, 0.0)
+        } else {
+            delta
+        };
+        pointer_ev.pointer_type = PointerType::Mouse(MouseInfo {
+            wheel_delta: delta,
+            wheel_delta_unit: ScrollDeltaUnit::Lines,
+            ..Default::default()
+        });
         pointer_ev.button = PointerButton::None;
+
+        self.with_handler(|h| h.wheel(&pointer_ev));
+        Ok(())
+    }
+
+    pub fn handle_gesture_pinch(&self, ev: &xinput::GesturePinchBeginEvent, phase: GesturePhase) {
+        let cancelled = (ev.flags | GesturePinchEventFlags::GESTURE_PINCH_CANCELLED) == ev.flags;
+        let scale = fp1616_to_f64(ev.scale);
+        let pan = Vec2::new(fp1616_to_f64(ev.delta_x), fp1616_to_f64(ev.delta_y));
+        let event = GestureEvent {
+            gesture: Gesture::Pinch {
+                scale,
+                rotation: Angle::degrees(fp1616_to_f64(ev.delta_angle)),
+                pan: pan.to_dp(self.scale.get()),
+            },
+            phase: if cancelled {
+                GesturePhase::Cancelled
+            } else {
+                phase
+            },
+            finger_count: ev.detail,
+            pos: self.gesture_pos(ev.event_x, ev.event_y),
+            modifiers: key_mods(modifier_state(ev.mods)),
+        };
+        // `zoom` takes the change of scale since the previous event
+        let previous_scale = match phase {
+            GesturePhase::Began => 1.0,
+            _ => self.pinch_scale.get(),
+        };
+        self.pinch_scale.set(scale);
+        self.with_handler(|h| {
+            h.gesture(&event);
+            if phase == GesturePhase::Changed && previous_scale != 0.0 {
+                h.zoom(scale / previous_scale - 1.0);
+            }
+        });
+    }
+
+    pub fn handle_gesture_swipe(&self, ev: &xinput::GestureSwipeBeginEvent, phase: GesturePhase) {
+        let cancelled = (ev.flags | GestureSwipeEventFlags::GESTURE_SWIPE_CANCELLED) == ev.flags;
+        let delta = Vec2::new(fp1616_to_f64(ev.delta_x), fp1616_to_f64(ev.delta_y));
+        let event = GestureEvent {
+            gesture: Gesture::Swipe {
+                delta: delta.to_dp(self.scale.get()),
+            },
+            phase: if cancelled {
+                GesturePhase::Cancelled
+            } else {
+                phase
+            },
+            finger_count: ev.detail,
+            pos: self.gesture_pos(ev.event_x, ev.event_y),
+            modifiers: key_mods(modifier_state(ev.mods)),
+        };
+        self.with_handler(|h| h.gesture(&event));
+    }
+
+    fn gesture_pos(&self, x: i32, y: i32) -> Point {
+        Point::new(fp1616_to_f64(x), fp1616_to_f64(y)).to_dp(self.scale.get())
+    }
+
+    /// Deliver consecutive motion events of one device as a single `pointer_move`, with the
+    /// earlier ones as its coalesced samples.
+    pub fn handle_motion_notify(&self, events: &[xinput::MotionEvent]) -> Result<(), Error> {
+        let mut samples = events
+            .iter()
+            .map(|ev| {
+                let mut pointer_ev = self.pointer_event(ev);
+                pointer_ev.button = PointerButton::None;
+                pointer_ev
+            })
+            .collect::<Vec<_>>();
+        let Some(mut pointer_ev) = samples.pop() else {
+            return Ok(());
+        };
+        self.pen_proximity_in(events[0].sourceid, samples.first().unwrap_or(&pointer_ev));
+        if !samples.is_empty() {
+            samples.push(pointer_ev.clone());
+            pointer_ev.coalesced = samples;
+        }
         self.with_handler(|h| h.pointer_move(&pointer_ev));
         Ok(())
     }
 
     pub fn handle_leave_notify(
         &self,
-        _leave_notify: &xproto::LeaveNotifyEvent,
+        leave_notify: &xproto::LeaveNotifyEvent,
     ) -> Result<(), Error> {
+        // Another client grabbed the pointer while a button was held in this window, so the
+        // button will be released somewhere we can't see
+        if leave_notify.mode == NotifyMode::GRAB {
+            if let Some(pointer_id) = self.grabbing_pointer.take() {
+                let pos = Point::new(leave_notify.event_x as f64, leave_notify.event_y as f64);
+                let pointer_ev = PointerEvent {
+                    pointer_id,
+                    time: Some(self.app.event_time(leave_notify.time)),
+                    pos: pos.to_dp(self.scale.get()),
+                    buttons: self.buttons.replace(PointerButtons::new()),
+                    modifiers: key_mods(leave_notify.state),
+                    ..PointerEvent::default()
+                };
+                self.with_handler(|h| h.pointer_cancel(&pointer_ev));
+            }
+        }
+        self.pens_proximity_out(|_| true, leave_notify.time);
         self.with_handler(|h| h.pointer_leave());
         Ok(())
     }
 
+    /// Grab the master pointer which moves `pointer_id`, so that its events come to this window
+    /// wherever the pointer goes.
+    fn capture_pointer(&self, pointer_id: PointerId) -> Result<(), Error> {
+        // Touch sequences, whose ids include the touch number, always go to the window they
+        // began in
+        if pointer_id.0 >> 32 != 0 {
+            return Ok(());
+        }
+        let device = self
+            .app
+            .pointer_device(pointer_id.0 as u16)
+            .ok_or_else(|| anyhow!("unknown pointer {:?}", pointer_id))?;
+        let master = device.master_pointer();
+        let mut captured = self.captured_devices.borrow_mut();
+        if captured.contains(&master) {
+            return Ok(());
+        }
+        let mask = XIEventMask::BUTTON_PRESS | XIEventMask::BUTTON_RELEASE | XIEventMask::MOTION;
+        let reply = self
+            .app
+            .connection()
+            .xinput_xi_grab_device(
+                self.id,
+                x11rb::CURRENT_TIME,
+                x11rb::NONE,
+                master,
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+                GrabOwner::OWNER,
+                &[mask.into()],
+            )?
+            .reply()?;
+        if reply.status != GrabStatus::SUCCESS {
+            return Err(anyhow!(
+                "grabbing pointer {} failed: {:?}",
+                master,
+                reply.status
+            ));
+        }
+        captured.push(master);
+        Ok(())
+    }
+
+    fn set_pointer_constraint(&self, constraint: PointerConstraint) -> Result<(), Error> {
+        self.pointer_constraint.set(constraint);
+        if self.has_focus.get() {
+            self.update_pointer_barriers()?;
+        }
+        Ok(())
+    }
+
+    /// Put up the pointer barriers which enforce the pointer constraint, replacing any we had
+    /// before, and listen to the raw motion of the pointer while there is one.
+    fn update_pointer_barriers(&self) -> Result<(), Error> {
+        self.remove_pointer_barriers()?;
+        let conn = self.app.connection();
+        let root = conn.setup().roots[self.app.screen_num()].root;
+        // The box which the pointer must stay in, in root window coordinates
+        let (min, max) = match self.pointer_constraint.get() {
+            PointerConstraint::None => return Ok(()),
+            PointerConstraint::Confined => {
+                let size = self.size().size_px();
+                let origin = self.to_root_px(Point::ZERO)?;
+                (
+                    origin,
+                    origin + Vec2::new(size.width - 1.0, size.height - 1.0),
+                )
+            }
+            // A box so small that the pointer can't move
+            PointerConstraint::Locked => {
+                let pointer = conn.query_pointer(root)?.reply()?;
+                let pos = Point::new(pointer.root_x as f64, pointer.root_y as f64);
+                (pos, pos + Vec2::new(1.0, 1.0))
+            }
+        };
+        if self.app.xfixes_version() >= 5 {
+            let (x1, y1) = (min.x.max(0.0) as u16, min.y.max(0.0) as u16);
+            let (x2, y2) = (max.x.max(0.0) as u16, max.y.max(0.0) as u16);
+            let mut barriers = self.pointer_barriers.borrow_mut();
+            for (bx1, by1, bx2, by2) in [
+                (x1, y1, x2, y1),
+                (x2, y1, x2, y2),
+                (x1, y2, x2, y2),
+                (x1, y1, x1, y2),
+            ] {
+                let barrier = conn.generate_id()?;
+                // No directions are allowed, so the barrier blocks the pointer both ways
+                conn.xfixes_create_pointer_barrier(
+                    barrier,
+                    root,
+                    bx1,
+                    by1,
+                    bx2,
+                    by2,
+                    0u32.into(),
+                    &[],
+                )?;
+                barriers.push(barrier);
+            }
+        } else {
+            warn!("The pointer can't be constrained without pointer barriers from xfixes 5");
+        }
+        self.app.set_raw_motion_window(Some(self.id))
+    }
+
+    fn remove_pointer_barriers(&self) -> Result<(), Error> {
+        let conn = self.app.connection();
+        for barrier in self.pointer_barriers.borrow_mut().drain(..) {
+            log_x11!(conn.xfixes_delete_pointer_barrier(barrier));
+        }
+        if self.app.raw_motion_window() == Some(self.id) {
+            self.app.set_raw_motion_window(None)?;
+        }
+        Ok(())
+    }
+
+    /// The physical keys which are held down on the core keyboard.
+    fn pressed_keys(&self) -> Result<Vec<Code>, Error> {
+        let keys = self.app.connection().query_keymap()?.reply()?.keys;
+        Ok((0..256u32)
+            .filter(|&keycode| keys[keycode as usize / 8] & (1 << (keycode % 8)) != 0)
+            .map(scancode_to_code)
+            .collect())
+    }
+
+    /// The buttons of the pointer which moves the cursor for this client, and the cursor's
+    /// position relative to the window if it's on the same screen.
+    fn pointer_state(&self) -> Result<(PointerButtons, Option<Point>), Error> {
+        let conn = self.app.connection();
+        let client_pointer = conn.xinput_xi_get_client_pointer(self.id)?.reply()?;
+        let device = if client_pointer.set {
+            client_pointer.deviceid
+        } else {
+            // The virtual core pointer
+            2
+        };
+        let reply = conn.xinput_xi_query_pointer(self.id, device)?.reply()?;
+        let pos = reply.same_screen.then(|| {
+            Point::new(fp1616_to_f64(reply.win_x), fp1616_to_f64(reply.win_y))
+                .to_dp(self.scale.get())
+        });
+        Ok((xi_pointer_buttons(&reply.buttons), pos))
+    }
+
+    fn set_cursor_visible(&self, visible: bool) {
+        let Some(invisible) = self.app.cursors.invisible else {
+            warn!("Unable to hide the cursor without an invisible cursor");
+            return;
+        };
+        if self.cursor_hidden.replace(!visible) != visible || self.destroyed() {
+            return;
+        }
+        // Unlike xfixes' HideCursor, which hides the cursor on the whole screen, a blank window
+        // cursor only applies while the pointer is over this window.
+        if visible {
+            self.apply_cursor(self.cursor.get());
+        } else {
+            self.apply_cursor(Some(invisible));
+        }
+    }
+
+    pub fn handle_raw_motion(&self, ev: &xinput::RawMotionEvent) {
+        // Valuators 0 and 1 are the x and y axes. Absolute devices such as tablets put positions
+        // rather than movements in them, but those don't make sense to lock anyway.
+        let read = |values: &[xinput::Fp3232]| {
+            let x = read_masked(&ev.valuator_mask, values, 0);
+            let y = read_masked(&ev.valuator_mask, values, 1);
+            (x.is_some() || y.is_some()).then(|| Vec2::new(x.unwrap_or(0.0), y.unwrap_or(0.0)))
+        };
+        let (Some(delta), Some(unaccelerated_delta)) =
+            (read(&ev.axisvalues), read(&ev.axisvalues_raw))
+        else {
+            return;
+        };
+        let event = RelativeMotionEvent {
+            pointer_id: PointerId(ev.sourceid as u64),
+            delta: delta.to_dp(self.scale.get()),
+            unaccelerated_delta,
+        };
+        self.with_handler(|h| h.relative_pointer_motion(&event));
+    }
+
+    fn release_pointer_capture(&self, pointer_id: PointerId) {
+        if pointer_id.0 >> 32 != 0 {
+            return;
+        }
+        let Some(device) = self.app.pointer_device(pointer_id.0 as u16) else {
+            return;
+        };
+        let master = device.master_pointer();
+        let mut captured = self.captured_devices.borrow_mut();
+        if let Some(idx) = captured.iter().position(|&id| id == master) {
+            captured.swap_remove(idx);
+            log_x11!(self
+                .app
+                .connection()
+                .xinput_xi_ungrab_device(x11rb::CURRENT_TIME, master));
+        }
+    }
+
     pub fn handle_got_focus(&self) {
+        #[cfg(feature = "accesskit")]
+        if let Some(adapter) = self.accesskit_adapter.get() {
+            adapter.update_window_focus_state(true);
+        }
+        self.has_focus.set(true);
+        if let Err(e) = self.update_pointer_barriers() {
+            error!("Failed to constrain the pointer: {}", e);
+        }
         self.with_handler(|h| h.got_focus());
     }
 
+    pub fn handle_keyboard_layout_changed(&self) {
+        self.with_handler(|h| h.keyboard_layout_changed());
+    }
+
     pub fn handle_lost_focus(&self, xkb_state: &mut KeyEventsState) {
+        #[cfg(feature = "accesskit")]
+        if let Some(adapter) = self.accesskit_adapter.get() {
+            adapter.update_window_focus_state(false);
+        }
+        // Other windows should have the pointer to themselves
+        self.has_focus.set(false);
+        if let Err(e) = self.remove_pointer_barriers() {
+            error!("Failed to release the pointer: {}", e);
+        }
+        let ime_composing = match self.app.ime() {
+            Some(ime) if ime.focused_window() == Some(self.id) => {
+                let composing = ime.is_composing();
+                ime.focus_out(self.id);
+                composing
+            }
+            _ => false,
+        };
         self.with_handler(|h| {
             h.lost_focus();
             let active = self.active_text_field.get();
             if let Some(field) = active {
+                if ime_composing {
+                    // Keep the preedit text, as GTK does, but end the composition
+                    let mut ime = h.acquire_input_lock(field, true);
+                    ime.set_composition_range(None);
+                    h.release_input_lock(field);
+                }
                 if xkb_state.cancel_composing() {
                     let mut ime = h.acquire_input_lock(field, true);
                     let range = ime.composition_range();
@@ -1070,17 +1841,106 @@ impl Window {
             let protocol = client_message.data.as_data32()[0];
             if protocol == atoms.WM_DELETE_WINDOW {
                 self.with_handler(|h| h.request_close());
+            } else if protocol == atoms._NET_WM_SYNC_REQUEST {
+                // https://specifications.freedesktop.org/wm-spec/wm-spec-1.3.html#idm45805407643248
+                let data = client_message.data.as_data32();
+                self.pending_sync_value.set(Some(sync::Int64 {
+                    hi: data[3] as i32,
+                    lo: data[2],
+                }));
             }
         }
     }
 
     #[allow(clippy::trivially_copy_pass_by_ref)]
     pub fn handle_destroy_notify(&self, _destroy_notify: &xproto::DestroyNotifyEvent) {
+        if let Some(ime) = self.app.ime() {
+            ime.focus_out(self.id);
+        }
+        if let Some(counter) = self.sync_counter {
+            log_x11!(self.app.connection().sync_destroy_counter(counter));
+        }
+        if let Err(e) = self.remove_pointer_barriers() {
+            error!("Failed to release the pointer: {}", e);
+        }
         self.with_handler(|h| h.destroy());
     }
 
     pub fn handle_configure_notify(&self, event: &ConfigureNotifyEvent) -> Result<(), Error> {
-        self.size_changed(Size::new(event.width as f64, event.height as f64))
+        #[cfg(feature = "accesskit")]
+        self.update_accesskit_bounds(event);
+        self.pending_size
+            .set(Some(Size::new(event.width as f64, event.height as f64)));
+        Ok(())
+    }
+
+    /// Apply the latest configuration, and answer the window manager's sync request, if any.
+    ///
+    /// The window manager only shows the new size once the counter is updated, so when the size
+    /// changes we paint it first.
+    pub(crate) fn apply_pending_configure(&self) -> Result<(), Error> {
+        let Some(size) = self.pending_size.take() else {
+            return Ok(());
+        };
+        let old_size = self.size().size_px();
+        self.size_changed(size)?;
+        // The window may have moved or changed size, taking the confinement with it
+        if self.pointer_constraint.get() == PointerConstraint::Confined && self.has_focus.get() {
+            self.update_pointer_barriers()?;
+        }
+        // The sync request applies to the configuration which follows it
+        if let Some(value) = self.pending_sync_value.take() {
+            if size != old_size {
+                self.redraw_now()?;
+            }
+            if let (Some(counter), false) = (self.sync_counter, self.destroyed()) {
+                log_x11!(self.app.connection().sync_set_counter(counter, value));
+            }
+        }
+        Ok(())
+    }
+
+    /// Create the AccessKit adapter for this window.
+    ///
+    /// `accesskit_unix` only creates an adapter if the AT-SPI bus is running, so the handler's
+    /// tree is only requested in that case, in place of the placeholder it starts with.
+    #[cfg(feature = "accesskit")]
+    fn init_accesskit(&self) {
+        let idle_handle = IdleHandle {
+            queue: Arc::clone(&self.idle_queue),
+            pipe: self.idle_pipe,
+        };
+        let action_handler = Box::new(AccessKitActionHandler { idle_handle });
+        let Some(adapter) = AccessKitAdapter::new(placeholder_tree, false, action_handler) else {
+            return;
+        };
+        if let Some(tree) = self.with_handler(|h| h.accesskit_tree()) {
+            adapter.update(tree);
+        }
+        // The adapter has only just been created, so the cell must be empty
+        let _ = self.accesskit_adapter.set(adapter);
+    }
+
+    /// Tell AccessKit where the window is on screen, so that it can report the position of nodes.
+    #[cfg(feature = "accesskit")]
+    fn update_accesskit_bounds(&self, event: &ConfigureNotifyEvent) {
+        let Some(adapter) = self.accesskit_adapter.get() else {
+            return;
+        };
+        // The position in the event is relative to the parent, which is usually a frame window
+        // created by the window manager
+        match self.to_root_px(Point::ZERO) {
+            Ok(origin) => {
+                let bounds = accesskit::Rect::new(
+                    origin.x,
+                    origin.y,
+                    origin.x + event.width as f64,
+                    origin.y + event.height as f64,
+                );
+                adapter.set_root_window_bounds(bounds, bounds);
+            }
+            Err(e) => error!("Failed to get the window position for AccessKit: {e}"),
+        }
     }
 
     pub(crate) fn run_idle(&self) {
@@ -1131,6 +1991,18 @@ impl Window {
     }
 }
 
+/// The X11 modifier and button state of an xinput event.
+fn modifier_state(mods: ModifierInfo) -> KeyButMask {
+    let mods = mods.base | mods.locked | mods.latched;
+    // TODO: what are the high 16 bits for? Maybe virtual modifiers?
+    (mods as u16).into()
+}
+
+/// Converts xinput's fixed-point `FP1616` values.
+fn fp1616_to_f64(x: i32) -> f64 {
+    x as f64 / 65536.0
+}
+
 // Converts from, e.g., the `details` field of `xcb::xproto::ButtonPressEvent`
 fn pointer_button(button: u32) -> PointerButton {
     match button {
@@ -1149,21 +2021,40 @@ fn pointer_button(button: u32) -> PointerButton {
     }
 }
 
-// Extracts the pointer buttons from, e.g., the `state` field of
-// `xcb::xproto::ButtonPressEvent`
-fn pointer_buttons(mods: KeyButMask) -> PointerButtons {
+/// The meaning of a button of a pen, or of its `eraser` end.
+fn pen_button(button: PointerButton, eraser: bool) -> PointerButton {
+    match button {
+        PointerButton::Primary if eraser => PointerButton::PenEraser,
+        // The lower barrel button, which X11 reports as the middle button
+        PointerButton::Auxiliary => PointerButton::PenBarrel,
+        button => button,
+    }
+}
+
+fn pen_buttons(buttons: PointerButtons, eraser: bool) -> PointerButtons {
+    let mut ret = PointerButtons::new();
+    for button in [
+        PointerButton::Primary,
+        PointerButton::Secondary,
+        PointerButton::Auxiliary,
+        PointerButton::X1,
+        PointerButton::X2,
+    ] {
+        if buttons.contains(button) {
+            ret.insert(pen_button(button, eraser));
+        }
+    }
+    ret
+}
+
+// Extracts the pointer buttons from the `buttons` mask of xinput events, which (unlike the
+// core protocol's `state`) includes the X1/X2 buttons
+fn xi_pointer_buttons(mask: &[u32]) -> PointerButtons {
     let mut buttons = PointerButtons::new();
-    let button_masks = &[
-        (xproto::ButtonMask::M1, PointerButton::Primary),
-        (xproto::ButtonMask::M2, PointerButton::Auxiliary),
-        (xproto::ButtonMask::M3, PointerButton::Secondary),
-        // TODO: determine the X1/X2 state, using our own caching if necessary.
-        // BUTTON_MASK_4/5 do not work: they are for scroll events.
-    ];
-    for (mask, button) in button_masks {
-        // TODO: future x11rb will have more convenient bitmasks
-        if u16::from(mods) & u16::from(*mask) != 0 {
-            buttons.insert(*button);
+    // Buttons 4 through 7 are for scrolling
+    for code in [1, 2, 3, 8, 9] {
+        if mask.first().is_some_and(|word| word & (1 << code) != 0) {
+            buttons.insert(pointer_button(code));
         }
     }
     buttons
@@ -1209,19 +2100,7 @@ pub(crate) enum IdleKind {
 
 impl IdleHandle {
     fn wake(&self) {
-        loop {
-            match nix::unistd::write(self.pipe, &[0]) {
-                Err(nix::errno::Errno::EINTR) => {}
-                Err(nix::errno::Errno::EAGAIN) => {}
-                Err(e) => {
-                    error!("Failed to write to idle pipe: {}", e);
-                    break;
-                }
-                Ok(_) => {
-                    break;
-                }
-            }
-        }
+        wake_idle_pipe(self.pipe);
     }
 
     pub(crate) fn schedule_redraw(&self) {
@@ -1245,6 +2124,20 @@ impl IdleHandle {
     }
 }
 
+#[cfg(feature = "accesskit")]
+struct AccessKitActionHandler {
+    idle_handle: IdleHandle,
+}
+
+#[cfg(feature = "accesskit")]
+impl accesskit::ActionHandler for AccessKitActionHandler {
+    fn do_action(&mut self, request: accesskit::ActionRequest) {
+        self.idle_handle.add_idle_callback(move |handler| {
+            handler.accesskit_action(request);
+        });
+    }
+}
+
 #[derive(Clone, Default)]
 pub(crate) struct WindowHandle {
     id: u32,
@@ -1414,6 +2307,7 @@ impl WindowHandle {
                 window.active_text_field.set(None);
                 window.need_to_reset_compose.set(true);
             }
+            window.text_field_properties.borrow_mut().remove(&token);
         }
     }
 
@@ -1423,6 +2317,19 @@ impl WindowHandle {
         }
     }
 
+    pub fn set_text_field_properties(&self, token: TextFieldToken, properties: InputProperties) {
+        if let Some(window) = self.window.upgrade() {
+            window
+                .text_field_properties
+                .borrow_mut()
+                .insert(token, properties);
+            // If the field became secure, the input method is sent away on the next key press
+            if window.active_text_field.get() == Some(token) {
+                window.need_to_reset_compose.set(true);
+            }
+        }
+    }
+
     pub fn update_text_field(&self, token: TextFieldToken, _update: Event) {
         if let Some(window) = self.window.upgrade() {
             // This should be active rather than passive, but since the X11 backend is
@@ -1508,6 +2415,99 @@ impl WindowHandle {
         warn!("WindowHandle::show_context_menu is currently unimplemented for X11 backend.");
     }
 
+    pub fn start_drag(
+        &self,
+        _formats: &[ClipboardFormat],
+        _actions: DragActions,
+        _image: Option<DragImage>,
+    ) -> Option<DragToken> {
+        // TODO(x11/dnd): implement the XDND protocol
+        warn!("WindowHandle::start_drag is currently unimplemented for X11 backend.");
+        None
+    }
+
+    pub fn capture_pointer(&self, pointer_id: PointerId) {
+        if let Some(w) = self.window.upgrade() {
+            if let Err(e) = w.capture_pointer(pointer_id) {
+                error!("Failed to capture pointer: {}", e);
+            }
+        }
+    }
+
+    pub fn release_pointer_capture(&self, pointer_id: PointerId) {
+        if let Some(w) = self.window.upgrade() {
+            w.release_pointer_capture(pointer_id);
+        }
+    }
+
+    pub fn set_pointer_constraint(&self, constraint: PointerConstraint) {
+        if let Some(w) = self.window.upgrade() {
+            if let Err(e) = w.set_pointer_constraint(constraint) {
+                error!("Failed to constrain the pointer: {}", e);
+            }
+        }
+    }
+
+    pub fn set_cursor_visible(&self, visible: bool) {
+        if let Some(w) = self.window.upgrade() {
+            w.set_cursor_visible(visible);
+        }
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        if let Some(w) = self.window.upgrade() {
+            w.app.modifiers()
+        } else {
+            error!("Window {} has already been dropped", self.id);
+            Modifiers::empty()
+        }
+    }
+
+    pub fn keyboard_layouts(&self) -> KeyboardLayouts {
+        if let Some(w) = self.window.upgrade() {
+            w.app.keyboard_layouts()
+        } else {
+            error!("Window {} has already been dropped", self.id);
+            KeyboardLayouts::default()
+        }
+    }
+
+    pub fn pressed_keys(&self) -> Vec<Code> {
+        match self.window.upgrade().map(|w| w.pressed_keys()) {
+            Some(Ok(keys)) => keys,
+            Some(Err(e)) => {
+                error!("Failed to query the keyboard: {}", e);
+                Vec::new()
+            }
+            None => {
+                error!("Window {} has already been dropped", self.id);
+                Vec::new()
+            }
+        }
+    }
+
+    pub fn pointer_buttons(&self) -> PointerButtons {
+        self.pointer_state().0
+    }
+
+    pub fn pointer_position(&self) -> Option<Point> {
+        self.pointer_state().1
+    }
+
+    fn pointer_state(&self) -> (PointerButtons, Option<Point>) {
+        match self.window.upgrade().map(|w| w.pointer_state()) {
+            Some(Ok(state)) => state,
+            Some(Err(e)) => {
+                error!("Failed to query the pointer: {}", e);
+                (PointerButtons::new(), None)
+            }
+            None => {
+                error!("Window {} has already been dropped", self.id);
+                (PointerButtons::new(), None)
+            }
+        }
+    }
+
     pub fn get_idle_handle(&self) -> Option<IdleHandle> {
         self.window.upgrade().map(|w| IdleHandle {
             queue: Arc::clone(&w.idle_queue),
@@ -1527,9 +2527,13 @@ impl WindowHandle {
     #[cfg(feature = "accesskit")]
     pub fn update_accesskit_if_active(
         &self,
-        _update_factory: impl FnOnce() -> accesskit::TreeUpdate,
+        update_factory: impl FnOnce() -> accesskit::TreeUpdate,
     ) {
-        // AccessKit doesn't yet support this backend.
+        if let Some(w) = self.window.upgrade() {
+            if let Some(adapter) = w.accesskit_adapter.get() {
+                adapter.update(update_factory());
+            }
+        }
     }
 }
 
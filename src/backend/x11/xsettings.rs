@@ -0,0 +1,174 @@
+//! Reading desktop settings through the [XSETTINGS] protocol.
+//!
+//! [XSETTINGS]: https://specifications.freedesktop.org/xsettings-spec/xsettings-latest.html
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Error};
+use x11rb::protocol::xproto::{AtomEnum, ConnectionExt};
+use x11rb::xcb_ffi::XCBConnection;
+
+/// The value of a setting.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Setting {
+    Integer(i32),
+    String(String),
+    /// Red, green, blue and alpha.
+    Color([u16; 4]),
+}
+
+/// The settings published by the settings manager of a screen, such as a desktop environment's
+/// settings daemon.
+#[derive(Clone, Debug, Default)]
+pub struct XSettings {
+    settings: HashMap<String, Setting>,
+}
+
+impl XSettings {
+    /// Read the settings of screen `screen_num`.
+    ///
+    /// The settings are empty if no settings manager is running.
+    pub fn read(conn: &XCBConnection, screen_num: usize) -> Result<Self, Error> {
+        let selection = conn
+            .intern_atom(false, format!("_XSETTINGS_S{screen_num}").as_bytes())?
+            .reply()?
+            .atom;
+        let property = conn
+            .intern_atom(false, b"_XSETTINGS_SETTINGS")?
+            .reply()?
+            .atom;
+        let owner = conn.get_selection_owner(selection)?.reply()?.owner;
+        if owner == x11rb::NONE {
+            return Ok(XSettings::default());
+        }
+        let reply = conn
+            .get_property(false, owner, property, AtomEnum::ANY, 0, u32::MAX / 4)?
+            .reply()?;
+        XSettings::parse(&reply.value)
+    }
+
+    /// Parse the contents of the `_XSETTINGS_SETTINGS` property.
+    fn parse(data: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader {
+            data,
+            big_endian: *data.first().ok_or_else(|| anyhow!("empty xsettings"))? != 0,
+        };
+        // The byte order, padding and serial
+        reader.skip(8)?;
+        let count = reader.u32()?;
+        let mut settings = HashMap::new();
+        for _ in 0..count {
+            let kind = reader.u8()?;
+            reader.skip(1)?;
+            let name_len = reader.u16()? as usize;
+            let name = String::from_utf8_lossy(reader.padded(name_len)?).into_owned();
+            // The serial of the last change
+            reader.skip(4)?;
+            let value = match kind {
+                0 => Setting::Integer(reader.u32()? as i32),
+                1 => {
+                    let len = reader.u32()? as usize;
+                    Setting::String(String::from_utf8_lossy(reader.padded(len)?).into_owned())
+                }
+                2 => Setting::Color([reader.u16()?, reader.u16()?, reader.u16()?, reader.u16()?]),
+                _ => return Err(anyhow!("unknown xsettings type {kind}")),
+            };
+            settings.insert(name, value);
+        }
+        Ok(XSettings { settings })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Setting> {
+        self.settings.get(name)
+    }
+
+    pub fn integer(&self, name: &str) -> Option<i32> {
+        match self.get(name)? {
+            Setting::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.data.len() < len {
+            return Err(anyhow!("truncated xsettings"));
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<(), Error> {
+        self.bytes(len).map(drop)
+    }
+
+    /// Read `len` bytes, followed by padding to a multiple of 4.
+    fn padded(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let bytes = self.bytes(len)?;
+        self.skip((4 - len % 4) % 4)?;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        let bytes = self.bytes(2)?.try_into().unwrap();
+        Ok(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        let bytes = self.bytes(4)?.try_into().unwrap();
+        Ok(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_settings() {
+        let mut data = vec![0, 0, 0, 0];
+        data.extend(7u32.to_le_bytes());
+        data.extend(2u32.to_le_bytes());
+        // An integer
+        data.extend([0, 0]);
+        data.extend(19u16.to_le_bytes());
+        data.extend(b"Net/DoubleClickTime\0");
+        data.extend(3u32.to_le_bytes());
+        data.extend(400i32.to_le_bytes());
+        // A string
+        data.extend([1, 0]);
+        data.extend(13u16.to_le_bytes());
+        data.extend(b"Net/ThemeName\0\0\0");
+        data.extend(1u32.to_le_bytes());
+        data.extend(7u32.to_le_bytes());
+        data.extend(b"Adwaita\0");
+
+        let settings = XSettings::parse(&data).unwrap();
+        assert_eq!(settings.integer("Net/DoubleClickTime"), Some(400));
+        assert_eq!(
+            settings.get("Net/ThemeName"),
+            Some(&Setting::String("Adwaita".into()))
+        );
+        assert_eq!(settings.integer("Net/ThemeName"), None);
+        assert!(XSettings::parse(&data[..data.len() - 4]).is_err());
+    }
+}
@@ -225,10 +225,15 @@ impl ClickCounter {
 
     /// Return the click count for a click occurring now, at the provided position.
     pub fn count_for_click(&self, click_pos: Point) -> u8 {
-        let click_time = Instant::now();
+        self.count_for_click_at(click_pos, Instant::now())
+    }
+
+    /// Return the click count for a click which occurred at `click_time`, at the provided
+    /// position.
+    pub fn count_for_click_at(&self, click_pos: Point, click_time: Instant) -> u8 {
         let last_time = self.last_click.replace(click_time);
         let last_pos = self.last_pos.replace(click_pos);
-        let elapsed = click_time - last_time;
+        let elapsed = click_time.saturating_duration_since(last_time);
         let distance = last_pos.distance(click_pos);
         if elapsed > self.max_interval.get() || distance > self.max_distance.get() {
             self.click_count.set(0);
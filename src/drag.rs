@@ -0,0 +1,176 @@
+//! Types for starting drag and drop operations.
+
+use crate::common_util::Counter;
+use crate::kurbo::Point;
+
+/// A token that uniquely identifies a drag operation started with
+/// [`WindowHandle::start_drag`].
+///
+/// [`WindowHandle::start_drag`]: crate::WindowHandle::start_drag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Hash)]
+pub struct DragToken(u64);
+
+impl DragToken {
+    /// A token that does not correspond to any drag operation.
+    pub const INVALID: DragToken = DragToken(0);
+
+    /// Create a new token.
+    pub fn next() -> DragToken {
+        static COUNTER: Counter = Counter::new();
+        DragToken(COUNTER.next())
+    }
+
+    /// Create a new token from a raw value.
+    pub const fn from_raw(id: u64) -> DragToken {
+        DragToken(id)
+    }
+
+    /// Get the raw value for a token.
+    pub const fn into_raw(self) -> u64 {
+        self.0
+    }
+}
+
+/// An action which the target of a drag and drop operation can perform with the data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DragAction {
+    /// The data is copied to the target.
+    Copy,
+    /// The data is moved to the target; the source should remove its copy.
+    Move,
+    /// The target should create a reference to the data, if it understands it.
+    Link,
+}
+
+/// A set of [`DragAction`]s.
+#[derive(PartialEq, Eq, Clone, Copy, Default, Hash)]
+pub struct DragActions(u8);
+
+fn action_bit(action: DragAction) -> u8 {
+    match action {
+        DragAction::Copy => 0b1,
+        DragAction::Move => 0b10,
+        DragAction::Link => 0b100,
+    }
+}
+
+impl DragActions {
+    /// Create a new empty set.
+    #[inline]
+    pub fn new() -> DragActions {
+        DragActions(0)
+    }
+
+    /// Create a set containing every action.
+    #[inline]
+    pub fn all() -> DragActions {
+        DragActions(0b111)
+    }
+
+    /// Add the `action` to the set.
+    #[inline]
+    pub fn insert(&mut self, action: DragAction) {
+        self.0 |= action_bit(action);
+    }
+
+    /// Remove the `action` from the set.
+    #[inline]
+    pub fn remove(&mut self, action: DragAction) {
+        self.0 &= !action_bit(action);
+    }
+
+    /// Builder-style method for adding the `action` to the set.
+    #[inline]
+    pub fn with(mut self, action: DragAction) -> DragActions {
+        self.insert(action);
+        self
+    }
+
+    /// Returns `true` if the `action` is in the set.
+    #[inline]
+    pub fn contains(self, action: DragAction) -> bool {
+        (self.0 & action_bit(action)) != 0
+    }
+
+    /// Returns `true` if the set is empty.
+    #[inline]
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl From<DragAction> for DragActions {
+    fn from(action: DragAction) -> DragActions {
+        DragActions::new().with(action)
+    }
+}
+
+impl std::fmt::Debug for DragActions {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut tup = f.debug_tuple("DragActions");
+
+        if self.contains(DragAction::Copy) {
+            tup.field(&"Copy");
+        }
+        if self.contains(DragAction::Move) {
+            tup.field(&"Move");
+        }
+        if self.contains(DragAction::Link) {
+            tup.field(&"Link");
+        }
+
+        tup.finish()
+    }
+}
+
+/// An image which follows the pointer while a drag is in progress.
+#[derive(Debug, Clone)]
+#[allow(dead_code)] // Not yet used on all platforms.
+pub struct DragImage {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    /// Non-premultiplied RGBA8 pixels, in row-major order.
+    pub(crate) pixels: Vec<u8>,
+    pub(crate) hot: Point,
+}
+
+impl DragImage {
+    /// Create a new drag image from non-premultiplied RGBA8 pixel data, in row-major order.
+    ///
+    /// `hot` is the position of the pointer within the image, in pixels with `(0, 0)` at the
+    /// top left.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pixels` is not exactly `width * height * 4` bytes long.
+    pub fn new(
+        width: usize,
+        height: usize,
+        pixels: impl Into<Vec<u8>>,
+        hot: impl Into<Point>,
+    ) -> DragImage {
+        let pixels = pixels.into();
+        assert_eq!(
+            pixels.len(),
+            width * height * 4,
+            "drag image data doesn't match its dimensions"
+        );
+        DragImage {
+            width,
+            height,
+            pixels,
+            hot: hot.into(),
+        }
+    }
+}
+
+/// How a drag operation started with [`WindowHandle::start_drag`] ended.
+///
+/// [`WindowHandle::start_drag`]: crate::WindowHandle::start_drag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DragOutcome {
+    /// The data was dropped on a target, which performed the given action.
+    Dropped(DragAction),
+    /// The drag was cancelled, or the target refused the data.
+    Cancelled,
+}
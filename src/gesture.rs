@@ -0,0 +1,54 @@
+//! Types for touchpad gestures.
+
+use crate::kurbo::{Point, Vec2};
+use crate::pointer::Angle;
+use crate::Modifiers;
+
+/// Where a gesture is in its lifetime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GesturePhase {
+    /// The fingers were put down. The gesture's values are the identity, such as a scale of `1.0`.
+    Began,
+    /// The fingers moved.
+    Changed,
+    /// The fingers were lifted, and the gesture should be applied.
+    Ended,
+    /// The gesture was cancelled, for example because another finger was put down. Any
+    /// effects of the gesture should be undone.
+    Cancelled,
+}
+
+/// The kind of a gesture, and how it has changed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gesture {
+    /// Fingers moving towards or away from each other, and rotating around their center.
+    Pinch {
+        /// The distance between the fingers, relative to when the gesture began.
+        scale: f64,
+        /// The clockwise rotation since the previous event of this gesture.
+        rotation: Angle,
+        /// The movement of the fingers' center since the previous event of this gesture.
+        pan: Vec2,
+    },
+    /// Fingers moving in the same direction.
+    Swipe {
+        /// The movement of the fingers since the previous event of this gesture.
+        delta: Vec2,
+    },
+}
+
+/// A touchpad gesture, as recognized by the platform.
+///
+/// A gesture is made of one [`GesturePhase::Began`] event, any number of
+/// [`GesturePhase::Changed`] events, and an [`GesturePhase::Ended`] or
+/// [`GesturePhase::Cancelled`] event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GestureEvent {
+    pub gesture: Gesture,
+    pub phase: GesturePhase,
+    /// The number of fingers taking part in the gesture.
+    pub finger_count: u32,
+    /// The position of the pointer.
+    pub pos: Point,
+    pub modifiers: Modifiers,
+}
@@ -15,10 +15,14 @@
 //! Hotkeys and helpers for parsing keyboard shortcuts.
 
 use std::borrow::Borrow;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
 
 use tracing::warn;
 
-use crate::keyboard::{IntoKey, KbKey, KeyEvent, Modifiers};
+use crate::keyboard::{Code, IntoKey, KbKey, KeyEvent, Modifiers};
+use crate::Application;
 
 // TODO: fix docstring
 
@@ -53,10 +57,31 @@ use crate::keyboard::{IntoKey, KbKey, KeyEvent, Modifiers};
 /// assert!(hotkey.matches(KeyEvent::for_test(RawMods::None, KbKey::ArrowLeft)));
 /// assert!(!hotkey.matches(KeyEvent::for_test(RawMods::Ctrl, KbKey::ArrowLeft)));
 /// ```
+///
+/// Hotkeys can also be parsed from strings, such as those in a settings file, and are
+/// written back in the same form by their `Display` implementation:
+///
+/// ```
+/// use glazier::{HotKey, KbKey, RawMods};
+///
+/// let hotkey: HotKey = "Ctrl+Shift+Left".parse().unwrap();
+/// assert_eq!(hotkey, HotKey::new(RawMods::CtrlShift, KbKey::ArrowLeft));
+/// assert_eq!(hotkey.to_string(), "Ctrl+Shift+ArrowLeft");
+/// ```
+///
+/// With the `serde` feature, hotkeys are serialized as these strings.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "String", try_from = "String")
+)]
 pub struct HotKey {
     pub(crate) mods: RawMods,
     pub(crate) key: KbKey,
+    /// The physical key which this matches instead of `key`, if it was made with
+    /// [`HotKey::for_code`].
+    pub(crate) code: Option<Code>,
 }
 
 impl HotKey {
@@ -84,10 +109,103 @@ impl HotKey {
         HotKey {
             mods: mods.into().unwrap_or(RawMods::None),
             key: key.into_key(),
+            code: None,
         }
         .warn_if_needed()
     }
 
+    /// Create a hotkey which matches a physical key, whatever it produces in the active
+    /// keyboard layout.
+    ///
+    /// This suits shortcuts which are chosen for the position of their keys, such as moving
+    /// with W, A, S and D in a game, which are Z, Q, S and D on a French keyboard.
+    ///
+    /// # Examples
+    /// ```
+    /// use glazier::{Code, HotKey, KeyEvent, RawMods};
+    ///
+    /// let forward = HotKey::for_code(None, Code::KeyW);
+    ///
+    /// let mut event = KeyEvent::for_test(RawMods::None, "z");
+    /// event.code = Code::KeyW;
+    /// assert!(forward.matches(event));
+    /// ```
+    pub fn for_code(mods: impl Into<Option<RawMods>>, code: Code) -> Self {
+        HotKey {
+            mods: mods.into().unwrap_or(RawMods::None),
+            key: KbKey::Unidentified,
+            code: Some(code),
+        }
+    }
+
+    /// The key to show to the user for this hotkey.
+    ///
+    /// For a hotkey made with [`HotKey::for_code`], this is the key which the physical key
+    /// produces in the active layout, from [`Application::key_for_code`]. Where the layout
+    /// isn't known, this falls back to the key of a US keyboard.
+    pub fn display_key(&self) -> KbKey {
+        let Some(code) = self.code else {
+            return self.key.clone();
+        };
+        Application::try_global()
+            .and_then(|app| app.key_for_code(code, Modifiers::empty()))
+            .or_else(|| us_key_for_code(code))
+            .unwrap_or(KbKey::Unidentified)
+    }
+
+    /// The text to show to the user for this hotkey, such as next to a menu item.
+    ///
+    /// This follows the conventions of the platform, such as `⌘⇧K` on macOS and
+    /// `Ctrl+Shift+K` elsewhere. The key is the one of the active layout, from
+    /// [`HotKey::display_key`], and the names of keys are translated into the language of
+    /// [`Application::get_locale`] where we know them.
+    ///
+    /// Windows menus label their items with this. macOS menus show their own key equivalents,
+    /// and the other backends don't implement menus yet.
+    pub fn display_string(&self) -> String {
+        let locale = Application::try_global()
+            .map(|_| Application::get_locale())
+            .unwrap_or_default();
+        self.format(DisplayStyle::NATIVE, &locale)
+    }
+
+    fn format(&self, style: DisplayStyle, locale: &str) -> String {
+        let language = locale.split(['-', '_']).next().unwrap_or_default();
+        let mods: Modifiers = self.mods.into();
+        let key = self.display_key();
+        let mut s = String::new();
+        if style.symbols {
+            for (modifier, symbol) in [
+                (Modifiers::CONTROL, "⌃"),
+                (Modifiers::ALT, "⌥"),
+                (Modifiers::SHIFT, "⇧"),
+                (Modifiers::META, style.meta),
+            ] {
+                if mods.contains(modifier) {
+                    s.push_str(symbol);
+                }
+            }
+            match mac_key_symbol(&key) {
+                Some(symbol) => s.push_str(symbol),
+                None => push_key_name(&mut s, &key, language),
+            }
+        } else {
+            for (modifier, name) in [
+                (Modifiers::CONTROL, "Ctrl"),
+                (Modifiers::SHIFT, "Shift"),
+                (Modifiers::ALT, "Alt"),
+                (Modifiers::META, style.meta),
+            ] {
+                if mods.contains(modifier) {
+                    s.push_str(translate(language, name));
+                    s.push('+');
+                }
+            }
+            push_key_name(&mut s, &key, language);
+        }
+        s
+    }
+
     //TODO: figure out if we need to be normalizing case or something?
     fn warn_if_needed(self) -> Self {
         if let KbKey::Character(s) = &self.key {
@@ -110,7 +228,373 @@ impl HotKey {
         // Should be a const but const bit_or doesn't work here.
         let base_mods = Modifiers::SHIFT | Modifiers::CONTROL | Modifiers::ALT | Modifiers::META;
         let event = event.borrow();
-        self.mods == event.mods & base_mods && self.key == event.key
+        let key_matches = match self.code {
+            Some(code) => code == event.code,
+            None => self.key == event.key,
+        };
+        self.mods == event.mods & base_mods && key_matches
+    }
+}
+
+/// Writes hotkeys in the form which [`HotKey`]'s `FromStr` implementation reads, such as
+/// `Ctrl+Shift+K`.
+///
+/// Unlike [`HotKey::display_string`], this is the same on every platform, and so suits
+/// settings files rather than the user interface.
+impl fmt::Display for HotKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mods: Modifiers = self.mods.into();
+        for (modifier, name) in MODIFIER_NAMES {
+            if mods.contains(modifier) {
+                write!(f, "{name}+")?;
+            }
+        }
+        match (self.code, &self.key) {
+            (Some(code), _) => write!(f, "[{code}]"),
+            (None, KbKey::Character(c)) => match c.as_str() {
+                "+" => f.write_str("Plus"),
+                " " => f.write_str("Space"),
+                c => f.write_str(&with_case(c, str::to_uppercase)),
+            },
+            (None, key) => write!(f, "{key}"),
+        }
+    }
+}
+
+/// Reads hotkeys such as `Ctrl+Shift+K`, `Cmd+,` or `Alt+F4`.
+///
+/// The modifiers are `Ctrl`, `Shift`, `Alt` (or `Option`), `Meta` (or `Super` or `Win`),
+/// and `Cmd`, which is the Command key on macOS and Ctrl elsewhere like [`SysMods::Cmd`].
+/// They can be in any order and in any case.
+///
+/// The key is either a single character, which is matched in upper case if Shift is held
+/// and in lower case otherwise, or the name of a [`KbKey`] such as `ArrowLeft` or `F4`.
+/// The abbreviations `Esc`, `Del`, `Ins`, `PgUp`, `PgDn`, `Left`, `Right`, `Up` and
+/// `Down` are also understood, as are `Plus` and `Space`. A physical key is written as its
+/// [`Code`] in brackets, such as `[KeyW]`, for hotkeys made with [`HotKey::for_code`].
+impl FromStr for HotKey {
+    type Err = ParseHotKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (mod_names, key_name) = if s == "+" {
+            ("", "+")
+        } else if let Some(mod_names) = s.strip_suffix("++") {
+            (mod_names, "+")
+        } else {
+            s.rsplit_once('+').unwrap_or(("", s))
+        };
+
+        let mut mods = Modifiers::empty();
+        if !mod_names.is_empty() {
+            for name in mod_names.split('+').map(str::trim) {
+                if name.is_empty() {
+                    return Err(ParseHotKeyError::EmptyModifier);
+                }
+                let modifier = parse_modifier(name)
+                    .ok_or_else(|| ParseHotKeyError::UnknownModifier(name.to_string()))?;
+                if mods.contains(modifier) {
+                    return Err(ParseHotKeyError::DuplicateModifier(name.to_string()));
+                }
+                mods |= modifier;
+            }
+        }
+        let raw_mods = RawMods::from_modifiers(mods);
+
+        let key_name = key_name.trim();
+        if key_name.is_empty() {
+            return Err(ParseHotKeyError::MissingKey);
+        }
+        let unknown_key = || ParseHotKeyError::UnknownKey(key_name.to_string());
+        if let Some(code) = key_name
+            .strip_prefix('[')
+            .and_then(|name| name.strip_suffix(']'))
+        {
+            let code = code.parse().map_err(|_| unknown_key())?;
+            return Ok(HotKey::for_code(raw_mods, code));
+        }
+        let key = match parse_key(key_name).ok_or_else(unknown_key)? {
+            KbKey::Character(c) if mods.shift() => {
+                KbKey::Character(with_case(&c, str::to_uppercase))
+            }
+            KbKey::Character(c) => KbKey::Character(with_case(&c, str::to_lowercase)),
+            key => key,
+        };
+        Ok(HotKey {
+            mods: raw_mods,
+            key,
+            code: None,
+        })
+    }
+}
+
+impl From<HotKey> for String {
+    fn from(hotkey: HotKey) -> String {
+        hotkey.to_string()
+    }
+}
+
+impl TryFrom<String> for HotKey {
+    type Error = ParseHotKeyError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// The reasons a string may not be a [`HotKey`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseHotKeyError {
+    /// There is no key after the modifiers, as in `Ctrl+`, or the string is empty.
+    MissingKey,
+    /// Two `+` have nothing between them, as in `Ctrl++K`.
+    EmptyModifier,
+    /// A modifier isn't one we know, such as `Hyper` in `Hyper+K`.
+    UnknownModifier(String),
+    /// A modifier is given twice, such as `Control` in `Ctrl+Control+K`.
+    DuplicateModifier(String),
+    /// The key is neither a single character nor the name of a key.
+    UnknownKey(String),
+}
+
+impl fmt::Display for ParseHotKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            ParseHotKeyError::MissingKey => write!(f, "The hotkey has no key after its modifiers."),
+            ParseHotKeyError::EmptyModifier => {
+                write!(f, "The hotkey has an empty modifier between two `+`.")
+            }
+            ParseHotKeyError::UnknownModifier(name) => {
+                write!(f, "`{name}` is not a known modifier.")
+            }
+            ParseHotKeyError::DuplicateModifier(name) => {
+                write!(f, "The modifier `{name}` is given more than once.")
+            }
+            ParseHotKeyError::UnknownKey(name) => write!(f, "`{name}` is not a known key."),
+        }
+    }
+}
+
+impl std::error::Error for ParseHotKeyError {}
+
+/// The modifiers in the order we write them, with their names in the `Display` form.
+const MODIFIER_NAMES: [(Modifiers, &str); 4] = [
+    (Modifiers::CONTROL, "Ctrl"),
+    (Modifiers::SHIFT, "Shift"),
+    (Modifiers::ALT, "Alt"),
+    (Modifiers::META, "Meta"),
+];
+
+fn parse_modifier(name: &str) -> Option<Modifiers> {
+    let modifier = match name.to_lowercase().as_str() {
+        "ctrl" | "control" => Modifiers::CONTROL,
+        "shift" => Modifiers::SHIFT,
+        "alt" | "option" => Modifiers::ALT,
+        "meta" | "super" | "win" => Modifiers::META,
+        "cmd" | "command" => RawMods::from(SysMods::Cmd).into(),
+        _ => return None,
+    };
+    Some(modifier)
+}
+
+fn parse_key(name: &str) -> Option<KbKey> {
+    if name.chars().count() == 1 {
+        return Some(KbKey::Character(name.to_string()));
+    }
+    let key = match name.to_lowercase().as_str() {
+        "plus" => KbKey::Character("+".to_string()),
+        "space" => KbKey::Character(" ".to_string()),
+        "esc" => KbKey::Escape,
+        "del" => KbKey::Delete,
+        "ins" => KbKey::Insert,
+        "pgup" => KbKey::PageUp,
+        "pgdn" => KbKey::PageDown,
+        "left" => KbKey::ArrowLeft,
+        "right" => KbKey::ArrowRight,
+        "up" => KbKey::ArrowUp,
+        "down" => KbKey::ArrowDown,
+        "return" => KbKey::Enter,
+        // Accept `f4` as well as `F4`
+        _ => {
+            let mut chars = name.chars();
+            let capitalized: String = chars.next()?.to_uppercase().chain(chars).collect();
+            match name.parse().or_else(|_| capitalized.parse()).ok()? {
+                KbKey::Unidentified => return None,
+                key => key,
+            }
+        }
+    };
+    Some(key)
+}
+
+/// A way of showing hotkeys to the user.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct DisplayStyle {
+    /// Whether to use symbols without separators, as on macOS, rather than names joined
+    /// with `+`.
+    symbols: bool,
+    /// The symbol or name of the meta key.
+    meta: &'static str,
+}
+
+impl DisplayStyle {
+    #[cfg(target_os = "macos")]
+    const NATIVE: DisplayStyle = DisplayStyle {
+        symbols: true,
+        meta: "⌘",
+    };
+    #[cfg(target_os = "windows")]
+    const NATIVE: DisplayStyle = DisplayStyle {
+        symbols: false,
+        meta: "Windows",
+    };
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    const NATIVE: DisplayStyle = DisplayStyle {
+        symbols: false,
+        meta: "Super",
+    };
+}
+
+/// The symbol which macOS menus show for `key`, if it has one.
+fn mac_key_symbol(key: &KbKey) -> Option<&'static str> {
+    let symbol = match key {
+        KbKey::Enter => "↩",
+        KbKey::Tab => "⇥",
+        KbKey::Backspace => "⌫",
+        KbKey::Delete => "⌦",
+        KbKey::Escape => "⎋",
+        KbKey::ArrowLeft => "←",
+        KbKey::ArrowRight => "→",
+        KbKey::ArrowUp => "↑",
+        KbKey::ArrowDown => "↓",
+        KbKey::PageUp => "⇞",
+        KbKey::PageDown => "⇟",
+        KbKey::Home => "↖",
+        KbKey::End => "↘",
+        _ => return None,
+    };
+    Some(symbol)
+}
+
+/// `c` converted by `convert`, unless that changes the number of characters, like `ß` to `SS`,
+/// which wouldn't be the same key any more.
+fn with_case(c: &str, convert: fn(&str) -> String) -> String {
+    let converted = convert(c);
+    if converted.chars().count() == c.chars().count() {
+        converted
+    } else {
+        c.to_string()
+    }
+}
+
+fn push_key_name(s: &mut String, key: &KbKey, language: &str) {
+    let name = match key {
+        KbKey::Character(c) => match c.as_str() {
+            "+" => "Plus",
+            "-" => "Minus",
+            " " => "Space",
+            _ => {
+                s.extend(c.chars().flat_map(char::to_uppercase));
+                return;
+            }
+        },
+        KbKey::Escape => "Esc",
+        KbKey::Delete => "Del",
+        KbKey::Insert => "Ins",
+        KbKey::PageUp => "PgUp",
+        KbKey::PageDown => "PgDn",
+        // These names match LibreOffice.
+        KbKey::ArrowLeft => "Left",
+        KbKey::ArrowRight => "Right",
+        KbKey::ArrowUp => "Up",
+        KbKey::ArrowDown => "Down",
+        KbKey::Home => "Home",
+        KbKey::End => "End",
+        KbKey::Enter => "Enter",
+        KbKey::Backspace => "Backspace",
+        _ => {
+            s.push_str(&key.to_string());
+            return;
+        }
+    };
+    s.push_str(translate(language, name));
+}
+
+/// The names of keys in the languages which we know them in, as printed on keyboards.
+const KEY_NAMES: &[(&str, &[(&str, &str)])] = &[
+    (
+        "de",
+        &[
+            ("Ctrl", "Strg"),
+            ("Shift", "Umschalt"),
+            ("Space", "Leertaste"),
+            ("Del", "Entf"),
+            ("Ins", "Einfg"),
+            ("PgUp", "Bild auf"),
+            ("PgDn", "Bild ab"),
+            ("Home", "Pos1"),
+            ("End", "Ende"),
+            ("Enter", "Eingabe"),
+            ("Backspace", "Rücktaste"),
+        ],
+    ),
+    (
+        "es",
+        &[
+            ("Shift", "Mayús"),
+            ("Space", "Espacio"),
+            ("Del", "Supr"),
+            ("PgUp", "RePág"),
+            ("PgDn", "AvPág"),
+            ("Home", "Inicio"),
+            ("End", "Fin"),
+            ("Enter", "Intro"),
+            ("Backspace", "Retroceso"),
+        ],
+    ),
+    (
+        "fr",
+        &[
+            ("Shift", "Maj"),
+            ("Space", "Espace"),
+            ("Esc", "Échap"),
+            ("Del", "Suppr"),
+            ("Ins", "Inser"),
+            ("PgUp", "Pg préc"),
+            ("PgDn", "Pg suiv"),
+            ("Home", "Début"),
+            ("End", "Fin"),
+            ("Enter", "Entrée"),
+            ("Backspace", "Retour arrière"),
+        ],
+    ),
+];
+
+/// The name of a key in `language`, or the English `name` if we don't know it.
+fn translate(language: &str, name: &'static str) -> &'static str {
+    KEY_NAMES
+        .iter()
+        .find(|(lang, _)| *lang == language)
+        .and_then(|(_, names)| names.iter().find(|(english, _)| *english == name))
+        .map_or(name, |(_, translated)| translated)
+}
+
+/// The key which `code` has on a US keyboard, for where the layout isn't known.
+///
+/// This only knows the keys which are named the same as their `Code`, besides letters and
+/// digits.
+fn us_key_for_code(code: Code) -> Option<KbKey> {
+    if code == Code::Space {
+        return Some(KbKey::Character(" ".to_string()));
+    }
+    let name = code.to_string();
+    match name
+        .strip_prefix("Key")
+        .or_else(|| name.strip_prefix("Digit"))
+    {
+        Some(label) if label.len() == 1 => Some(KbKey::Character(label.to_lowercase())),
+        _ => name.parse().ok(),
     }
 }
 
@@ -119,6 +603,7 @@ impl HotKey {
 /// This does one thing: it allows specifying hotkeys that use the Command key
 /// on macOS, but use the Ctrl key on other platforms.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SysMods {
     None,
     Shift,
@@ -137,6 +622,7 @@ pub enum SysMods {
 ///
 /// This is intended to be clearer than `Modifiers`, when describing hotkeys.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RawMods {
     None,
     Alt,
@@ -156,6 +642,30 @@ pub enum RawMods {
     AltCtrlMetaShift,
 }
 
+impl RawMods {
+    /// The combination of the shift, ctrl, alt and meta keys in `mods`.
+    fn from_modifiers(mods: Modifiers) -> RawMods {
+        match (mods.alt(), mods.ctrl(), mods.meta(), mods.shift()) {
+            (false, false, false, false) => RawMods::None,
+            (true, false, false, false) => RawMods::Alt,
+            (false, true, false, false) => RawMods::Ctrl,
+            (false, false, true, false) => RawMods::Meta,
+            (false, false, false, true) => RawMods::Shift,
+            (true, true, false, false) => RawMods::AltCtrl,
+            (true, false, true, false) => RawMods::AltMeta,
+            (true, false, false, true) => RawMods::AltShift,
+            (false, true, true, false) => RawMods::CtrlMeta,
+            (false, true, false, true) => RawMods::CtrlShift,
+            (false, false, true, true) => RawMods::MetaShift,
+            (true, true, true, false) => RawMods::AltCtrlMeta,
+            (true, false, true, true) => RawMods::AltMetaShift,
+            (true, true, false, true) => RawMods::AltCtrlShift,
+            (false, true, true, true) => RawMods::CtrlMetaShift,
+            (true, true, true, true) => RawMods::AltCtrlMetaShift,
+        }
+    }
+}
+
 impl std::cmp::PartialEq<Modifiers> for RawMods {
     fn eq(&self, other: &Modifiers) -> bool {
         let mods: Modifiers = (*self).into();
@@ -241,3 +751,90 @@ impl From<SysMods> for RawMods {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_and_write() {
+        let hotkey: HotKey = "ctrl + shift + k".parse().unwrap();
+        assert_eq!(hotkey, HotKey::new(RawMods::CtrlShift, "K"));
+        assert_eq!(hotkey.to_string(), "Ctrl+Shift+K");
+
+        let zoom_in: HotKey = "Ctrl++".parse().unwrap();
+        assert_eq!(zoom_in, HotKey::new(RawMods::Ctrl, "+"));
+        assert_eq!(zoom_in.to_string(), "Ctrl+Plus");
+        assert_eq!("Ctrl+Plus".parse::<HotKey>().unwrap(), zoom_in);
+
+        let settings: HotKey = "Cmd+,".parse().unwrap();
+        assert_eq!(settings, HotKey::new(SysMods::Cmd, ","));
+
+        let forward: HotKey = "Shift+[KeyW]".parse().unwrap();
+        assert_eq!(forward, HotKey::for_code(RawMods::Shift, Code::KeyW));
+        assert_eq!(forward.to_string(), "Shift+[KeyW]");
+
+        assert_eq!("Alt+f4".parse::<HotKey>().unwrap().key, KbKey::F4);
+        assert_eq!("PgDn".parse::<HotKey>().unwrap().key, KbKey::PageDown);
+    }
+
+    #[test]
+    fn case_round_trip() {
+        for c in ["ß", "ŉ", "ǰ", "ΐ", "İ", "é"] {
+            let upper = with_case(c, str::to_uppercase);
+            for hotkey in [
+                HotKey::new(None, c),
+                HotKey::new(RawMods::Ctrl, c),
+                HotKey::new(RawMods::Shift, upper.as_str()),
+            ] {
+                assert_eq!(hotkey.to_string().parse::<HotKey>().unwrap(), hotkey);
+            }
+        }
+        assert_eq!(HotKey::new(RawMods::Ctrl, "ß").to_string(), "Ctrl+ß");
+        assert_eq!(HotKey::new(RawMods::Ctrl, "é").to_string(), "Ctrl+É");
+    }
+
+    #[test]
+    fn parse_errors() {
+        let parse = |s: &str| s.parse::<HotKey>().unwrap_err();
+        assert_eq!(parse(""), ParseHotKeyError::MissingKey);
+        assert_eq!(parse("Ctrl+"), ParseHotKeyError::MissingKey);
+        assert_eq!(parse("Ctrl++K"), ParseHotKeyError::EmptyModifier);
+        assert_eq!(
+            parse("Hyper+K"),
+            ParseHotKeyError::UnknownModifier("Hyper".to_string())
+        );
+        assert_eq!(
+            parse("Ctrl+Control+K"),
+            ParseHotKeyError::DuplicateModifier("Control".to_string())
+        );
+        assert_eq!(
+            parse("Ctrl+Kay"),
+            ParseHotKeyError::UnknownKey("Kay".to_string())
+        );
+        assert_eq!(
+            parse("[Nope]"),
+            ParseHotKeyError::UnknownKey("[Nope]".to_string())
+        );
+    }
+
+    #[test]
+    fn display_styles() {
+        let mac = DisplayStyle {
+            symbols: true,
+            meta: "⌘",
+        };
+        let windows = DisplayStyle {
+            symbols: false,
+            meta: "Windows",
+        };
+        let hotkey = HotKey::new(RawMods::CtrlShift, "K");
+        assert_eq!(hotkey.format(mac, "en-US"), "⌃⇧K");
+        assert_eq!(hotkey.format(windows, "en-US"), "Ctrl+Shift+K");
+        assert_eq!(hotkey.format(windows, "de_DE"), "Strg+Umschalt+K");
+
+        let delete = HotKey::new(RawMods::MetaShift, KbKey::Delete);
+        assert_eq!(delete.format(mac, "en-US"), "⇧⌘⌦");
+        assert_eq!(delete.format(windows, "fr-FR"), "Maj+Windows+Suppr");
+    }
+}
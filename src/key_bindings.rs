@@ -0,0 +1,371 @@
+//! The keyboard shortcuts of text fields.
+
+use crate::hotkey::{HotKey, RawMods};
+use crate::keyboard::{IntoKey, KbKey, KeyEvent};
+use crate::text::{Action, Direction, Movement, VerticalMovement};
+
+/// A set of conventions for the keyboard shortcuts of text fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyBindingStyle {
+    /// The shortcuts of Windows' text controls.
+    Windows,
+    /// The shortcuts of GTK, which GNOME uses.
+    Gnome,
+    /// The shortcuts of Qt, which KDE uses. Unlike the others, these don't move between
+    /// paragraphs with Ctrl+Up and Ctrl+Down, and only redo with Ctrl+Shift+Z.
+    Kde,
+    /// GNOME's shortcuts, with the Emacs-like shortcuts of GTK's "Emacs" key theme replacing
+    /// some of them, such as Ctrl+A moving to the start of the line rather than selecting
+    /// everything. Ctrl+K and Ctrl+Y kill and yank, rather than deleting and pasting as GTK's
+    /// do.
+    Emacs,
+}
+
+impl KeyBindingStyle {
+    /// The conventions of the current platform.
+    ///
+    /// On Linux and the BSDs, this is [`Kde`] when running in KDE, and [`Gnome`] otherwise.
+    ///
+    /// [`Kde`]: KeyBindingStyle::Kde
+    /// [`Gnome`]: KeyBindingStyle::Gnome
+    pub fn platform_default() -> Self {
+        #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "openbsd"))]
+        {
+            let desktop = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+            if desktop.split(':').any(|it| it.eq_ignore_ascii_case("KDE")) {
+                KeyBindingStyle::Kde
+            } else {
+                KeyBindingStyle::Gnome
+            }
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "freebsd", target_os = "openbsd")))]
+        KeyBindingStyle::Windows
+    }
+}
+
+/// A table from keyboard shortcuts to the [`Action`]s they perform in text fields.
+///
+/// Glazier uses the table of the [`Application`] to turn key presses into actions wherever it
+/// implements text input itself, which is on Windows, X11, Wayland and the web. Key presses
+/// only reach the table if [`WinHandler::key_down`] doesn't handle them. On macOS, the system's
+/// own key bindings apply instead.
+///
+/// # Examples
+///
+/// ```no_run
+/// use glazier::text::{Action, Movement, VerticalMovement};
+/// use glazier::{Application, HotKey, KeyBindingStyle, KeyBindings, RawMods};
+///
+/// let app = Application::new().unwrap();
+/// let mut bindings = KeyBindings::new(KeyBindingStyle::Emacs);
+/// bindings.insert(
+///     HotKey::new(RawMods::AltShift, "<"),
+///     Action::Move(Movement::Vertical(VerticalMovement::DocumentStart)),
+/// );
+/// bindings.remove(&HotKey::new(RawMods::Ctrl, "k"));
+/// app.set_key_bindings(bindings);
+/// ```
+///
+/// [`Application`]: crate::Application
+/// [`WinHandler::key_down`]: crate::WinHandler::key_down
+#[derive(Debug, Clone)]
+pub struct KeyBindings {
+    bindings: Vec<(HotKey, Action)>,
+}
+
+impl KeyBindings {
+    /// A table without any shortcuts.
+    pub fn empty() -> Self {
+        KeyBindings {
+            bindings: Vec::new(),
+        }
+    }
+
+    /// The shortcuts of `style`.
+    pub fn new(style: KeyBindingStyle) -> Self {
+        let mut bindings = KeyBindings::empty();
+        bindings.add_common();
+        match style {
+            KeyBindingStyle::Windows | KeyBindingStyle::Gnome => {
+                bindings.add_paragraph_movement();
+                bindings.insert(HotKey::new(RawMods::Ctrl, "y"), Action::Redo);
+            }
+            KeyBindingStyle::Kde => {}
+            KeyBindingStyle::Emacs => {
+                bindings.add_paragraph_movement();
+                bindings.add_emacs();
+            }
+        }
+        bindings
+    }
+
+    /// Bind `hotkey` to `action`, replacing its previous action if it had one.
+    pub fn insert(&mut self, hotkey: HotKey, action: Action) {
+        match self.bindings.iter_mut().find(|(it, _)| *it == hotkey) {
+            Some(binding) => binding.1 = action,
+            None => self.bindings.push((hotkey, action)),
+        }
+    }
+
+    /// Unbind `hotkey`, returning the action it had.
+    pub fn remove(&mut self, hotkey: &HotKey) -> Option<Action> {
+        let idx = self.bindings.iter().position(|(it, _)| it == hotkey)?;
+        Some(self.bindings.remove(idx).1)
+    }
+
+    /// The action which `hotkey` is bound to.
+    pub fn get(&self, hotkey: &HotKey) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(it, _)| it == hotkey)
+            .map(|(_, action)| *action)
+    }
+
+    /// The action which the key press `event` performs.
+    pub fn action(&self, event: &KeyEvent) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(hotkey, _)| hotkey.matches(event))
+            .map(|(_, action)| *action)
+    }
+
+    /// All of the shortcuts, and their actions.
+    pub fn iter(&self) -> impl Iterator<Item = (&HotKey, &Action)> {
+        self.bindings
+            .iter()
+            .map(|(hotkey, action)| (hotkey, action))
+    }
+
+    /// Bind `key` to moving, and Shift+`key` to selecting, with `mods` held in both cases.
+    fn insert_movement(&mut self, mods: RawMods, key: impl IntoKey, movement: Movement) {
+        let key = key.into_key();
+        let selecting = match mods {
+            RawMods::None => RawMods::Shift,
+            RawMods::Ctrl => RawMods::CtrlShift,
+            RawMods::Alt => RawMods::AltShift,
+            RawMods::Meta => RawMods::MetaShift,
+            RawMods::AltCtrl => RawMods::AltCtrlShift,
+            _ => unreachable!("no key bindings use {mods:?}"),
+        };
+        // Shift turns the characters of letter keys uppercase
+        let selecting_key = match &key {
+            KbKey::Character(c) => KbKey::Character(c.to_uppercase()),
+            key => key.clone(),
+        };
+        self.insert(HotKey::new(mods, key), Action::Move(movement));
+        self.insert(
+            HotKey::new(selecting, selecting_key),
+            Action::MoveSelecting(movement),
+        );
+    }
+
+    /// The shortcuts which Windows, GTK and Qt share.
+    fn add_common(&mut self) {
+        use keyboard_types::Key::{
+            ArrowDown, ArrowLeft, ArrowRight, ArrowUp, Backspace, Delete, End, Enter, Home, Insert,
+            PageDown, PageUp, Tab,
+        };
+        use RawMods::{Ctrl, CtrlShift, Shift};
+
+        let none = RawMods::None;
+        self.insert_movement(none, ArrowLeft, Movement::Grapheme(Direction::Left));
+        self.insert_movement(none, ArrowRight, Movement::Grapheme(Direction::Right));
+        self.insert_movement(Ctrl, ArrowLeft, Movement::Word(Direction::Left));
+        self.insert_movement(Ctrl, ArrowRight, Movement::Word(Direction::Right));
+        self.insert_movement(none, ArrowUp, Movement::Vertical(VerticalMovement::LineUp));
+        self.insert_movement(
+            none,
+            ArrowDown,
+            Movement::Vertical(VerticalMovement::LineDown),
+        );
+        self.insert_movement(none, Home, Movement::Line(Direction::Upstream));
+        self.insert_movement(none, End, Movement::Line(Direction::Downstream));
+        self.insert_movement(
+            Ctrl,
+            Home,
+            Movement::Vertical(VerticalMovement::DocumentStart),
+        );
+        self.insert_movement(Ctrl, End, Movement::Vertical(VerticalMovement::DocumentEnd));
+        self.insert_movement(none, PageUp, Movement::Vertical(VerticalMovement::PageUp));
+        self.insert_movement(
+            none,
+            PageDown,
+            Movement::Vertical(VerticalMovement::PageDown),
+        );
+
+        let delete_back = Action::Delete(Movement::Grapheme(Direction::Upstream));
+        self.insert(HotKey::new(none, Backspace), delete_back);
+        self.insert(HotKey::new(Shift, Backspace), delete_back);
+        self.insert(
+            HotKey::new(Ctrl, Backspace),
+            Action::Delete(Movement::Word(Direction::Upstream)),
+        );
+        self.insert(
+            HotKey::new(none, Delete),
+            Action::Delete(Movement::Grapheme(Direction::Downstream)),
+        );
+        self.insert(
+            HotKey::new(Ctrl, Delete),
+            Action::Delete(Movement::Word(Direction::Downstream)),
+        );
+
+        let new_line = Action::InsertNewLine {
+            ignore_hotkey: false,
+            newline_type: '\n',
+        };
+        self.insert(HotKey::new(none, Enter), new_line);
+        self.insert(HotKey::new(Shift, Enter), new_line);
+        self.insert(
+            HotKey::new(none, Tab),
+            Action::InsertTab {
+                ignore_hotkey: false,
+            },
+        );
+        self.insert(HotKey::new(Shift, Tab), Action::InsertBacktab);
+
+        self.insert(HotKey::new(Ctrl, "a"), Action::SelectAll);
+
+        self.insert(HotKey::new(Ctrl, "z"), Action::Undo);
+        self.insert(HotKey::new(CtrlShift, "Z"), Action::Redo);
+        self.insert(HotKey::new(Ctrl, "x"), Action::Cut);
+        self.insert(HotKey::new(Shift, Delete), Action::Cut);
+        self.insert(HotKey::new(Ctrl, "c"), Action::Copy);
+        self.insert(HotKey::new(Ctrl, Insert), Action::Copy);
+        self.insert(HotKey::new(Ctrl, "v"), Action::Paste);
+        self.insert(HotKey::new(Shift, Insert), Action::Paste);
+        self.insert(HotKey::new(CtrlShift, "V"), Action::PasteAsPlainText);
+    }
+
+    /// Ctrl+Up and Ctrl+Down moving to the start and end of paragraphs.
+    fn add_paragraph_movement(&mut self) {
+        self.insert_movement(RawMods::Ctrl, KbKey::ArrowUp, Movement::ParagraphStart);
+        self.insert_movement(RawMods::Ctrl, KbKey::ArrowDown, Movement::ParagraphEnd);
+    }
+
+    /// The shortcuts of GTK's Emacs key theme.
+    fn add_emacs(&mut self) {
+        use RawMods::{Alt, Ctrl};
+
+        self.insert_movement(Ctrl, "a", Movement::Line(Direction::Upstream));
+        self.insert_movement(Ctrl, "e", Movement::Line(Direction::Downstream));
+        self.insert_movement(Ctrl, "b", Movement::Grapheme(Direction::Upstream));
+        self.insert_movement(Ctrl, "f", Movement::Grapheme(Direction::Downstream));
+        self.insert_movement(Ctrl, "p", Movement::Vertical(VerticalMovement::LineUp));
+        self.insert_movement(Ctrl, "n", Movement::Vertical(VerticalMovement::LineDown));
+        self.insert_movement(Alt, "b", Movement::Word(Direction::Upstream));
+        self.insert_movement(Alt, "f", Movement::Word(Direction::Downstream));
+
+        self.insert(
+            HotKey::new(Ctrl, "h"),
+            Action::Delete(Movement::Grapheme(Direction::Upstream)),
+        );
+        self.insert(
+            HotKey::new(Ctrl, "d"),
+            Action::Delete(Movement::Grapheme(Direction::Downstream)),
+        );
+        self.insert(
+            HotKey::new(Alt, KbKey::Backspace),
+            Action::Kill(Movement::Word(Direction::Upstream)),
+        );
+        self.insert(
+            HotKey::new(Alt, "d"),
+            Action::Kill(Movement::Word(Direction::Downstream)),
+        );
+        self.insert(HotKey::new(Ctrl, "k"), Action::Kill(Movement::ParagraphEnd));
+        self.insert(
+            HotKey::new(Ctrl, "u"),
+            Action::Kill(Movement::ParagraphStart),
+        );
+        self.insert(HotKey::new(Ctrl, "y"), Action::Yank);
+        self.insert(HotKey::new(Ctrl, " "), Action::SetMark);
+
+        self.insert(HotKey::new(Ctrl, "w"), Action::Cut);
+        self.insert(HotKey::new(Alt, "w"), Action::Copy);
+    }
+}
+
+impl Default for KeyBindings {
+    /// The shortcuts of [`KeyBindingStyle::platform_default`].
+    fn default() -> Self {
+        KeyBindings::new(KeyBindingStyle::platform_default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn styles() {
+        let ctrl_up = KeyEvent::for_test(RawMods::Ctrl, KbKey::ArrowUp);
+        let gnome = KeyBindings::new(KeyBindingStyle::Gnome);
+        assert_eq!(
+            gnome.action(&ctrl_up),
+            Some(Action::Move(Movement::ParagraphStart))
+        );
+        assert_eq!(
+            KeyBindings::new(KeyBindingStyle::Kde).action(&ctrl_up),
+            None
+        );
+
+        let ctrl_a = KeyEvent::for_test(RawMods::Ctrl, "a");
+        assert_eq!(gnome.action(&ctrl_a), Some(Action::SelectAll));
+        let emacs = KeyBindings::new(KeyBindingStyle::Emacs);
+        assert_eq!(
+            emacs.action(&ctrl_a),
+            Some(Action::Move(Movement::Line(Direction::Upstream)))
+        );
+        let shift_ctrl_f = KeyEvent::for_test(RawMods::CtrlShift, "F");
+        assert_eq!(
+            emacs.action(&shift_ctrl_f),
+            Some(Action::MoveSelecting(Movement::Grapheme(
+                Direction::Downstream
+            )))
+        );
+    }
+
+    #[test]
+    fn clipboard() {
+        let windows = KeyBindings::new(KeyBindingStyle::Windows);
+        let kde = KeyBindings::new(KeyBindingStyle::Kde);
+        let emacs = KeyBindings::new(KeyBindingStyle::Emacs);
+        let ctrl_y = KeyEvent::for_test(RawMods::Ctrl, "y");
+        assert_eq!(windows.action(&ctrl_y), Some(Action::Redo));
+        assert_eq!(kde.action(&ctrl_y), None);
+        assert_eq!(emacs.action(&ctrl_y), Some(Action::Yank));
+        let ctrl_shift_z = KeyEvent::for_test(RawMods::CtrlShift, "Z");
+        assert_eq!(kde.action(&ctrl_shift_z), Some(Action::Redo));
+        let shift_delete = KeyEvent::for_test(RawMods::Shift, KbKey::Delete);
+        assert_eq!(windows.action(&shift_delete), Some(Action::Cut));
+    }
+
+    #[test]
+    fn overrides() {
+        let mut bindings = KeyBindings::new(KeyBindingStyle::Windows);
+        let tab = HotKey::new(None, KbKey::Tab);
+        assert_eq!(
+            bindings.remove(&tab),
+            Some(Action::InsertTab {
+                ignore_hotkey: false
+            })
+        );
+        assert_eq!(
+            bindings.action(&KeyEvent::for_test(RawMods::None, KbKey::Tab)),
+            None
+        );
+
+        let ctrl_left = HotKey::new(RawMods::Ctrl, KbKey::ArrowLeft);
+        bindings.insert(
+            ctrl_left.clone(),
+            Action::Move(Movement::Line(Direction::Left)),
+        );
+        assert_eq!(
+            bindings.get(&ctrl_left),
+            Some(Action::Move(Movement::Line(Direction::Left)))
+        );
+        assert_eq!(
+            bindings.iter().filter(|(it, _)| **it == ctrl_left).count(),
+            1
+        );
+    }
+}
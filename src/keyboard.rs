@@ -14,6 +14,8 @@
 
 //! Keyboard types.
 
+use instant::Instant;
+
 pub use keyboard_types::{Code, KeyState, Location, Modifiers};
 
 /// The meaning (mapped value) of a keypress.
@@ -43,6 +45,12 @@ pub struct KeyEvent {
     /// Events with this flag should be ignored in a text editor
     /// and instead composition events should be used.
     pub is_composing: bool,
+    /// When the key was pressed or released, if the platform reports it.
+    ///
+    /// This is on the same clock as [`PointerEvent::time`]; see there for details.
+    ///
+    /// [`PointerEvent::time`]: crate::PointerEvent::time
+    pub time: Option<Instant>,
 }
 
 /// A convenience trait for creating Key objects.
@@ -71,6 +79,7 @@ impl KeyEvent {
             mods,
             is_composing: false,
             repeat: false,
+            time: None,
         }
     }
 }
@@ -86,3 +95,22 @@ impl IntoKey for &str {
         KbKey::Character(self.into())
     }
 }
+
+/// The keyboard layouts which the user has configured, and which of them is active.
+///
+/// On X11 and Wayland, these are the layouts (or groups) of the xkb keymap.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct KeyboardLayouts {
+    /// The names of the layouts, such as "English (US)", in the order the user
+    /// switches between them. A name is empty if the platform doesn't know it.
+    pub names: Vec<String>,
+    /// The index in `names` of the layout which is active.
+    pub active: usize,
+}
+
+impl KeyboardLayouts {
+    /// The name of the active layout, if any layouts are known.
+    pub fn active_name(&self) -> Option<&str> {
+        self.names.get(self.active).map(String::as_str)
+    }
+}
@@ -0,0 +1,311 @@
+//! A store of killed text, for Emacs-style editing.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::rc::Rc;
+
+use crate::text::{Action, InputHandler, Movement, Selection};
+
+/// How many kills a [`KillRing`] remembers by default.
+const DEFAULT_CAPACITY: usize = 60;
+
+thread_local! {
+    static SHARED: Rc<RefCell<KillRing>> = Rc::new(RefCell::new(KillRing::default()));
+}
+
+/// The text deleted by [`Action::Kill`] and [`Action::DeleteToMark`], which [`Action::Yank`]
+/// inserts again.
+///
+/// The kill ring is separate from the clipboard. Like the kill rings of Cocoa and Emacs, an
+/// application usually shares one between all of its text fields, so that text killed in one
+/// field can be yanked into another. Consecutive kills from the same place are joined, so that
+/// pressing Ctrl+K a few times and then Ctrl+Y restores all of the lines which were killed.
+///
+/// Using a kill ring is optional: [`InputHandler`]s which don't keep one can treat `Kill` like
+/// [`Action::Delete`] and ignore `Yank`.
+///
+/// # Examples
+///
+/// Handling the kill ring's actions in [`InputHandler::handle_action`], where `kill_ring` is
+/// [`KillRing::shared`]:
+///
+/// ```ignore
+/// fn handle_action(&mut self, action: Action) {
+///     if let Action::Kill(_) | Action::DeleteToMark | Action::Yank = action {
+///         let kill_ring = self.kill_ring.clone();
+///         kill_ring.borrow_mut().handle_action(self, action);
+///         return;
+///     }
+///     // ...
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct KillRing {
+    /// The kills, latest first.
+    entries: VecDeque<String>,
+    capacity: usize,
+    /// Where the caret was, and how long the document was, after the latest kill.
+    ///
+    /// A kill from the same caret position in an unchanged document joins the latest entry.
+    last_kill: Option<(usize, usize)>,
+    /// The text which the latest yank inserted, and how long the document was afterwards.
+    last_yank: Option<(Range<usize>, usize)>,
+}
+
+impl KillRing {
+    /// A kill ring which remembers up to `capacity` kills.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "a kill ring must be able to hold a kill");
+        KillRing {
+            entries: VecDeque::new(),
+            capacity,
+            last_kill: None,
+            last_yank: None,
+        }
+    }
+
+    /// The kill ring shared by all of this thread's text fields.
+    ///
+    /// On macOS, Cocoa's paragraph deletions reach handlers as [`Action::Delete`], and the
+    /// backend adds the deleted text to this ring, so that it can be yanked from the ring.
+    pub fn shared() -> Rc<RefCell<KillRing>> {
+        SHARED.with(Rc::clone)
+    }
+
+    /// The text which [`Action::Yank`] inserts, if anything has been killed.
+    pub fn latest(&self) -> Option<&str> {
+        self.entries.front().map(String::as_str)
+    }
+
+    /// All of the remembered kills, latest first.
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(String::as_str)
+    }
+
+    /// Add `text` to the ring as a new kill, for text which the application deleted itself.
+    pub fn push(&mut self, text: impl Into<String>) {
+        self.record(text.into(), None);
+        self.last_kill = None;
+        self.last_yank = None;
+    }
+
+    /// Move the latest kill to the back of the ring, so that the one before it is yanked next.
+    ///
+    /// See [`yank_pop`](Self::yank_pop) for also replacing the text which was just yanked.
+    pub fn rotate(&mut self) {
+        self.entries.rotate_left(self.entries.len().min(1));
+        self.last_kill = None;
+        self.last_yank = None;
+    }
+
+    /// Replace the text which the latest [`Action::Yank`] inserted into `handler` with the kill
+    /// before it, like Emacs' `yank-pop`.
+    ///
+    /// Returns `false` without doing anything if the handler was edited or the caret moved
+    /// since the yank.
+    pub fn yank_pop(&mut self, handler: &mut dyn InputHandler) -> bool {
+        let Some((range, len)) = self.last_yank.take() else {
+            return false;
+        };
+        let selection = handler.selection();
+        if handler.len() != len || !selection.is_caret() || selection.active != range.end {
+            return false;
+        }
+        self.entries.rotate_left(1);
+        let text = &self.entries[0];
+        let end = range.start + text.len();
+        handler.replace_range(range.clone(), text);
+        handler.set_selection(Selection::caret(end));
+        self.last_yank = Some((range.start..end, handler.len()));
+        true
+    }
+
+    /// Perform `action` on `handler`, if it is one of the kill ring's actions.
+    ///
+    /// Handles [`Action::Kill`], [`Action::DeleteToMark`] and [`Action::Yank`], returning `false`
+    /// for any other action. The kill ring drives the handler with other actions to do so:
+    /// `MoveSelecting` to find the text to kill when the selection is a caret, and
+    /// `SelectToMark` to find the text to kill for `DeleteToMark`.
+    pub fn handle_action(&mut self, handler: &mut dyn InputHandler, action: Action) -> bool {
+        match action {
+            Action::Kill(movement) => {
+                let join = self.select_kill(handler, movement);
+                self.kill_selection(handler, join);
+                self.last_yank = None;
+            }
+            Action::DeleteToMark => {
+                handler.handle_action(Action::SelectToMark);
+                self.kill_selection(handler, None);
+                self.last_yank = None;
+            }
+            Action::Yank => {
+                self.last_yank = None;
+                if let Some(text) = self.entries.front() {
+                    let start = handler.selection().min();
+                    let end = start + text.len();
+                    handler.replace_range(handler.selection().range(), text);
+                    handler.set_selection(Selection::caret(end));
+                    self.last_yank = Some((start..end, handler.len()));
+                }
+                self.last_kill = None;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Record the text which `movement` is about to delete from `handler`, for platforms which
+    /// delete it themselves, joining it to the latest kill like [`Action::Kill`] does.
+    #[allow(dead_code)] // Only used on macOS so far.
+    pub(crate) fn record_kill(&mut self, handler: &mut dyn InputHandler, movement: Movement) {
+        let before = handler.selection();
+        let join = self.select_kill(handler, movement);
+        let range = handler.selection().range();
+        if !range.is_empty() {
+            let text = handler.slice(range.clone()).into_owned();
+            self.record(text, join);
+            // Where the caret will be, in the document without the text
+            self.last_kill = Some((range.start, handler.len() - range.len()));
+        }
+        handler.set_selection(before);
+        self.last_yank = None;
+    }
+
+    /// Select the text which killing with `movement` deletes from `handler`, returning whether
+    /// it joins the latest kill in the form [`record`](Self::record) takes.
+    fn select_kill(&self, handler: &mut dyn InputHandler, movement: Movement) -> Option<bool> {
+        let before = handler.selection();
+        let may_join = before.is_caret() && self.last_kill == Some((before.active, handler.len()));
+        if before.is_caret() {
+            handler.handle_action(Action::MoveSelecting(movement));
+        }
+        let after = handler.selection();
+        may_join.then_some(after.active < after.anchor)
+    }
+
+    /// Delete the text selected in `handler`, and record it.
+    fn kill_selection(&mut self, handler: &mut dyn InputHandler, join: Option<bool>) {
+        let range = handler.selection().range();
+        if range.is_empty() {
+            return;
+        }
+        let text = handler.slice(range.clone()).into_owned();
+        handler.replace_range(range.clone(), "");
+        handler.set_selection(Selection::caret(range.start));
+        self.record(text, join);
+        self.last_kill = Some((range.start, handler.len()));
+    }
+
+    /// Add `text` to the ring, joining it to the latest kill if `join` says which end to put it
+    /// on: `Some(true)` for an upstream kill, which goes before the latest kill.
+    fn record(&mut self, text: String, join: Option<bool>) {
+        match (join, self.entries.front_mut()) {
+            (Some(true), Some(latest)) => latest.insert_str(0, &text),
+            (Some(false), Some(latest)) => latest.push_str(&text),
+            _ => {
+                self.entries.push_front(text);
+                self.entries.truncate(self.capacity);
+            }
+        }
+    }
+}
+
+impl Default for KillRing {
+    fn default() -> Self {
+        KillRing::new(DEFAULT_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::kurbo::{Point, Rect};
+    use crate::text::{Affinity, Direction, HitTestPoint, TextBuffer, TextLayout};
+
+    /// Lays the text out on a single line, which is enough for word and paragraph movement.
+    struct OneLine(usize);
+
+    impl TextLayout for OneLine {
+        fn set_text(&mut self, text: &str) {
+            self.0 = text.len();
+        }
+
+        fn hit_test_point(&self, _: Point) -> HitTestPoint {
+            HitTestPoint::new(0, false)
+        }
+
+        fn line_range(&self, _: usize, _: Affinity) -> Range<usize> {
+            0..self.0
+        }
+
+        fn bounding_box(&self) -> Option<Rect> {
+            None
+        }
+
+        fn slice_bounding_box(&self, _: Range<usize>) -> Option<Rect> {
+            None
+        }
+    }
+
+    #[test]
+    fn joins_and_rotates() {
+        let mut ring = KillRing::new(2);
+        assert_eq!(ring.latest(), None);
+        ring.record("one".into(), None);
+        ring.record(" two".into(), Some(false));
+        ring.record("zero ".into(), Some(true));
+        assert_eq!(ring.latest(), Some("zero one two"));
+
+        ring.push("three");
+        ring.push("four");
+        assert_eq!(ring.iter().collect::<Vec<_>>(), ["four", "three"]);
+        ring.rotate();
+        assert_eq!(ring.latest(), Some("three"));
+    }
+
+    #[test]
+    fn kills_and_yanks() {
+        let mut ring = KillRing::default();
+        ring.push("earlier");
+        let mut field = TextBuffer::new("one two three", OneLine(0));
+        field.set_selection(Selection::caret(7));
+        assert!(ring.handle_action(&mut field, Action::Kill(Movement::ParagraphEnd)));
+        let word_start = Movement::Word(Direction::Upstream);
+        assert!(ring.handle_action(&mut field, Action::Kill(word_start)));
+        assert_eq!(field.text(), "one ");
+        assert_eq!(ring.iter().collect::<Vec<_>>(), ["two three", "earlier"]);
+
+        assert!(ring.handle_action(&mut field, Action::Yank));
+        assert_eq!(field.text(), "one two three");
+        assert_eq!(field.selection(), Selection::caret(13));
+        assert!(ring.yank_pop(&mut field));
+        assert_eq!(field.text(), "one earlier");
+        assert_eq!(field.selection(), Selection::caret(11));
+        assert!(ring.yank_pop(&mut field));
+        assert_eq!(field.text(), "one two three");
+
+        field.set_selection(Selection::caret(0));
+        assert!(!ring.yank_pop(&mut field));
+        assert!(!ring.handle_action(&mut field, Action::SetMark));
+    }
+
+    #[test]
+    fn joins_kills_made_by_the_platform() {
+        let mut ring = KillRing::default();
+        let mut field = TextBuffer::new("one two three", OneLine(0));
+        field.set_selection(Selection::caret(0));
+        let word_end = Movement::Word(Direction::Downstream);
+        for _ in 0..2 {
+            ring.record_kill(&mut field, word_end);
+            assert_eq!(field.selection(), Selection::caret(0));
+            field.handle_action(Action::Delete(word_end));
+        }
+        assert_eq!(field.text(), " three");
+        assert_eq!(ring.iter().collect::<Vec<_>>(), ["one two"]);
+    }
+}
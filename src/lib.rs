@@ -48,15 +48,20 @@ mod backend;
 mod clipboard;
 mod common_util;
 mod dialog;
+mod drag;
 mod error;
+mod gesture;
 mod hotkey;
+mod key_bindings;
 mod keyboard;
+mod kill_ring;
 mod menu;
 mod mouse;
 mod pointer;
 mod region;
 mod scale;
 mod screen;
+mod touch_gesture;
 mod window;
 
 pub mod platform;
@@ -66,21 +71,29 @@ pub use application::{AppHandle, AppHandler, Application};
 pub use clipboard::{Clipboard, ClipboardFormat, FormatId};
 pub use common_util::Counter;
 pub use dialog::{FileDialogOptions, FileInfo, FileSpec};
+pub use drag::{DragAction, DragActions, DragImage, DragOutcome, DragToken};
 pub use error::Error;
-pub use hotkey::{HotKey, RawMods, SysMods};
-pub use keyboard::{Code, IntoKey, KbKey, KeyEvent, KeyState, Location, Modifiers};
+pub use gesture::{Gesture, GestureEvent, GesturePhase};
+pub use hotkey::{HotKey, ParseHotKeyError, RawMods, SysMods};
+pub use key_bindings::{KeyBindingStyle, KeyBindings};
+pub use keyboard::{
+    Code, IntoKey, KbKey, KeyEvent, KeyState, KeyboardLayouts, Location, Modifiers,
+};
+pub use kill_ring::KillRing;
 pub use menu::Menu;
 pub use mouse::{Cursor, CursorDesc};
 pub use pointer::{
-    MouseInfo, PenInclination, PenInfo, PointerButton, PointerButtons, PointerEvent, PointerId,
-    PointerType, TouchInfo,
+    Angle, MouseInfo, PenInclination, PenInfo, PenTool, PenToolType, PointerButton, PointerButtons,
+    PointerConstraint, PointerEvent, PointerId, PointerType, RelativeMotionEvent, ScrollDeltaUnit,
+    ScrollPhase, ScrollSource, TouchInfo,
 };
 pub use region::Region;
 pub use scale::{Scalable, Scale, ScaledArea};
 pub use screen::{Monitor, Screen};
+pub use touch_gesture::{TouchGesture, TouchGestureConfig, TouchGestureRecognizer};
 pub use window::{
-    FileDialogToken, IdleHandle, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowBuilder,
-    WindowHandle, WindowLevel, WindowState,
+    FileDialogToken, FrameTiming, IdleHandle, IdleToken, TextFieldToken, TimerToken, WinHandler,
+    WindowBuilder, WindowHandle, WindowLevel, WindowState,
 };
 
 pub use keyboard_types;
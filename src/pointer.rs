@@ -1,3 +1,5 @@
+use instant::Instant;
+
 use crate::kurbo::{Point, Size, Vec2};
 use crate::Modifiers;
 
@@ -149,10 +151,40 @@ pub struct PenInfo {
     pub inclination: PenInclination,
     /// How much has the pen been twisted around its axis. In the range `[0, 2π)` radians.
     pub twist: Angle,
+    /// How far the pen is above the tablet while it hovers, ranging from `0.0` (touching) to
+    /// `1.0` (the furthest away the tablet can track it). `None` if the tablet doesn't report it.
+    pub distance: Option<f64>,
+    /// The tool which caused the event.
+    pub tool: PenTool,
 }
 
 impl PenInfo {}
 
+/// Identifies the tool used with a tablet, such as one of several styluses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct PenTool {
+    /// The kind of tool.
+    pub tool_type: PenToolType,
+    /// The serial number of the tool, which is unique to each physical tool and stays the same
+    /// when it is used with another tablet, so it can be used to keep settings (such as a
+    /// brush) for each tool. `None` if the tablet or platform doesn't report it.
+    pub serial: Option<u64>,
+}
+
+/// The kind of a tool used with a tablet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PenToolType {
+    /// The platform didn't tell us.
+    #[default]
+    Unknown,
+    /// The tip of a stylus.
+    Pen,
+    /// The eraser end of a stylus, or a dedicated eraser.
+    Eraser,
+    /// A puck which is moved around on the tablet, like a mouse.
+    Mouse,
+}
+
 /// Various properties of a touch event.
 ///
 /// These follow the web [PointerEvents] specification fairly closely, so see those
@@ -167,9 +199,87 @@ pub struct TouchInfo {
 }
 
 /// Various properties of a mouse event.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct MouseInfo {
+    /// The distance scrolled by a wheel event, measured in [`wheel_delta_unit`]s.
+    ///
+    /// Positive values scroll towards the bottom right of the content.
+    ///
+    /// This used to be a platform-specific number of pixels, such as 120 for each detent of
+    /// a mouse wheel on Windows and X11. Mouse wheels now report one line per detent, in
+    /// [`Lines`], and [`wheel_delta_pixels`] converts any unit into pixels.
+    ///
+    /// [`wheel_delta_unit`]: MouseInfo::wheel_delta_unit
+    /// [`Lines`]: ScrollDeltaUnit::Lines
+    /// [`wheel_delta_pixels`]: MouseInfo::wheel_delta_pixels
     pub wheel_delta: Vec2,
+    /// The unit of [`wheel_delta`](MouseInfo::wheel_delta).
+    pub wheel_delta_unit: ScrollDeltaUnit,
+    /// Where this event is in a scroll gesture, for devices (such as touchpads) which
+    /// report when the fingers are put down and lifted.
+    pub scroll_phase: ScrollPhase,
+    /// Where this event is in the momentum ("kinetic") scrolling which the platform
+    /// generates after the fingers are lifted.
+    pub momentum_phase: ScrollPhase,
+    /// The kind of device which caused the scroll.
+    pub scroll_source: ScrollSource,
+}
+
+impl MouseInfo {
+    /// The wheel delta in pixels (display points), given the height of a line and the size of
+    /// a page of the content being scrolled.
+    pub fn wheel_delta_pixels(&self, line_height: f64, page_size: Size) -> Vec2 {
+        match self.wheel_delta_unit {
+            ScrollDeltaUnit::Pixels => self.wheel_delta,
+            ScrollDeltaUnit::Lines => self.wheel_delta * line_height,
+            ScrollDeltaUnit::Pages => Vec2::new(
+                self.wheel_delta.x * page_size.width,
+                self.wheel_delta.y * page_size.height,
+            ),
+        }
+    }
+}
+
+/// The unit of [`MouseInfo::wheel_delta`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ScrollDeltaUnit {
+    /// Display points, as reported by touchpads and other precise devices.
+    Pixels,
+    /// Lines of content. One line is usually one detent of a mouse wheel; how far that
+    /// scrolls is up to the application.
+    #[default]
+    Lines,
+    /// Pages of content.
+    Pages,
+}
+
+/// Where a scroll event is in a gesture.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ScrollPhase {
+    /// The event isn't part of a gesture, for example because it came from a mouse wheel.
+    #[default]
+    None,
+    /// The first event of the gesture.
+    Began,
+    /// The gesture continued.
+    Changed,
+    /// The gesture ended (or was cancelled). This event usually has a zero delta.
+    Ended,
+}
+
+/// The kind of device which caused a scroll.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ScrollSource {
+    /// The platform didn't tell us.
+    #[default]
+    Unknown,
+    /// A mouse wheel, which scrolls in discrete steps.
+    Wheel,
+    /// Fingers on a touchpad or touchscreen.
+    Finger,
+    /// A continuous movement without a clear end, for example from a trackball or from a
+    /// button held down while moving the mouse.
+    Continuous,
 }
 
 impl Default for PenInfo {
@@ -182,6 +292,8 @@ impl Default for PenInfo {
                 altitude: Angle::degrees(90.0),
                 azimuth: Angle::degrees(0.0),
             },
+            distance: None,
+            tool: PenTool::default(),
         }
     }
 }
@@ -222,6 +334,18 @@ pub enum PointerButton {
     X1,
     /// X2 (forward) Mouse.
     X2,
+    /// A button on the barrel of a pen, which doesn't have a more common meaning.
+    ///
+    /// Pens with two barrel buttons usually report the other one as
+    /// [`PointerButton::Secondary`].
+    PenBarrel,
+    /// The eraser end of a pen touching the tablet.
+    ///
+    /// Like in the [Pointer Events] spec, an eraser's contact is reported as this rather than
+    /// [`PointerButton::Primary`].
+    ///
+    /// [Pointer Events]: https://w3c.github.io/pointerevents/#the-button-property
+    PenEraser,
 }
 
 impl PointerButton {
@@ -254,6 +378,18 @@ impl PointerButton {
     pub fn is_x2(self) -> bool {
         self == PointerButton::X2
     }
+
+    /// Returns `true` if this is [`PointerButton::PenBarrel`].
+    #[inline]
+    pub fn is_pen_barrel(self) -> bool {
+        self == PointerButton::PenBarrel
+    }
+
+    /// Returns `true` if this is [`PointerButton::PenEraser`].
+    #[inline]
+    pub fn is_pen_eraser(self) -> bool {
+        self == PointerButton::PenEraser
+    }
 }
 
 /// A set of [`PointerButton`]s.
@@ -268,6 +404,8 @@ fn button_bit(button: PointerButton) -> u8 {
         PointerButton::Auxiliary => 0b100,
         PointerButton::X1 => 0b1000,
         PointerButton::X2 => 0b10000,
+        PointerButton::PenBarrel => 0b100000,
+        PointerButton::PenEraser => 0b1000000,
     }
 }
 
@@ -352,6 +490,18 @@ impl PointerButtons {
         self.contains(PointerButton::X2)
     }
 
+    /// Returns `true` if [`PointerButton::PenBarrel`] is in the set.
+    #[inline]
+    pub fn has_pen_barrel(self) -> bool {
+        self.contains(PointerButton::PenBarrel)
+    }
+
+    /// Returns `true` if [`PointerButton::PenEraser`] is in the set.
+    #[inline]
+    pub fn has_pen_eraser(self) -> bool {
+        self.contains(PointerButton::PenEraser)
+    }
+
     /// Adds all the `buttons` to the set.
     pub fn extend(&mut self, buttons: PointerButtons) {
         self.0 |= buttons.0;
@@ -379,7 +529,7 @@ impl PointerButtons {
 
 impl std::fmt::Debug for PointerButtons {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "PointerButtons({:05b})", self.0)
+        write!(f, "PointerButtons({:07b})", self.0)
     }
 }
 
@@ -392,7 +542,15 @@ pub struct PointerEvent {
     pub is_primary: bool,
     pub pointer_type: PointerType,
 
-    // TODO: figure out timestamps.
+    /// When the event happened, if the platform reports it.
+    ///
+    /// This is on the same clock as [`Instant::now`], so it can be compared with the times of
+    /// other events (such as [`KeyEvent::time`]) and with the current time, for example to find
+    /// a pointer's velocity or the latency of handling its events. Platforms generally report
+    /// event times in milliseconds. Currently only X11 and Wayland provide this.
+    ///
+    /// [`KeyEvent::time`]: crate::KeyEvent::time
+    pub time: Option<Instant>,
     pub pos: Point,
     pub buttons: PointerButtons,
     pub modifiers: Modifiers,
@@ -407,6 +565,26 @@ pub struct PointerEvent {
 
     // TODO: Should this be here, or only in mouse/pen events?
     pub count: u8,
+
+    /// The samples which were merged into this movement, oldest first, ending with one
+    /// matching this event.
+    ///
+    /// Pointers such as pens can report their position much more often than the window is able
+    /// to handle it, so platforms may merge several samples into one `pointer_move`. This is
+    /// empty if the event was not merged, so [`coalesced_events`](Self::coalesced_events) is
+    /// the easiest way to visit every sample. This is like `getCoalescedEvents` in the
+    /// [Pointer Events] spec.
+    ///
+    /// [Pointer Events]: https://w3c.github.io/pointerevents/#dom-pointerevent-getcoalescedevents
+    pub coalesced: Vec<PointerEvent>,
+    /// Samples which the platform expects the pointer to produce next, oldest first.
+    ///
+    /// These can be drawn ahead of the real samples to hide latency, but will be replaced by the
+    /// real samples in later events. This is like `getPredictedEvents` in the [Pointer Events]
+    /// spec. It is usually empty, as few platforms make predictions.
+    ///
+    /// [Pointer Events]: https://w3c.github.io/pointerevents/#dom-pointerevent-getpredictedevents
+    pub predicted: Vec<PointerEvent>,
 }
 
 // Do we need a way of getting at maxTouchPoints?
@@ -422,9 +600,10 @@ impl Default for PointerEvent {
             count: 0,
             pointer_id: PointerId(0),
             is_primary: true,
-            pointer_type: PointerType::Mouse(MouseInfo {
-                wheel_delta: Vec2::ZERO,
-            }),
+            pointer_type: PointerType::Mouse(MouseInfo::default()),
+            time: None,
+            coalesced: Vec::new(),
+            predicted: Vec::new(),
         }
     }
 }
@@ -443,6 +622,42 @@ impl PointerEvent {
     pub fn is_pen(&self) -> bool {
         matches!(self.pointer_type, PointerType::Pen(_))
     }
+
+    /// Every sample making up this event: its [`coalesced`](Self::coalesced) samples, or the
+    /// event itself if there are none.
+    pub fn coalesced_events(&self) -> &[PointerEvent] {
+        if self.coalesced.is_empty() {
+            std::slice::from_ref(self)
+        } else {
+            &self.coalesced
+        }
+    }
+}
+
+/// How the pointer is restricted while it is over a window.
+///
+/// See [`WindowHandle::set_pointer_constraint`](crate::WindowHandle::set_pointer_constraint).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PointerConstraint {
+    /// The pointer moves freely.
+    #[default]
+    None,
+    /// The pointer can't leave the window.
+    Confined,
+    /// The pointer stays where it is, so its movement is only reported as
+    /// [`RelativeMotionEvent`]s. This is what first-person camera controls want.
+    Locked,
+}
+
+/// The movement of a pointer which is locked or confined to a window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelativeMotionEvent {
+    pub pointer_id: PointerId,
+    /// The movement in display points, after the platform's pointer acceleration.
+    pub delta: Vec2,
+    /// The movement as reported by the device, without pointer acceleration. The unit
+    /// depends on the device: for mice, it's usually the resolution of the sensor.
+    pub unaccelerated_delta: Vec2,
 }
 
 #[cfg(test)]
@@ -460,4 +675,34 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn wheel_delta_pixels() {
+        let page = Size::new(300.0, 200.0);
+        let mut info = MouseInfo {
+            wheel_delta: Vec2::new(1.0, -2.0),
+            ..Default::default()
+        };
+        assert_eq!(info.wheel_delta_pixels(20.0, page), Vec2::new(20.0, -40.0));
+        info.wheel_delta_unit = ScrollDeltaUnit::Pages;
+        assert_eq!(
+            info.wheel_delta_pixels(20.0, page),
+            Vec2::new(300.0, -400.0)
+        );
+        info.wheel_delta_unit = ScrollDeltaUnit::Pixels;
+        assert_eq!(info.wheel_delta_pixels(20.0, page), Vec2::new(1.0, -2.0));
+    }
+
+    #[test]
+    fn coalesced_events() {
+        let mut event = PointerEvent::default();
+        assert_eq!(event.coalesced_events(), &[event.clone()]);
+        let earlier = PointerEvent {
+            pos: Point::new(1.0, 2.0),
+            ..PointerEvent::default()
+        };
+        event.coalesced = vec![earlier.clone(), event.clone()];
+        assert_eq!(event.coalesced_events().len(), 2);
+        assert_eq!(event.coalesced_events()[0], earlier);
+    }
 }
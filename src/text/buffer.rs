@@ -0,0 +1,869 @@
+//! An [`InputHandler`] over a `String`.
+
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::ops::Range;
+use std::rc::Rc;
+
+use unicode_segmentation::{GraphemeCursor, UnicodeSegmentation};
+
+use super::{
+    Action, Affinity, Direction, HitTestPoint, InputHandler, Movement, PreeditAttributes,
+    Selection, VerticalMovement, WritingDirection,
+};
+use crate::application::Application;
+use crate::kill_ring::KillRing;
+use crate::kurbo::{Point, Rect};
+
+/// The layout of the text of a [`TextBuffer`], which answers the geometric queries of
+/// [`InputHandler`] for it.
+///
+/// Like those of [`InputHandler`], positions are in window coordinates, and indices are in
+/// UTF-8 code units.
+pub trait TextLayout {
+    /// Lay out `text`, which replaces the previous text.
+    ///
+    /// The buffer calls this after every edit.
+    fn set_text(&mut self, text: &str);
+
+    /// See [`InputHandler::hit_test_point`].
+    fn hit_test_point(&self, point: Point) -> HitTestPoint;
+
+    /// See [`InputHandler::line_range`].
+    fn line_range(&self, index: usize, affinity: Affinity) -> Range<usize>;
+
+    /// See [`InputHandler::bounding_box`].
+    fn bounding_box(&self) -> Option<Rect>;
+
+    /// See [`InputHandler::slice_bounding_box`].
+    ///
+    /// The buffer also uses this with empty ranges to find where the caret is for vertical
+    /// movement.
+    fn slice_bounding_box(&self, range: Range<usize>) -> Option<Rect>;
+}
+
+/// A text field's document, selection and composition, which implements every [`Action`].
+///
+/// This is a reference implementation of [`InputHandler`] for applications which don't need
+/// their own text representation. Movement is by extended grapheme clusters and by words as
+/// defined by [Unicode Text Segmentation](https://unicode.org/reports/tr29/), and the buffer
+/// keeps the undo history and the mark. Layout is left to a [`TextLayout`].
+///
+/// The buffer treats all text as left-to-right, so [`Direction::Left`] is upstream. Actions
+/// which concern the view or the rest of the window rather than the text, like
+/// [`Action::Scroll`], [`Action::InsertBacktab`] and [`Action::Cancel`], are left to the
+/// application: [`TextBuffer::apply_action`] returns `false` for them.
+///
+/// An `Rc<RefCell<TextBuffer>>` is also an [`InputHandler`], so that an application can keep
+/// the buffer and return a clone of it from [`WinHandler::acquire_input_lock`].
+///
+/// [`WinHandler::acquire_input_lock`]: crate::WinHandler::acquire_input_lock
+#[derive(Debug)]
+pub struct TextBuffer<L> {
+    text: String,
+    selection: Selection,
+    composition: Option<Range<usize>>,
+    preedit: Option<PreeditAttributes>,
+    mark: Option<usize>,
+    layout: L,
+    kill_ring: Option<Rc<RefCell<KillRing>>>,
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+}
+
+/// A change to the text, as recorded for undo and redo.
+#[derive(Debug)]
+struct Edit {
+    /// Where the change was made.
+    start: usize,
+    /// The text which was replaced.
+    old: String,
+    /// The text which replaced it.
+    new: String,
+    /// The selection before the change, which undoing it restores.
+    selection: Selection,
+}
+
+impl<L: TextLayout> TextBuffer<L> {
+    /// A buffer containing `text`, laid out by `layout`, with the caret at the end.
+    pub fn new(text: impl Into<String>, mut layout: L) -> Self {
+        let text = text.into();
+        layout.set_text(&text);
+        TextBuffer {
+            selection: Selection::caret(text.len()),
+            text,
+            composition: None,
+            preedit: None,
+            mark: None,
+            layout,
+            kill_ring: None,
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    /// The text of the document.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replace the whole document with `text`, putting the caret at the end.
+    ///
+    /// This also forgets the composition, the mark and the undo history.
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        self.text = text.into();
+        self.selection = Selection::caret(self.text.len());
+        self.composition = None;
+        self.preedit = None;
+        self.mark = None;
+        self.undo.clear();
+        self.redo.clear();
+        self.layout.set_text(&self.text);
+    }
+
+    /// The layout of the text.
+    pub fn layout(&self) -> &L {
+        &self.layout
+    }
+
+    /// The layout of the text, for changing how it is laid out, such as its width.
+    pub fn layout_mut(&mut self) -> &mut L {
+        &mut self.layout
+    }
+
+    /// The mark set by [`Action::SetMark`].
+    pub fn mark(&self) -> Option<usize> {
+        self.mark
+    }
+
+    /// How the composition should be drawn, if the input method said so.
+    pub fn preedit_attributes(&self) -> Option<&PreeditAttributes> {
+        self.preedit.as_ref()
+    }
+
+    /// Use `kill_ring` for [`Action::Kill`] and [`Action::Yank`].
+    ///
+    /// Without a kill ring, `Kill` deletes the text and `Yank` does nothing.
+    pub fn set_kill_ring(&mut self, kill_ring: Option<Rc<RefCell<KillRing>>>) {
+        self.kill_ring = kill_ring;
+    }
+
+    /// Perform `action`, returning whether it was handled.
+    ///
+    /// This is what [`InputHandler::handle_action`] does, for applications which want to handle
+    /// the actions the buffer doesn't.
+    pub fn apply_action(&mut self, action: Action) -> bool {
+        match action {
+            Action::Move(movement) => {
+                let selection = self.selection;
+                let upstream = movement_is_upstream(movement);
+                let index = if upstream {
+                    selection.min()
+                } else {
+                    selection.max()
+                };
+                if selection.is_caret() || !matches!(movement, Movement::Grapheme(_)) {
+                    let (index, h_pos) = self.move_index(index, movement, selection.h_pos);
+                    self.selection = Selection::caret(index).with_h_pos(h_pos);
+                } else {
+                    self.selection = Selection::caret(index);
+                }
+            }
+            Action::MoveSelecting(movement) => {
+                let mut selection = self.selection;
+                let (active, h_pos) = self.move_index(selection.active, movement, selection.h_pos);
+                selection.active = active;
+                self.selection = selection.with_h_pos(h_pos);
+            }
+            Action::SelectAll => self.selection = Selection::new(0, self.text.len()),
+            Action::SelectLine => {
+                let selection = self.selection;
+                let end_affinity = match selection.is_caret() {
+                    true => Affinity::Downstream,
+                    false => Affinity::Upstream,
+                };
+                let start = self
+                    .layout
+                    .line_range(selection.min(), Affinity::Downstream)
+                    .start;
+                let end = self.layout.line_range(selection.max(), end_affinity).end;
+                self.selection = Selection::new(start, end);
+            }
+            Action::SelectParagraph => {
+                let selection = self.selection;
+                let mut last = selection.max();
+                if !selection.is_caret() && self.text[..last].ends_with('\n') {
+                    last -= 1;
+                }
+                let start = self.paragraph_start(selection.min());
+                self.selection = Selection::new(start, self.paragraph_end(last));
+            }
+            Action::SelectWord => {
+                let selection = self.selection;
+                let start = self.word_segment(selection.min()).start;
+                let end = match selection.is_caret() {
+                    true => self.word_segment(selection.min()).end,
+                    false => self.word_segment(selection.max() - 1).end,
+                };
+                self.selection = Selection::new(start, end);
+            }
+            Action::Delete(movement) => self.delete(movement),
+            Action::DecomposingBackspace => {
+                let selection = self.selection;
+                if selection.is_caret() {
+                    let Some(c) = self.text[..selection.active].chars().next_back() else {
+                        return false;
+                    };
+                    let start = selection.active - c.len_utf8();
+                    self.replace(start..selection.active, "");
+                } else {
+                    self.replace(selection.range(), "");
+                }
+            }
+            Action::UppercaseSelection => self.map_selection(|_, c| c.to_uppercase().collect()),
+            Action::LowercaseSelection => self.map_selection(|_, c| c.to_lowercase().collect()),
+            Action::TitlecaseSelection => {
+                let word_starts: Vec<usize> = self
+                    .text
+                    .split_word_bound_indices()
+                    .map(|(start, _)| start)
+                    .collect();
+                self.map_selection(|index, c| match word_starts.binary_search(&index) {
+                    Ok(_) => c.to_uppercase().collect(),
+                    Err(_) => c.to_lowercase().collect(),
+                })
+            }
+            Action::InsertNewLine { newline_type, .. } => {
+                self.insert(newline_type.encode_utf8(&mut [0; 4]))
+            }
+            Action::InsertTab { .. } => self.insert("\t"),
+            Action::InsertSingleQuoteIgnoringSmartQuotes => self.insert("'"),
+            Action::InsertDoubleQuoteIgnoringSmartQuotes => self.insert("\""),
+            Action::Undo => {
+                let Some(edit) = self.undo.pop() else {
+                    return false;
+                };
+                self.splice(edit.start..edit.start + edit.new.len(), &edit.old);
+                self.selection = edit.selection.constrained(&self.text);
+                self.redo.push(edit);
+            }
+            Action::Redo => {
+                let Some(edit) = self.redo.pop() else {
+                    return false;
+                };
+                self.splice(edit.start..edit.start + edit.old.len(), &edit.new);
+                self.selection = Selection::caret(edit.start + edit.new.len());
+                self.undo.push(edit);
+            }
+            Action::Cut | Action::Copy => {
+                let selection = self.selection;
+                let Some(app) = Application::try_global() else {
+                    return false;
+                };
+                if selection.is_caret() {
+                    return false;
+                }
+                app.clipboard().put_string(&self.text[selection.range()]);
+                if action == Action::Cut {
+                    self.replace(selection.range(), "");
+                }
+            }
+            Action::Paste | Action::PasteAsPlainText => {
+                let Some(app) = Application::try_global() else {
+                    return false;
+                };
+                let Some(contents) = app.clipboard().get_string() else {
+                    return false;
+                };
+                self.insert(&contents);
+            }
+            Action::Kill(movement) => match self.kill_ring.clone() {
+                Some(kill_ring) => return kill_ring.borrow_mut().handle_action(self, action),
+                None => self.delete(movement),
+            },
+            Action::Yank => match self.kill_ring.clone() {
+                Some(kill_ring) => return kill_ring.borrow_mut().handle_action(self, action),
+                None => return false,
+            },
+            Action::SetMark => self.mark = Some(self.selection.active),
+            Action::SelectToMark => {
+                let Some(mark) = self.mark else {
+                    return false;
+                };
+                let selection = self.selection;
+                if mark < selection.min() {
+                    self.selection = Selection::new(selection.max(), mark);
+                } else if mark > selection.max() {
+                    self.selection = Selection::new(selection.min(), mark);
+                }
+            }
+            Action::DeleteToMark => match self.kill_ring.clone() {
+                Some(kill_ring) => return kill_ring.borrow_mut().handle_action(self, action),
+                None => {
+                    self.apply_action(Action::SelectToMark);
+                    self.replace(self.selection.range(), "");
+                }
+            },
+            Action::SwapWithMark => {
+                let Some(mark) = self.mark else {
+                    return false;
+                };
+                self.mark = Some(self.selection.active);
+                self.selection = Selection::caret(mark);
+            }
+            Action::InsertBacktab
+            | Action::Scroll(_)
+            | Action::ScrollToSelection
+            | Action::SetSelectionWritingDirection(_)
+            | Action::SetParagraphWritingDirection(_)
+            | Action::Cancel => return false,
+        }
+        true
+    }
+
+    /// Replace `range` with `text`, recording the change for undo.
+    fn replace(&mut self, range: Range<usize>, text: &str) {
+        let edit = Edit {
+            start: range.start,
+            old: self.text[range.clone()].to_string(),
+            new: text.to_string(),
+            selection: self.selection,
+        };
+        self.splice(range, text);
+        self.redo.clear();
+        match self.undo.last_mut() {
+            // Typing a word, or an input method changing the text it just inserted
+            Some(last) if last.continues_with(&edit) => {
+                last.new.replace_range(edit.start - last.start.., &edit.new);
+            }
+            _ => self.undo.push(edit),
+        }
+    }
+
+    /// Replace the selection with `text`, leaving the caret after it.
+    fn insert(&mut self, text: &str) {
+        let selection = self.selection;
+        self.replace(selection.range(), text);
+        self.selection = Selection::caret(selection.min() + text.len());
+    }
+
+    /// Replace `range` with `text`, updating the selection as [`InputHandler::replace_range`]
+    /// describes.
+    fn splice(&mut self, range: Range<usize>, text: &str) {
+        self.text.replace_range(range.clone(), text);
+        let update = |index: usize| {
+            if index < range.start {
+                index
+            } else if index > range.end {
+                index - range.len() + text.len()
+            } else {
+                range.start + text.len()
+            }
+        };
+        let selection = self.selection;
+        let before = selection.anchor < range.start && selection.active < range.start;
+        let after = selection.anchor > range.end && selection.active > range.end;
+        if after {
+            self.selection = Selection::new(update(selection.anchor), update(selection.active));
+        } else if !before {
+            self.selection = Selection::caret(range.start + text.len());
+        }
+        self.selection.anchor = self.grapheme_boundary_at_or_after(self.selection.anchor);
+        self.selection.active = self.grapheme_boundary_at_or_after(self.selection.active);
+        self.mark = self.mark.map(update);
+        self.composition = None;
+        self.preedit = None;
+        self.layout.set_text(&self.text);
+    }
+
+    /// Delete the selection, or the text between the caret and where `movement` takes it.
+    fn delete(&mut self, movement: Movement) {
+        let selection = self.selection;
+        if selection.is_caret() {
+            let (index, _) = self.move_index(selection.active, movement, None);
+            self.replace(Selection::new(selection.active, index).range(), "");
+        } else {
+            self.replace(selection.range(), "");
+        }
+    }
+
+    /// Replace each character of the selection with `f(index, character)`, keeping the new
+    /// text selected.
+    fn map_selection(&mut self, mut f: impl FnMut(usize, char) -> String) {
+        let selection = self.selection;
+        let range = selection.range();
+        let mapped: String = self.text[range.clone()]
+            .char_indices()
+            .map(|(i, c)| f(range.start + i, c))
+            .collect();
+        self.replace(range.clone(), &mapped);
+        let end = range.start + mapped.len();
+        self.selection = match selection.anchor <= selection.active {
+            true => Selection::new(range.start, end),
+            false => Selection::new(end, range.start),
+        };
+    }
+
+    /// Where `movement` takes `index`, with the horizontal position to keep for further
+    /// vertical movement.
+    fn move_index(
+        &self,
+        index: usize,
+        movement: Movement,
+        h_pos: Option<f64>,
+    ) -> (usize, Option<f64>) {
+        let index = match movement {
+            Movement::Grapheme(direction) => {
+                let mut cursor = GraphemeCursor::new(index, self.text.len(), true);
+                match is_upstream(direction) {
+                    true => cursor.prev_boundary(&self.text, 0),
+                    false => cursor.next_boundary(&self.text, 0),
+                }
+                .ok()
+                .flatten()
+                .unwrap_or(index)
+            }
+            Movement::Word(direction) => {
+                let mut words = self
+                    .text
+                    .split_word_bound_indices()
+                    .filter(|(_, word)| word.chars().any(char::is_alphanumeric))
+                    .map(|(start, word)| start..start + word.len());
+                match is_upstream(direction) {
+                    true => words
+                        .rev()
+                        .find(|word| word.start < index)
+                        .map_or(0, |word| word.start),
+                    false => words
+                        .find(|word| word.end > index)
+                        .map_or(self.text.len(), |word| word.end),
+                }
+            }
+            Movement::Line(direction) => {
+                let line = self.layout.line_range(index, Affinity::Downstream);
+                match is_upstream(direction) {
+                    true => line.start,
+                    false => self.without_newline(line).end,
+                }
+            }
+            Movement::ParagraphStart => self.paragraph_start(index),
+            Movement::ParagraphEnd => self.paragraph_end(index),
+            Movement::Vertical(movement) => return self.move_vertically(index, movement, h_pos),
+        };
+        (index, None)
+    }
+
+    fn move_vertically(
+        &self,
+        index: usize,
+        movement: VerticalMovement,
+        h_pos: Option<f64>,
+    ) -> (usize, Option<f64>) {
+        let len = self.text.len();
+        let (up, page) = match movement {
+            VerticalMovement::DocumentStart => return (0, None),
+            VerticalMovement::DocumentEnd => return (len, None),
+            VerticalMovement::LineUp => (true, false),
+            VerticalMovement::LineDown => (false, false),
+            VerticalMovement::PageUp => (true, true),
+            VerticalMovement::PageDown => (false, true),
+        };
+        let edge = if up { 0 } else { len };
+        let Some(caret) = self.layout.slice_bounding_box(index..index) else {
+            return (edge, None);
+        };
+        let x = h_pos.unwrap_or(caret.x0);
+        let mut distance = caret.height();
+        if page {
+            if let Some(bounds) = self.layout.bounding_box() {
+                distance = distance.max(bounds.height());
+            }
+        }
+        let y = caret.center().y + if up { -distance } else { distance };
+        let target = self.layout.hit_test_point(Point::new(x, y)).idx;
+        // Moving past the first or last line goes to the start or end of the document
+        let line = self.layout.line_range(index, Affinity::Downstream);
+        if self.layout.line_range(target, Affinity::Downstream) == line {
+            return (edge, Some(x));
+        }
+        (self.grapheme_boundary_at_or_after(target), Some(x))
+    }
+
+    fn paragraph_start(&self, index: usize) -> usize {
+        self.text[..index].rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    fn paragraph_end(&self, index: usize) -> usize {
+        let end = self.text[index..]
+            .find('\n')
+            .map_or(self.text.len(), |i| index + i);
+        self.without_newline(index.min(end)..end).end
+    }
+
+    /// `range`, without the line break it may end with.
+    fn without_newline(&self, mut range: Range<usize>) -> Range<usize> {
+        let text = &self.text[range.clone()];
+        if text.ends_with("\r\n") {
+            range.end -= 2;
+        } else if text.ends_with('\n') {
+            range.end -= 1;
+        }
+        range
+    }
+
+    /// The word, or space or punctuation between words, which contains `index`.
+    fn word_segment(&self, index: usize) -> Range<usize> {
+        self.text
+            .split_word_bound_indices()
+            .map(|(start, word)| start..start + word.len())
+            .find(|word| word.contains(&index))
+            .unwrap_or(index..index)
+    }
+
+    /// `index` if it is a grapheme cluster boundary, and otherwise the next one.
+    fn grapheme_boundary_at_or_after(&self, index: usize) -> usize {
+        let mut index = index.min(self.text.len());
+        while !self.text.is_char_boundary(index) {
+            index += 1;
+        }
+        let mut cursor = GraphemeCursor::new(index, self.text.len(), true);
+        match cursor.is_boundary(&self.text, 0) {
+            Ok(false) => cursor
+                .next_boundary(&self.text, 0)
+                .ok()
+                .flatten()
+                .unwrap_or(self.text.len()),
+            _ => index,
+        }
+    }
+}
+
+impl Edit {
+    /// Whether `next` should be undone together with this edit.
+    fn continues_with(&self, next: &Edit) -> bool {
+        let end = self.start + self.new.len();
+        let replaces_this = next.start == self.start && next.old == self.new;
+        // Typing is undone a word at a time
+        let starts_word =
+            self.new.ends_with(char::is_whitespace) && !next.new.starts_with(char::is_whitespace);
+        let types_on =
+            self.old.is_empty() && next.old.is_empty() && next.start == end && !starts_word;
+        replaces_this || types_on
+    }
+}
+
+/// Whether `direction` is upstream, for a buffer which treats all text as left-to-right.
+fn is_upstream(direction: Direction) -> bool {
+    direction.is_upstream_for_direction(WritingDirection::LeftToRight)
+}
+
+fn movement_is_upstream(movement: Movement) -> bool {
+    match movement {
+        Movement::Grapheme(direction) | Movement::Word(direction) | Movement::Line(direction) => {
+            is_upstream(direction)
+        }
+        Movement::ParagraphStart => true,
+        Movement::ParagraphEnd => false,
+        Movement::Vertical(movement) => matches!(
+            movement,
+            VerticalMovement::LineUp | VerticalMovement::PageUp | VerticalMovement::DocumentStart
+        ),
+    }
+}
+
+impl<L: TextLayout> InputHandler for TextBuffer<L> {
+    fn selection(&self) -> Selection {
+        self.selection
+    }
+
+    fn set_selection(&mut self, selection: Selection) {
+        let anchor = self.grapheme_boundary_at_or_after(selection.anchor);
+        let active = self.grapheme_boundary_at_or_after(selection.active);
+        self.selection = Selection::new(anchor, active).with_h_pos(selection.h_pos);
+    }
+
+    fn composition_range(&self) -> Option<Range<usize>> {
+        self.composition.clone()
+    }
+
+    fn set_composition_range(&mut self, range: Option<Range<usize>>) {
+        if range.is_none() {
+            self.preedit = None;
+        }
+        self.composition = range.map(|range| {
+            self.grapheme_boundary_at_or_after(range.start)
+                ..self.grapheme_boundary_at_or_after(range.end)
+        });
+    }
+
+    fn set_preedit_attributes(&mut self, attributes: PreeditAttributes) {
+        self.preedit = Some(attributes);
+    }
+
+    fn is_char_boundary(&self, i: usize) -> bool {
+        self.text.is_char_boundary(i)
+    }
+
+    fn len(&self) -> usize {
+        self.text.len()
+    }
+
+    fn slice(&self, range: Range<usize>) -> Cow<str> {
+        Cow::Borrowed(&self.text[range])
+    }
+
+    fn utf16_to_utf8(&self, utf16_range: Range<usize>) -> usize {
+        let mut utf16 = 0;
+        let mut start = None;
+        let mut end = None;
+        for (i, c) in self.text.char_indices() {
+            if start.is_none() && utf16 >= utf16_range.start {
+                start = Some(i);
+            }
+            if utf16 >= utf16_range.end {
+                end = Some(i);
+                break;
+            }
+            utf16 += c.len_utf16();
+        }
+        let len = self.text.len();
+        end.unwrap_or(len) - start.unwrap_or(len).min(end.unwrap_or(len))
+    }
+
+    fn replace_range(&mut self, range: Range<usize>, text: &str) {
+        self.replace(range, text);
+    }
+
+    fn hit_test_point(&self, point: Point) -> HitTestPoint {
+        self.layout.hit_test_point(point)
+    }
+
+    fn line_range(&self, index: usize, affinity: Affinity) -> Range<usize> {
+        self.layout.line_range(index, affinity)
+    }
+
+    fn bounding_box(&self) -> Option<Rect> {
+        self.layout.bounding_box()
+    }
+
+    fn slice_bounding_box(&self, range: Range<usize>) -> Option<Rect> {
+        self.layout.slice_bounding_box(range)
+    }
+
+    fn handle_action(&mut self, action: Action) {
+        self.apply_action(action);
+    }
+}
+
+impl<L: TextLayout> InputHandler for Rc<RefCell<TextBuffer<L>>> {
+    fn selection(&self) -> Selection {
+        self.borrow().selection()
+    }
+
+    fn set_selection(&mut self, selection: Selection) {
+        self.borrow_mut().set_selection(selection)
+    }
+
+    fn composition_range(&self) -> Option<Range<usize>> {
+        self.borrow().composition_range()
+    }
+
+    fn set_composition_range(&mut self, range: Option<Range<usize>>) {
+        self.borrow_mut().set_composition_range(range)
+    }
+
+    fn set_preedit_attributes(&mut self, attributes: PreeditAttributes) {
+        self.borrow_mut().set_preedit_attributes(attributes)
+    }
+
+    fn is_char_boundary(&self, i: usize) -> bool {
+        self.borrow().is_char_boundary(i)
+    }
+
+    fn len(&self) -> usize {
+        self.borrow().len()
+    }
+
+    fn slice(&self, range: Range<usize>) -> Cow<str> {
+        Cow::Owned(self.borrow().text[range].to_string())
+    }
+
+    fn utf8_to_utf16(&self, utf8_range: Range<usize>) -> usize {
+        self.borrow().utf8_to_utf16(utf8_range)
+    }
+
+    fn utf16_to_utf8(&self, utf16_range: Range<usize>) -> usize {
+        self.borrow().utf16_to_utf8(utf16_range)
+    }
+
+    fn replace_range(&mut self, range: Range<usize>, text: &str) {
+        self.borrow_mut().replace_range(range, text)
+    }
+
+    fn hit_test_point(&self, point: Point) -> HitTestPoint {
+        self.borrow().hit_test_point(point)
+    }
+
+    fn line_range(&self, index: usize, affinity: Affinity) -> Range<usize> {
+        self.borrow().line_range(index, affinity)
+    }
+
+    fn bounding_box(&self) -> Option<Rect> {
+        self.borrow().bounding_box()
+    }
+
+    fn slice_bounding_box(&self, range: Range<usize>) -> Option<Rect> {
+        self.borrow().slice_bounding_box(range)
+    }
+
+    fn handle_action(&mut self, action: Action) {
+        self.borrow_mut().apply_action(action);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Lays text out in a grid, with every character one unit wide and every line one unit tall.
+    #[derive(Default)]
+    struct Grid {
+        lines: Vec<Range<usize>>,
+    }
+
+    impl TextLayout for Grid {
+        fn set_text(&mut self, text: &str) {
+            let mut start = 0;
+            self.lines = text
+                .split('\n')
+                .map(|line| {
+                    let range = start..start + line.len();
+                    start = range.end + 1;
+                    range
+                })
+                .collect();
+        }
+
+        fn hit_test_point(&self, point: Point) -> HitTestPoint {
+            let row = (point.y.max(0.0) as usize).min(self.lines.len() - 1);
+            let line = &self.lines[row];
+            let column = (point.x.round().max(0.0) as usize).min(line.len());
+            HitTestPoint::new(line.start + column, true)
+        }
+
+        fn line_range(&self, index: usize, _: Affinity) -> Range<usize> {
+            let line = self.lines.iter().find(|line| index <= line.end).unwrap();
+            line.clone()
+        }
+
+        fn bounding_box(&self) -> Option<Rect> {
+            Some(Rect::new(0.0, 0.0, 80.0, 2.0))
+        }
+
+        fn slice_bounding_box(&self, range: Range<usize>) -> Option<Rect> {
+            let row = self.lines.iter().position(|line| range.start <= line.end)?;
+            let start = self.lines[row].start;
+            let x = |index: usize| (index - start) as f64;
+            let y = row as f64;
+            Some(Rect::new(x(range.start), y, x(range.end), y + 1.0))
+        }
+    }
+
+    fn buffer_at(text: &str, caret: usize) -> TextBuffer<Grid> {
+        let mut buffer = TextBuffer::new(text, Grid::default());
+        buffer.set_selection(Selection::caret(caret));
+        buffer
+    }
+
+    #[test]
+    fn movement() {
+        let mut buffer = buffer_at("re\u{301}sume\u{301} of\nthe day", 0);
+        buffer.apply_action(Action::Move(Movement::Grapheme(Direction::Right)));
+        buffer.apply_action(Action::Move(Movement::Grapheme(Direction::Right)));
+        assert_eq!(buffer.selection(), Selection::caret(4));
+        buffer.apply_action(Action::Move(Movement::Word(Direction::Downstream)));
+        assert_eq!(buffer.selection(), Selection::caret(10));
+        buffer.apply_action(Action::Move(Movement::Word(Direction::Downstream)));
+        assert_eq!(buffer.selection(), Selection::caret(13));
+        buffer.apply_action(Action::MoveSelecting(Movement::Word(Direction::Upstream)));
+        assert_eq!(buffer.selection(), Selection::new(13, 11));
+        buffer.apply_action(Action::Move(Movement::ParagraphStart));
+        assert_eq!(buffer.selection(), Selection::caret(0));
+        buffer.set_selection(Selection::caret(2));
+        assert_eq!(buffer.selection(), Selection::caret(4));
+
+        // Vertical movement keeps the column it started from, even through shorter lines
+        let mut buffer = buffer_at("a long line\nshort\nanother line", 9);
+        buffer.apply_action(Action::Move(Movement::Vertical(VerticalMovement::LineDown)));
+        assert_eq!(buffer.selection().active, 17);
+        buffer.apply_action(Action::Move(Movement::Vertical(VerticalMovement::LineDown)));
+        assert_eq!(buffer.selection().active, 27);
+        buffer.apply_action(Action::Move(Movement::Vertical(VerticalMovement::LineDown)));
+        assert_eq!(buffer.selection().active, 30);
+        buffer.apply_action(Action::Move(Movement::Vertical(VerticalMovement::PageUp)));
+        assert_eq!(buffer.selection().active, 9);
+        buffer.apply_action(Action::Move(Movement::Vertical(VerticalMovement::PageUp)));
+        assert_eq!(buffer.selection().active, 0);
+    }
+
+    #[test]
+    fn editing_and_undo() {
+        let mut buffer = buffer_at("", 0);
+        for c in ["h", "i", " ", "y", "o", "u"] {
+            buffer.insert(c);
+        }
+        buffer.apply_action(Action::Delete(Movement::Grapheme(Direction::Upstream)));
+        assert_eq!(buffer.text(), "hi yo");
+        buffer.apply_action(Action::Undo);
+        assert_eq!(buffer.text(), "hi you");
+        buffer.apply_action(Action::Undo);
+        assert_eq!(buffer.text(), "hi ");
+        buffer.apply_action(Action::Redo);
+        assert_eq!(buffer.text(), "hi you");
+        assert_eq!(buffer.selection(), Selection::caret(6));
+
+        buffer.apply_action(Action::MoveSelecting(Movement::ParagraphStart));
+        buffer.apply_action(Action::TitlecaseSelection);
+        assert_eq!(buffer.text(), "Hi You");
+        assert_eq!(buffer.selection(), Selection::new(6, 0));
+
+        // An input method replacing its composition is a single edit
+        buffer.set_selection(Selection::caret(2));
+        buffer.replace_range(2..2, "k");
+        buffer.set_composition_range(Some(2..3));
+        buffer.set_preedit_attributes(PreeditAttributes::default());
+        assert_eq!(buffer.composition_range(), Some(2..3));
+        buffer.replace_range(2..3, "\u{304b}");
+        assert_eq!(buffer.composition_range(), None);
+        assert_eq!(buffer.preedit_attributes(), None);
+        assert_eq!(buffer.selection(), Selection::caret(5));
+        assert_eq!(buffer.utf16_to_utf8(0..3), 5);
+        buffer.apply_action(Action::Undo);
+        assert_eq!(buffer.text(), "Hi You");
+    }
+
+    #[test]
+    fn mark_and_kill_ring() {
+        let kill_ring = Rc::new(RefCell::new(KillRing::default()));
+        let mut buffer = buffer_at("one two\nthree", 0);
+        buffer.set_kill_ring(Some(kill_ring.clone()));
+        buffer.apply_action(Action::Kill(Movement::ParagraphEnd));
+        buffer.apply_action(Action::Kill(Movement::ParagraphEnd));
+        assert_eq!(buffer.text(), "\nthree");
+        assert_eq!(kill_ring.borrow().latest(), Some("one two"));
+
+        buffer.apply_action(Action::Move(Movement::Vertical(
+            VerticalMovement::DocumentEnd,
+        )));
+        buffer.apply_action(Action::SetMark);
+        buffer.apply_action(Action::Move(Movement::Word(Direction::Upstream)));
+        buffer.apply_action(Action::Yank);
+        assert_eq!(buffer.text(), "\none twothree");
+        assert_eq!(buffer.mark(), Some(13));
+        buffer.apply_action(Action::SwapWithMark);
+        assert_eq!(buffer.selection(), Selection::caret(13));
+        buffer.apply_action(Action::DeleteToMark);
+        assert_eq!(buffer.text(), "\none two");
+        assert_eq!(kill_ring.borrow().latest(), Some("three"));
+    }
+}
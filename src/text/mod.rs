@@ -96,17 +96,25 @@
 //!
 //! ## Supported Platforms
 //!
-//! Currently, `glazier` text input is fully implemented on macOS. Our goal
-//! is to have full support for all glazier targets, but for now,
-//! `InputHandler` calls are simulated from keypresses on other platforms, which
-//! doesn't allow for IME input, dead keys, etc.
-
+//! Currently, `glazier` text input is fully implemented on macOS. On X11, IME
+//! input goes through IBus (or any input method implementing its D-Bus
+//! protocol, such as fcitx5) when one is running. Our goal is to have full
+//! support for all glazier targets, but for now, `InputHandler` calls are
+//! simulated from keypresses on other platforms (and on X11 without an input
+//! method), which doesn't allow for IME input, dead keys, etc.
+
+use crate::application::Application;
+use crate::key_bindings::KeyBindings;
 use crate::keyboard::{KbKey, KeyEvent};
 use crate::kurbo::{Point, Rect};
 use crate::window::{TextFieldToken, WinHandler};
 use std::borrow::Cow;
 use std::ops::Range;
 
+mod buffer;
+
+pub use buffer::{TextBuffer, TextLayout};
+
 /// An event representing an application-initiated change in [`InputHandler`]
 /// state.
 ///
@@ -130,6 +138,135 @@ pub enum Event {
     Reset,
 }
 
+/// What kind of text a text field is for.
+///
+/// On-screen keyboards and input methods use this to pick a suitable layout,
+/// such as a numeric keypad for [`InputPurpose::Number`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum InputPurpose {
+    /// Any text.
+    #[default]
+    Normal,
+    /// A password, which is hidden while typing and never shown to input methods.
+    Password,
+    /// An email address.
+    Email,
+    /// A number.
+    Number,
+    /// A phone number.
+    Phone,
+    /// A URL.
+    Url,
+}
+
+/// Hints about how the platform should help with entering text into a text field.
+///
+/// Hints can be combined with `|`. The platform may ignore any of them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct InputHints(u32);
+
+impl InputHints {
+    /// No hints: the platform's usual assistance is wanted.
+    pub const NONE: InputHints = InputHints(0);
+    /// Don't suggest completions or corrections, e.g. for code or user names.
+    pub const NO_AUTOCORRECT: InputHints = InputHints(1);
+    /// Don't capitalize the start of sentences.
+    pub const NO_AUTO_CAPITALIZE: InputHints = InputHints(1 << 1);
+    /// The field accepts more than one line of text.
+    pub const MULTILINE: InputHints = InputHints(1 << 2);
+    /// The text is sensitive, such as a one-time code, and should not be
+    /// remembered or shown to input methods.
+    pub const SENSITIVE: InputHints = InputHints(1 << 3);
+
+    /// Whether all of the hints in `other` are also in `self`.
+    pub fn contains(self, other: InputHints) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for InputHints {
+    type Output = InputHints;
+
+    fn bitor(self, rhs: InputHints) -> InputHints {
+        InputHints(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for InputHints {
+    fn bitor_assign(&mut self, rhs: InputHints) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// The properties of a text field, set with [`WindowHandle::set_text_field_properties`].
+///
+/// [`WindowHandle::set_text_field_properties`]: crate::WindowHandle::set_text_field_properties
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct InputProperties {
+    /// What kind of text the field is for.
+    pub purpose: InputPurpose,
+    /// How the platform should help with entering text.
+    pub hints: InputHints,
+}
+
+impl InputProperties {
+    /// Properties for a field with the given purpose, and no hints.
+    pub fn new(purpose: InputPurpose) -> Self {
+        InputProperties {
+            purpose,
+            hints: InputHints::NONE,
+        }
+    }
+
+    /// These properties, with `hints` added.
+    pub fn with_hints(mut self, hints: InputHints) -> Self {
+        self.hints |= hints;
+        self
+    }
+
+    /// Whether the field's contents must be kept from input methods, because
+    /// it is a password or marked [`InputHints::SENSITIVE`].
+    pub fn is_secure(&self) -> bool {
+        self.purpose == InputPurpose::Password || self.hints.contains(InputHints::SENSITIVE)
+    }
+}
+
+/// How the composition (or preedit) text should be drawn, as given by the input method.
+///
+/// See [`InputHandler::set_preedit_attributes`]. All ranges and offsets are in UTF-8 code units,
+/// relative to the start of the composition range.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PreeditAttributes {
+    /// Where the input method's cursor is within the composition, or `None`
+    /// if it should be hidden.
+    pub cursor: Option<usize>,
+    /// The styled parts of the composition, in order. Parts of the composition
+    /// which aren't in a segment are drawn without any styling.
+    pub segments: Vec<PreeditSegment>,
+}
+
+/// A part of the composition, and how it should be drawn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreeditSegment {
+    /// The range of the segment, relative to the start of the composition.
+    pub range: Range<usize>,
+    /// How the segment should be drawn.
+    pub style: PreeditStyle,
+}
+
+/// The style of a [`PreeditSegment`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PreeditStyle {
+    /// Text which is still being entered, or has been converted. Usually drawn
+    /// with a thin underline.
+    Underline,
+    /// Text which the input method has selected, such as the clause being
+    /// converted. Usually drawn with the selection's highlight.
+    Highlight,
+}
+
 /// A range of selected text, or a caret.
 ///
 /// A caret is the blinking vertical bar where text is to be inserted. We
@@ -331,6 +468,21 @@ pub trait InputHandler {
     /// Requires a mutable lock.
     fn set_composition_range(&mut self, range: Option<Range<usize>>);
 
+    /// Set how the composition should be drawn.
+    ///
+    /// The platform calls this after setting the composition range, with the
+    /// input method's cursor and the styles of the parts of the composition,
+    /// such as the clause being converted by a Japanese input method. The
+    /// attributes only describe the current composition, and should be
+    /// forgotten when the composition range is set to `None` or the text is
+    /// changed with `replace_range`.
+    ///
+    /// The default implementation ignores the attributes, so that the whole
+    /// composition is drawn the same way.
+    ///
+    /// Requires a mutable lock.
+    fn set_preedit_attributes(&mut self, _attributes: PreeditAttributes) {}
+
     /// Check if the provided index is the first byte of a UTF-8 code point
     /// sequence, or is the end of the document.
     ///
@@ -593,137 +745,33 @@ pub(crate) fn simulate_input<H: WinHandler + ?Sized>(
     change_occured
 }
 
-/// Simulate the effect of a single keypress on the
+/// Simulate the effect of a single keypress on the text field of `input_handler`.
+///
+/// Key presses which are bound to an [`Action`] in the application's [`KeyBindings`] perform
+/// that action, and other characters are inserted.
 #[allow(dead_code)]
 pub(crate) fn simulate_single_input(
     event: &KeyEvent,
     input_handler: &mut dyn InputHandler,
 ) -> bool {
+    let action = match Application::try_global() {
+        Some(app) => app.key_binding(event),
+        None => KeyBindings::default().action(event),
+    };
+    if let Some(action) = action {
+        input_handler.handle_action(action);
+        return true;
+    }
     match &event.key {
         KbKey::Character(c) if !event.mods.ctrl() && !event.mods.meta() && !event.mods.alt() => {
             let selection = input_handler.selection();
             input_handler.replace_range(selection.range(), c);
             let new_caret_index = selection.min() + c.len();
             input_handler.set_selection(Selection::caret(new_caret_index));
+            true
         }
-        KbKey::ArrowLeft => {
-            let movement = if event.mods.ctrl() {
-                Movement::Word(Direction::Left)
-            } else {
-                Movement::Grapheme(Direction::Left)
-            };
-            if event.mods.shift() {
-                input_handler.handle_action(Action::MoveSelecting(movement));
-            } else {
-                input_handler.handle_action(Action::Move(movement));
-            }
-        }
-        KbKey::ArrowRight => {
-            let movement = if event.mods.ctrl() {
-                Movement::Word(Direction::Right)
-            } else {
-                Movement::Grapheme(Direction::Right)
-            };
-            if event.mods.shift() {
-                input_handler.handle_action(Action::MoveSelecting(movement));
-            } else {
-                input_handler.handle_action(Action::Move(movement));
-            }
-        }
-        KbKey::ArrowUp => {
-            let movement = Movement::Vertical(VerticalMovement::LineUp);
-            if event.mods.shift() {
-                input_handler.handle_action(Action::MoveSelecting(movement));
-            } else {
-                input_handler.handle_action(Action::Move(movement));
-            }
-        }
-        KbKey::ArrowDown => {
-            let movement = Movement::Vertical(VerticalMovement::LineDown);
-            if event.mods.shift() {
-                input_handler.handle_action(Action::MoveSelecting(movement));
-            } else {
-                input_handler.handle_action(Action::Move(movement));
-            }
-        }
-        KbKey::Backspace => {
-            let movement = if event.mods.ctrl() {
-                Movement::Word(Direction::Upstream)
-            } else {
-                Movement::Grapheme(Direction::Upstream)
-            };
-            input_handler.handle_action(Action::Delete(movement));
-        }
-        KbKey::Delete => {
-            let movement = if event.mods.ctrl() {
-                Movement::Word(Direction::Downstream)
-            } else {
-                Movement::Grapheme(Direction::Downstream)
-            };
-            input_handler.handle_action(Action::Delete(movement));
-        }
-        KbKey::Enter => {
-            // I'm sorry windows, you'll get IME soon.
-            input_handler.handle_action(Action::InsertNewLine {
-                ignore_hotkey: false,
-                newline_type: '\n',
-            });
-        }
-        KbKey::Tab => {
-            let action = if event.mods.shift() {
-                Action::InsertBacktab
-            } else {
-                Action::InsertTab {
-                    ignore_hotkey: false,
-                }
-            };
-            input_handler.handle_action(action);
-        }
-        KbKey::Home => {
-            let movement = if event.mods.ctrl() {
-                Movement::Vertical(VerticalMovement::DocumentStart)
-            } else {
-                Movement::Line(Direction::Upstream)
-            };
-            if event.mods.shift() {
-                input_handler.handle_action(Action::MoveSelecting(movement));
-            } else {
-                input_handler.handle_action(Action::Move(movement));
-            }
-        }
-        KbKey::End => {
-            let movement = if event.mods.ctrl() {
-                Movement::Vertical(VerticalMovement::DocumentEnd)
-            } else {
-                Movement::Line(Direction::Downstream)
-            };
-            if event.mods.shift() {
-                input_handler.handle_action(Action::MoveSelecting(movement));
-            } else {
-                input_handler.handle_action(Action::Move(movement));
-            }
-        }
-        KbKey::PageUp => {
-            let movement = Movement::Vertical(VerticalMovement::PageUp);
-            if event.mods.shift() {
-                input_handler.handle_action(Action::MoveSelecting(movement));
-            } else {
-                input_handler.handle_action(Action::Move(movement));
-            }
-        }
-        KbKey::PageDown => {
-            let movement = Movement::Vertical(VerticalMovement::PageDown);
-            if event.mods.shift() {
-                input_handler.handle_action(Action::MoveSelecting(movement));
-            } else {
-                input_handler.handle_action(Action::Move(movement));
-            }
-        }
-        _ => {
-            return false;
-        }
+        _ => false,
     }
-    true
 }
 
 /// Indicates a movement that transforms a particular text position in a
@@ -999,6 +1047,54 @@ pub enum Action {
     ///
     /// Triggered on most operating systems with escape.
     Cancel,
+
+    /// Undoes the latest edit.
+    Undo,
+
+    /// Redoes the latest edit which was undone.
+    Redo,
+
+    /// Copies the selection to the clipboard, then deletes it.
+    Cut,
+
+    /// Copies the selection to the clipboard.
+    Copy,
+
+    /// Replaces the selection with the contents of the clipboard.
+    Paste,
+
+    /// Replaces the selection with the contents of the clipboard, leaving out any formatting
+    /// so that the pasted text matches its surroundings.
+    PasteAsPlainText,
+
+    /// Deletes some text like [`Action::Delete`], keeping it to be inserted again by
+    /// [`Action::Yank`].
+    ///
+    /// See [`KillRing`] for a helper which implements this. On macOS, control-k kills to the
+    /// end of the paragraph, but arrives as [`Action::Delete`] with the text added to
+    /// [`KillRing::shared`].
+    ///
+    /// [`KillRing`]: crate::KillRing
+    Kill(Movement),
+
+    /// Inserts the text which was most recently killed, replacing the selection.
+    Yank,
+
+    /// Remembers the position of the selection's active edge as the mark.
+    SetMark,
+
+    /// Extends the selection from the selection to the mark.
+    ///
+    /// If there is no mark, the selection is left unchanged.
+    SelectToMark,
+
+    /// Kills the text between the selection and the mark, like [`Action::Kill`].
+    ///
+    /// If there is no mark, the selection is killed.
+    DeleteToMark,
+
+    /// Moves the caret to the mark, and the mark to where the caret was.
+    SwapWithMark,
 }
 
 /// Result of hit testing a point in a block of text.
@@ -1023,3 +1119,19 @@ impl HitTestPoint {
         Self { idx, is_inside }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn secure_properties() {
+        assert!(!InputProperties::default().is_secure());
+        assert!(!InputProperties::new(InputPurpose::Email).is_secure());
+        assert!(InputProperties::new(InputPurpose::Password).is_secure());
+        let sensitive = InputProperties::new(InputPurpose::Number)
+            .with_hints(InputHints::NO_AUTOCORRECT | InputHints::SENSITIVE);
+        assert!(sensitive.is_secure());
+        assert!(sensitive.with_hints(InputHints::MULTILINE).is_secure());
+    }
+}
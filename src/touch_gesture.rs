@@ -0,0 +1,656 @@
+//! Recognizing gestures such as taps, pans and pinches from touch events.
+
+use std::time::Duration;
+
+use instant::Instant;
+
+use crate::gesture::GesturePhase;
+use crate::kurbo::{Point, Vec2};
+use crate::pointer::{Angle, PointerEvent, PointerId};
+
+/// The thresholds used by a [`TouchGestureRecognizer`].
+///
+/// Distances are in display points.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TouchGestureConfig {
+    /// How far a touch can move and still be a tap or a long press, rather than a pan.
+    pub tap_slop: f64,
+    /// How long a touch can be held and still be a tap.
+    pub tap_timeout: Duration,
+    /// The longest time between the end of one tap and the start of the next, for them to be
+    /// counted as a double tap.
+    pub double_tap_timeout: Duration,
+    /// How far apart two taps can be, for them to be counted as a double tap.
+    pub double_tap_slop: f64,
+    /// How long a touch must be held without moving to be a long press.
+    pub long_press_timeout: Duration,
+    /// How much the distance between two touches must change for them to start a pinch.
+    pub pinch_threshold: f64,
+    /// How far two touches must turn around each other to start a rotation.
+    pub rotation_threshold: Angle,
+}
+
+impl Default for TouchGestureConfig {
+    fn default() -> Self {
+        TouchGestureConfig {
+            tap_slop: 10.0,
+            tap_timeout: Duration::from_millis(300),
+            double_tap_timeout: Duration::from_millis(300),
+            double_tap_slop: 40.0,
+            long_press_timeout: Duration::from_millis(500),
+            pinch_threshold: 10.0,
+            rotation_threshold: Angle::degrees(10.0),
+        }
+    }
+}
+
+/// A gesture recognized by a [`TouchGestureRecognizer`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum TouchGesture {
+    /// A touch which was lifted soon after it went down, without moving.
+    Tap {
+        pos: Point,
+        /// The number of taps in quick succession at about the same place, so `2` for the
+        /// second tap of a double tap.
+        count: u32,
+    },
+    /// A touch which was held down without moving.
+    LongPress { pos: Point },
+    /// One or more touches moving together.
+    Pan {
+        phase: GesturePhase,
+        /// The center of the touches.
+        pos: Point,
+        /// The movement since the previous event of this gesture, or since the touches went
+        /// down for the first event.
+        delta: Vec2,
+        finger_count: u32,
+    },
+    /// Two or more touches moving towards or away from each other.
+    Pinch {
+        phase: GesturePhase,
+        /// The center of the touches.
+        center: Point,
+        /// The distance between the touches, relative to when they went down.
+        scale: f64,
+    },
+    /// Two or more touches turning around their center.
+    Rotate {
+        phase: GesturePhase,
+        /// The center of the touches.
+        center: Point,
+        /// The clockwise rotation since the previous event of this gesture, or since the
+        /// touches went down for the first event.
+        rotation: Angle,
+    },
+}
+
+/// Turns sequences of touch events into [`TouchGesture`]s.
+///
+/// The recognizer is fed the pointer events of a window, usually only those where
+/// [`PointerEvent::is_touch`] is true, and keeps track of each pointer by its [`PointerId`].
+/// It works the same on every platform.
+///
+/// Long presses happen when a touch has been held for long enough, rather than in response to
+/// an event, so after each event the application should request a timer for
+/// [`next_deadline`](Self::next_deadline), and call [`timer`](Self::timer) when it fires.
+#[derive(Debug, Clone, Default)]
+pub struct TouchGestureRecognizer {
+    config: TouchGestureConfig,
+    /// The touches which are down, in the order they went down.
+    touches: Vec<(PointerId, Touch)>,
+    state: State,
+    /// When and where the latest tap ended, and how many taps led up to it.
+    last_tap: Option<(Instant, Point, u32)>,
+}
+
+#[derive(Debug, Clone)]
+struct Touch {
+    start_pos: Point,
+    start_time: Instant,
+    pos: Point,
+}
+
+#[derive(Debug, Clone, Default)]
+enum State {
+    /// No touches are down.
+    #[default]
+    Idle,
+    /// A single touch is down, which could still become a tap, a long press or a pan.
+    Possible { long_press_deadline: Instant },
+    /// A single touch has been held as a long press.
+    LongPressed,
+    /// A single touch is panning.
+    Panning { last_pos: Point },
+    /// Several touches are down.
+    Multi(Multi),
+    /// The touches are no longer part of a gesture, for example because they were used for a
+    /// pinch and then some were lifted, so we wait for all of them to be lifted.
+    Done,
+}
+
+#[derive(Debug, Clone)]
+struct Multi {
+    start_center: Point,
+    last_center: Point,
+    /// The distance between the first two touches which would give a scale of `1.0`.
+    base_span: f64,
+    scale: f64,
+    start_angle: f64,
+    last_angle: f64,
+    panning: bool,
+    pinching: bool,
+    rotating: bool,
+}
+
+impl TouchGestureRecognizer {
+    pub fn new(config: TouchGestureConfig) -> Self {
+        TouchGestureRecognizer {
+            config,
+            ..Default::default()
+        }
+    }
+
+    pub fn config(&self) -> &TouchGestureConfig {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: TouchGestureConfig) {
+        self.config = config;
+    }
+
+    /// Handle a pointer going down.
+    pub fn pointer_down(&mut self, event: &PointerEvent) -> Vec<TouchGesture> {
+        let time = event_time(event);
+        let mut gestures = Vec::new();
+        self.touches.retain(|(id, _)| *id != event.pointer_id);
+        self.touches.push((
+            event.pointer_id,
+            Touch {
+                start_pos: event.pos,
+                start_time: time,
+                pos: event.pos,
+            },
+        ));
+        match self.state {
+            State::Idle => {
+                self.state = State::Possible {
+                    long_press_deadline: time + self.config.long_press_timeout,
+                };
+            }
+            State::Possible { .. } | State::LongPressed => {
+                self.state = State::Multi(self.start_multi());
+            }
+            State::Panning { last_pos } => {
+                gestures.push(pan_end(GesturePhase::Ended, last_pos));
+                self.state = State::Multi(self.start_multi());
+            }
+            State::Multi(_) => self.rebase_multi(),
+            State::Done => {}
+        }
+        gestures
+    }
+
+    /// Handle a pointer moving.
+    pub fn pointer_move(&mut self, event: &PointerEvent) -> Vec<TouchGesture> {
+        let Some(touch) = self.touch_mut(event.pointer_id) else {
+            return Vec::new();
+        };
+        touch.pos = event.pos;
+        let start_pos = touch.start_pos;
+        let mut gestures = Vec::new();
+        match &mut self.state {
+            State::Possible { .. } if start_pos.distance(event.pos) > self.config.tap_slop => {
+                gestures.push(TouchGesture::Pan {
+                    phase: GesturePhase::Began,
+                    pos: event.pos,
+                    delta: event.pos - start_pos,
+                    finger_count: 1,
+                });
+                self.state = State::Panning {
+                    last_pos: event.pos,
+                };
+            }
+            State::Panning { last_pos } => {
+                gestures.push(TouchGesture::Pan {
+                    phase: GesturePhase::Changed,
+                    pos: event.pos,
+                    delta: event.pos - *last_pos,
+                    finger_count: 1,
+                });
+                *last_pos = event.pos;
+            }
+            State::Multi(_) => self.update_multi(&mut gestures),
+            _ => {}
+        }
+        gestures
+    }
+
+    /// Handle a pointer going up.
+    pub fn pointer_up(&mut self, event: &PointerEvent) -> Vec<TouchGesture> {
+        let Some(idx) = self
+            .touches
+            .iter()
+            .position(|(id, _)| *id == event.pointer_id)
+        else {
+            return Vec::new();
+        };
+        let time = event_time(event);
+        let (_, touch) = self.touches.remove(idx);
+        let mut gestures = Vec::new();
+        match self.state {
+            State::Possible { .. }
+                if time.saturating_duration_since(touch.start_time) <= self.config.tap_timeout =>
+            {
+                gestures.push(self.tap(event.pos, touch.start_time, time));
+            }
+            State::Panning { last_pos } => gestures.push(pan_end(GesturePhase::Ended, last_pos)),
+            State::Multi(_) if self.touches.len() >= 2 => self.rebase_multi(),
+            State::Multi(_) => {
+                self.end_multi(GesturePhase::Ended, &mut gestures);
+                self.state = State::Done;
+            }
+            _ => {}
+        }
+        if self.touches.is_empty() {
+            self.state = State::Idle;
+        }
+        gestures
+    }
+
+    /// Handle the platform taking the pointers away, which cancels any gesture in progress.
+    pub fn pointer_cancel(&mut self, _event: &PointerEvent) -> Vec<TouchGesture> {
+        let mut gestures = Vec::new();
+        match self.state {
+            State::Panning { last_pos } => {
+                gestures.push(pan_end(GesturePhase::Cancelled, last_pos));
+            }
+            State::Multi(_) => self.end_multi(GesturePhase::Cancelled, &mut gestures),
+            _ => {}
+        }
+        self.touches.clear();
+        self.state = State::Idle;
+        gestures
+    }
+
+    /// When [`timer`](Self::timer) should next be called, if a gesture is waiting for time to
+    /// pass.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        match self.state {
+            State::Possible {
+                long_press_deadline,
+            } => Some(long_press_deadline),
+            _ => None,
+        }
+    }
+
+    /// Recognize the gestures which happen after time passes, such as long presses.
+    pub fn timer(&mut self, now: Instant) -> Vec<TouchGesture> {
+        match self.state {
+            State::Possible {
+                long_press_deadline,
+            } if now >= long_press_deadline => {
+                self.state = State::LongPressed;
+                let pos = self.touches.first().map_or(Point::ZERO, |(_, t)| t.pos);
+                vec![TouchGesture::LongPress { pos }]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn touch_mut(&mut self, id: PointerId) -> Option<&mut Touch> {
+        self.touches
+            .iter_mut()
+            .find(|(touch_id, _)| *touch_id == id)
+            .map(|(_, touch)| touch)
+    }
+
+    fn tap(&mut self, pos: Point, start_time: Instant, time: Instant) -> TouchGesture {
+        let count = match self.last_tap {
+            Some((last_time, last_pos, count))
+                if start_time.saturating_duration_since(last_time)
+                    <= self.config.double_tap_timeout
+                    && last_pos.distance(pos) <= self.config.double_tap_slop =>
+            {
+                count + 1
+            }
+            _ => 1,
+        };
+        self.last_tap = Some((time, pos, count));
+        TouchGesture::Tap { pos, count }
+    }
+
+    /// The center of the touches.
+    fn center(&self) -> Point {
+        if self.touches.is_empty() {
+            return Point::ZERO;
+        }
+        let sum = self
+            .touches
+            .iter()
+            .fold(Vec2::ZERO, |sum, (_, touch)| sum + touch.pos.to_vec2());
+        (sum / self.touches.len() as f64).to_point()
+    }
+
+    /// The distance between the first two touches, and the angle of the line between them.
+    fn span_and_angle(&self) -> (f64, f64) {
+        match &self.touches[..] {
+            [(_, first), (_, second), ..] => {
+                let line = second.pos - first.pos;
+                (line.hypot(), line.atan2())
+            }
+            _ => (0.0, 0.0),
+        }
+    }
+
+    fn start_multi(&self) -> Multi {
+        let center = self.center();
+        let (span, angle) = self.span_and_angle();
+        Multi {
+            start_center: center,
+            last_center: center,
+            base_span: span,
+            scale: 1.0,
+            start_angle: angle,
+            last_angle: angle,
+            panning: false,
+            pinching: false,
+            rotating: false,
+        }
+    }
+
+    /// Carry on the gestures of several touches after a touch was added or removed, which
+    /// moves their center and changes which touches are measured.
+    fn rebase_multi(&mut self) {
+        let center = self.center();
+        let (span, angle) = self.span_and_angle();
+        if let State::Multi(multi) = &mut self.state {
+            multi.start_center += center - multi.last_center;
+            multi.last_center = center;
+            multi.base_span = span / multi.scale;
+            multi.start_angle += angle - multi.last_angle;
+            multi.last_angle = angle;
+        }
+    }
+
+    fn update_multi(&mut self, gestures: &mut Vec<TouchGesture>) {
+        let center = self.center();
+        let (span, angle) = self.span_and_angle();
+        let finger_count = self.touches.len() as u32;
+        let State::Multi(multi) = &mut self.state else {
+            return;
+        };
+        let config = &self.config;
+
+        if multi.panning {
+            gestures.push(TouchGesture::Pan {
+                phase: GesturePhase::Changed,
+                pos: center,
+                delta: center - multi.last_center,
+                finger_count,
+            });
+        } else if center.distance(multi.start_center) > config.tap_slop {
+            multi.panning = true;
+            gestures.push(TouchGesture::Pan {
+                phase: GesturePhase::Began,
+                pos: center,
+                delta: center - multi.start_center,
+                finger_count,
+            });
+        }
+        multi.last_center = center;
+
+        if multi.base_span > 0.0 {
+            multi.scale = span / multi.base_span;
+        }
+        if multi.pinching {
+            gestures.push(TouchGesture::Pinch {
+                phase: GesturePhase::Changed,
+                center,
+                scale: multi.scale,
+            });
+        } else if (span - multi.base_span).abs() > config.pinch_threshold {
+            multi.pinching = true;
+            gestures.push(TouchGesture::Pinch {
+                phase: GesturePhase::Began,
+                center,
+                scale: multi.scale,
+            });
+        }
+
+        if multi.rotating {
+            gestures.push(TouchGesture::Rotate {
+                phase: GesturePhase::Changed,
+                center,
+                rotation: Angle::radians(angle_between(multi.last_angle, angle)),
+            });
+        } else {
+            let rotation = angle_between(multi.start_angle, angle);
+            if rotation.abs() > config.rotation_threshold.to_radians() {
+                multi.rotating = true;
+                gestures.push(TouchGesture::Rotate {
+                    phase: GesturePhase::Began,
+                    center,
+                    rotation: Angle::radians(rotation),
+                });
+            }
+        }
+        multi.last_angle = angle;
+    }
+
+    fn end_multi(&self, phase: GesturePhase, gestures: &mut Vec<TouchGesture>) {
+        let State::Multi(multi) = &self.state else {
+            return;
+        };
+        let center = multi.last_center;
+        let finger_count = self.touches.len().max(1) as u32;
+        if multi.panning {
+            gestures.push(TouchGesture::Pan {
+                phase,
+                pos: center,
+                delta: Vec2::ZERO,
+                finger_count,
+            });
+        }
+        if multi.pinching {
+            gestures.push(TouchGesture::Pinch {
+                phase,
+                center,
+                scale: multi.scale,
+            });
+        }
+        if multi.rotating {
+            gestures.push(TouchGesture::Rotate {
+                phase,
+                center,
+                rotation: Angle::radians(0.0),
+            });
+        }
+    }
+}
+
+/// The end of a pan by a single touch at `pos`.
+fn pan_end(phase: GesturePhase, pos: Point) -> TouchGesture {
+    TouchGesture::Pan {
+        phase,
+        pos,
+        delta: Vec2::ZERO,
+        finger_count: 1,
+    }
+}
+
+fn event_time(event: &PointerEvent) -> Instant {
+    event.time.unwrap_or_else(Instant::now)
+}
+
+/// The clockwise angle from `from` to `to`, in radians in the range `[-π, π)`.
+fn angle_between(from: f64, to: f64) -> f64 {
+    use std::f64::consts::{PI, TAU};
+    (to - from + PI).rem_euclid(TAU) - PI
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Touches {
+        recognizer: TouchGestureRecognizer,
+        start: Instant,
+    }
+
+    impl Touches {
+        fn new() -> Self {
+            Touches {
+                recognizer: TouchGestureRecognizer::default(),
+                start: Instant::now(),
+            }
+        }
+
+        fn event(&self, id: u64, x: f64, y: f64, ms: u64) -> PointerEvent {
+            PointerEvent {
+                pointer_id: PointerId(id),
+                pos: Point::new(x, y),
+                time: Some(self.start + Duration::from_millis(ms)),
+                ..PointerEvent::default()
+            }
+        }
+
+        fn down(&mut self, id: u64, x: f64, y: f64, ms: u64) -> Vec<TouchGesture> {
+            let event = self.event(id, x, y, ms);
+            self.recognizer.pointer_down(&event)
+        }
+
+        fn moved(&mut self, id: u64, x: f64, y: f64, ms: u64) -> Vec<TouchGesture> {
+            let event = self.event(id, x, y, ms);
+            self.recognizer.pointer_move(&event)
+        }
+
+        fn up(&mut self, id: u64, x: f64, y: f64, ms: u64) -> Vec<TouchGesture> {
+            let event = self.event(id, x, y, ms);
+            self.recognizer.pointer_up(&event)
+        }
+    }
+
+    #[test]
+    fn tap_and_double_tap() {
+        let mut touches = Touches::new();
+        assert!(touches.down(1, 10.0, 10.0, 0).is_empty());
+        assert!(touches.moved(1, 12.0, 10.0, 50).is_empty());
+        let pos = Point::new(12.0, 10.0);
+        assert_eq!(
+            touches.up(1, 12.0, 10.0, 100),
+            vec![TouchGesture::Tap { pos, count: 1 }]
+        );
+        touches.down(2, 12.0, 10.0, 200);
+        assert_eq!(
+            touches.up(2, 12.0, 10.0, 250),
+            vec![TouchGesture::Tap { pos, count: 2 }]
+        );
+        // Too late to be a triple tap
+        touches.down(3, 12.0, 10.0, 1000);
+        assert_eq!(
+            touches.up(3, 12.0, 10.0, 1050),
+            vec![TouchGesture::Tap { pos, count: 1 }]
+        );
+        // Held too long to be a tap
+        touches.down(4, 12.0, 10.0, 2000);
+        assert!(touches.up(4, 12.0, 10.0, 2400).is_empty());
+    }
+
+    #[test]
+    fn long_press() {
+        let mut touches = Touches::new();
+        touches.down(1, 10.0, 10.0, 0);
+        let deadline = touches.recognizer.next_deadline().unwrap();
+        assert_eq!(deadline, touches.start + Duration::from_millis(500));
+        assert!(touches
+            .recognizer
+            .timer(deadline - Duration::from_millis(1))
+            .is_empty());
+        assert_eq!(
+            touches.recognizer.timer(deadline),
+            vec![TouchGesture::LongPress {
+                pos: Point::new(10.0, 10.0)
+            }]
+        );
+        assert_eq!(touches.recognizer.next_deadline(), None);
+        assert!(touches.up(1, 10.0, 10.0, 600).is_empty());
+    }
+
+    #[test]
+    fn pan() {
+        let mut touches = Touches::new();
+        touches.down(1, 10.0, 10.0, 0);
+        assert_eq!(
+            touches.moved(1, 30.0, 10.0, 20),
+            vec![TouchGesture::Pan {
+                phase: GesturePhase::Began,
+                pos: Point::new(30.0, 10.0),
+                delta: Vec2::new(20.0, 0.0),
+                finger_count: 1,
+            }]
+        );
+        assert_eq!(
+            touches.moved(1, 30.0, 15.0, 40),
+            vec![TouchGesture::Pan {
+                phase: GesturePhase::Changed,
+                pos: Point::new(30.0, 15.0),
+                delta: Vec2::new(0.0, 5.0),
+                finger_count: 1,
+            }]
+        );
+        assert_eq!(touches.recognizer.next_deadline(), None);
+        assert_eq!(
+            touches.up(1, 30.0, 15.0, 60),
+            vec![TouchGesture::Pan {
+                phase: GesturePhase::Ended,
+                pos: Point::new(30.0, 15.0),
+                delta: Vec2::ZERO,
+                finger_count: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn pinch_and_rotate() {
+        let mut touches = Touches::new();
+        touches.down(1, 0.0, 0.0, 0);
+        touches.down(2, 100.0, 0.0, 10);
+        let gestures = touches.moved(2, 200.0, 0.0, 20);
+        let pinch = gestures.iter().find_map(|gesture| match gesture {
+            TouchGesture::Pinch { phase, scale, .. } => Some((*phase, *scale)),
+            _ => None,
+        });
+        assert_eq!(pinch, Some((GesturePhase::Began, 2.0)));
+
+        // Turning the second finger a quarter turn clockwise around the first
+        let gestures = touches.moved(2, 0.0, 200.0, 30);
+        let rotation = gestures.iter().find_map(|gesture| match gesture {
+            TouchGesture::Rotate {
+                phase: GesturePhase::Began,
+                rotation,
+                ..
+            } => Some(rotation.to_degrees()),
+            _ => None,
+        });
+        assert!((rotation.unwrap() - 90.0).abs() < 1e-9);
+
+        let ended = touches.up(1, 0.0, 0.0, 40);
+        assert!(ended.iter().all(|gesture| matches!(
+            gesture,
+            TouchGesture::Pan {
+                phase: GesturePhase::Ended,
+                ..
+            } | TouchGesture::Pinch {
+                phase: GesturePhase::Ended,
+                ..
+            } | TouchGesture::Rotate {
+                phase: GesturePhase::Ended,
+                ..
+            }
+        )));
+        // The remaining finger doesn't start a new gesture
+        assert!(touches.moved(2, 0.0, 300.0, 50).is_empty());
+        assert!(touches.up(2, 0.0, 300.0, 60).is_empty());
+    }
+}
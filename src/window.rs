@@ -18,19 +18,24 @@ use std::any::Any;
 use std::fmt;
 use std::time::Duration;
 
+use instant::Instant;
+
 use crate::application::Application;
 use crate::backend::window as backend;
+use crate::clipboard::ClipboardFormat;
 use crate::common_util::Counter;
 use crate::dialog::{FileDialogOptions, FileInfo};
+use crate::drag::{DragActions, DragImage, DragOutcome, DragToken};
 use crate::error::Error;
-use crate::keyboard::KeyEvent;
+use crate::gesture::GestureEvent;
+use crate::keyboard::{Code, KeyEvent, KeyboardLayouts, Modifiers};
 use crate::kurbo::{Insets, Point, Rect, Size};
 use crate::menu::Menu;
 use crate::mouse::{Cursor, CursorDesc};
 use crate::region::Region;
 use crate::scale::Scale;
-use crate::text::{Event, InputHandler};
-use crate::PointerEvent;
+use crate::text::{Event, InputHandler, InputProperties};
+use crate::{PointerButtons, PointerConstraint, PointerEvent, PointerId, RelativeMotionEvent};
 
 use raw_window_handle::{
     HasRawDisplayHandle, HasRawWindowHandle, RawDisplayHandle, RawWindowHandle,
@@ -182,6 +187,27 @@ pub enum WindowState {
     Restored,
 }
 
+/// Timing information about the frame being prepared, passed to
+/// [`WinHandler::prepare_paint`].
+///
+/// Backends fill in as much of this as the platform reports, so every field may be unknown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FrameTiming {
+    /// The time at which the frame is expected to appear on screen.
+    ///
+    /// Animations should be advanced to this time, rather than to the time at which
+    /// `prepare_paint` is called, to move smoothly.
+    pub target_presentation_time: Option<Instant>,
+    /// The refresh interval of the display showing the window.
+    pub refresh_interval: Option<Duration>,
+    /// The number of display refreshes which passed without a new frame since the previous
+    /// frame of a continuous animation, because painting took too long.
+    ///
+    /// This is `0` for the first frame after [`WindowHandle::request_anim_frame`] or an
+    /// invalidation, when no animation was running.
+    pub missed_frames: u32,
+}
+
 /// A handle to a platform window object.
 #[derive(Clone, PartialEq, Eq)]
 pub struct WindowHandle(pub(crate) backend::WindowHandle);
@@ -363,6 +389,16 @@ impl WindowHandle {
         self.0.set_focused_text_field(active_field);
     }
 
+    /// Set the properties of a text field, such as whether it holds a password.
+    ///
+    /// The platform passes these on to on-screen keyboards and input methods, and
+    /// keeps input methods away from fields which are
+    /// [secure](InputProperties::is_secure). Fields have the default properties
+    /// until this is called.
+    pub fn set_text_field_properties(&self, token: TextFieldToken, properties: InputProperties) {
+        self.0.set_text_field_properties(token, properties);
+    }
+
     /// Notify the platform that some text input state has changed, such as the
     /// selection, contents, etc.
     ///
@@ -421,6 +457,109 @@ impl WindowHandle {
         self.0.show_context_menu(menu.into_inner(), pos);
     }
 
+    /// Start a drag and drop operation from this window.
+    ///
+    /// `formats` are the representations of the dragged data which are offered to drop
+    /// targets; the data is only sent to a target once it asks for one of the formats.
+    /// `actions` are the actions which the target is allowed to perform, and `image` is
+    /// shown under the pointer for the duration of the drag.
+    ///
+    /// This should be called in response to a [`WinHandler::pointer_down`] or
+    /// [`WinHandler::pointer_move`] whilst a button is held, as platforms generally
+    /// require an active pointer grab to start a drag.
+    ///
+    /// When the operation ends, [`WinHandler::drag_finished`] is called with the returned
+    /// token, including if the drag could not be started. Returns `None` if the platform
+    /// doesn't support starting drags.
+    pub fn start_drag(
+        &self,
+        formats: &[ClipboardFormat],
+        actions: DragActions,
+        image: Option<DragImage>,
+    ) -> Option<DragToken> {
+        self.0.start_drag(formats, actions, image)
+    }
+
+    /// Keep sending the events of the pointer `pointer_id` to this window, even once it
+    /// leaves the window, until [`release_pointer_capture`] is called.
+    ///
+    /// This is like `setPointerCapture` in [Pointer Events], and is usually called from
+    /// [`WinHandler::pointer_down`] so that a drag can continue outside the window.
+    /// Platforms already capture a pointer while one of its buttons is held, and a touch
+    /// for as long as it is down; on Wayland, that implicit capture is the only one there is.
+    ///
+    /// [`release_pointer_capture`]: WindowHandle::release_pointer_capture
+    /// [Pointer Events]: https://w3c.github.io/pointerevents/#pointer-capture
+    pub fn capture_pointer(&self, pointer_id: PointerId) {
+        self.0.capture_pointer(pointer_id)
+    }
+
+    /// Stop capturing the pointer `pointer_id`, which [`capture_pointer`] started.
+    ///
+    /// [`capture_pointer`]: WindowHandle::capture_pointer
+    pub fn release_pointer_capture(&self, pointer_id: PointerId) {
+        self.0.release_pointer_capture(pointer_id)
+    }
+
+    /// Lock or confine the pointer while it is over this window.
+    ///
+    /// While there is a constraint, the pointer's movement is also reported to
+    /// [`WinHandler::relative_pointer_motion`], which is the only way to follow a locked
+    /// pointer. Platforms only apply the constraint while the window has focus, and may
+    /// refuse it altogether.
+    ///
+    /// Locking usually goes with hiding the cursor, through [`set_cursor_visible`].
+    ///
+    /// [`set_cursor_visible`]: WindowHandle::set_cursor_visible
+    pub fn set_pointer_constraint(&self, constraint: PointerConstraint) {
+        self.0.set_pointer_constraint(constraint)
+    }
+
+    /// Show or hide the cursor while it is over this window.
+    pub fn set_cursor_visible(&self, visible: bool) {
+        self.0.set_cursor_visible(visible)
+    }
+
+    /// The keyboard modifiers which are currently active.
+    ///
+    /// Unlike the modifiers of the latest [`KeyEvent`] or [`PointerEvent`], this is up to
+    /// date even when keys were pressed or released while the window didn't have focus, such
+    /// as in [`WinHandler::got_focus`] after switching windows with Alt-Tab.
+    pub fn modifiers(&self) -> Modifiers {
+        self.0.modifiers()
+    }
+
+    /// The physical keys which are currently held down.
+    ///
+    /// On Wayland, this is only known while the window has keyboard focus, and is empty
+    /// otherwise.
+    pub fn pressed_keys(&self) -> Vec<Code> {
+        self.0.pressed_keys()
+    }
+
+    /// The keyboard layouts which the user has configured, and which of them is active.
+    ///
+    /// [`WinHandler::keyboard_layout_changed`] is called when this changes. On Wayland,
+    /// the layouts are only known once the window has had keyboard focus.
+    pub fn keyboard_layouts(&self) -> KeyboardLayouts {
+        self.0.keyboard_layouts()
+    }
+
+    /// The buttons of the pointer which are currently held down.
+    ///
+    /// On Wayland, this is only known while the pointer is over the window.
+    pub fn pointer_buttons(&self) -> PointerButtons {
+        self.0.pointer_buttons()
+    }
+
+    /// The position of the pointer, in display points relative to the window.
+    ///
+    /// This is `None` if the position isn't known, such as when the pointer is on another
+    /// screen, or on Wayland when it isn't over the window.
+    pub fn pointer_position(&self) -> Option<Point> {
+        self.0.pointer_position()
+    }
+
     /// Get a handle that can be used to schedule an idle task.
     pub fn get_idle_handle(&self) -> Option<IdleHandle> {
         self.0.get_idle_handle().map(IdleHandle)
@@ -596,7 +735,10 @@ pub trait WinHandler {
     /// any regions that need to be repainted on the next call to `paint`, the handler should
     /// invalidate those regions by calling [`WindowHandle::invalidate_rect`] or
     /// [`WindowHandle::invalidate`].
-    fn prepare_paint(&mut self);
+    ///
+    /// `timing` describes when the frame will be shown, which animations should use to decide
+    /// how far to advance.
+    fn prepare_paint(&mut self, timing: FrameTiming);
 
     /// Request the handler to paint the window contents.  `invalid` is the region in [display
     /// points](crate::Scale) that needs to be repainted; painting outside the invalid region will
@@ -649,6 +791,17 @@ pub trait WinHandler {
     #[allow(unused_variables)]
     fn open_files(&mut self, token: FileDialogToken, files: Vec<FileInfo>) {}
 
+    /// Called when a drag and drop operation started with [`WindowHandle::start_drag`]
+    /// has ended.
+    ///
+    /// `token` is the value returned by [`WindowHandle::start_drag`]. If `outcome` is
+    /// [`DragOutcome::Dropped`] with [`DragAction::Move`], the handler should remove
+    /// the dragged data.
+    ///
+    /// [`DragAction::Move`]: crate::DragAction::Move
+    #[allow(unused_variables)]
+    fn drag_finished(&mut self, token: DragToken, outcome: DragOutcome) {}
+
     /// Called on a key down event.
     ///
     /// Return `true` if the event is handled.
@@ -662,6 +815,14 @@ pub trait WinHandler {
     #[allow(unused_variables)]
     fn key_up(&mut self, event: &KeyEvent) {}
 
+    /// Called when the active keyboard layout changes, or the user's layouts are
+    /// reconfigured.
+    ///
+    /// The new layouts are available from [`WindowHandle::keyboard_layouts`]. The
+    /// keys of later [`KeyEvent`]s are in the new layout.
+    #[allow(unused_variables)]
+    fn keyboard_layout_changed(&mut self) {}
+
     /// Take a lock for the text document specified by `token`.
     ///
     /// All calls to this method must be balanced with a call to
@@ -696,6 +857,15 @@ pub trait WinHandler {
     #[allow(unused_variables)]
     fn zoom(&mut self, delta: f64) {}
 
+    /// Called when a touchpad gesture, such as a pinch or a multi-finger swipe, begins,
+    /// changes or ends.
+    ///
+    /// Pinches are also reported to [`zoom`](WinHandler::zoom).
+    ///
+    /// This is currently only delivered on X11 and Wayland.
+    #[allow(unused_variables)]
+    fn gesture(&mut self, event: &GestureEvent) {}
+
     /// Called on a mouse wheel event.
     ///
     /// The polarity is the amount to be added to the scroll position,
@@ -726,6 +896,38 @@ pub trait WinHandler {
     /// Called when a pointer has left the application window.
     fn pointer_leave(&mut self) {}
 
+    /// Called when a pen comes close enough to a tablet to be tracked, before its other events
+    /// in this window.
+    ///
+    /// The event's [`PenInfo::tool`](crate::PenInfo::tool) identifies the tool, for example to
+    /// pick its brush. This is currently only delivered on X11, where leaving proximity is only
+    /// reported by the Wacom driver.
+    #[allow(unused_variables)]
+    fn pen_proximity_in(&mut self, event: &PointerEvent) {}
+
+    /// Called when a pen which [came into proximity](WinHandler::pen_proximity_in) of a tablet
+    /// moves out of its range.
+    #[allow(unused_variables)]
+    fn pen_proximity_out(&mut self, event: &PointerEvent) {}
+
+    /// Called when the platform takes a pointer away from this window while it is down.
+    ///
+    /// This happens when, for example, the system recognizes a touch as a gesture of its own,
+    /// or another application grabs the pointer. No [`pointer_up`] will follow for the
+    /// pointer, so anything it was doing, such as a drag, should be abandoned. This is like
+    /// `pointercancel` in [Pointer Events].
+    ///
+    /// [`pointer_up`]: WinHandler::pointer_up
+    /// [Pointer Events]: https://w3c.github.io/pointerevents/#the-pointercancel-event
+    #[allow(unused_variables)]
+    fn pointer_cancel(&mut self, event: &PointerEvent) {}
+
+    /// Called when a pointer which is locked or confined to this window moves.
+    ///
+    /// See [`WindowHandle::set_pointer_constraint`].
+    #[allow(unused_variables)]
+    fn relative_pointer_motion(&mut self, event: &RelativeMotionEvent) {}
+
     /// Called on timer event.
     ///
     /// This is called at (approximately) the requested deadline by a